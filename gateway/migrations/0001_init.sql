@@ -0,0 +1,117 @@
+-- Portable between SQLite and PostgreSQL through the sqlx Any driver: timestamps
+-- are unix microseconds, flags are 0/1 and every integer column is BIGINT.
+
+CREATE TABLE users (
+    id TEXT PRIMARY KEY,
+    handle TEXT UNIQUE,
+    created_at BIGINT NOT NULL
+);
+
+CREATE TABLE wallet_links (
+    pubkey TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users (id),
+    chain TEXT NOT NULL,
+    is_primary BIGINT NOT NULL,
+    linked_at BIGINT NOT NULL
+);
+
+CREATE INDEX wallet_links_user_id ON wallet_links (user_id);
+
+CREATE TABLE auth_nonces (
+    nonce TEXT PRIMARY KEY,
+    wallet TEXT NOT NULL,
+    request_id TEXT NOT NULL,
+    link_user_id TEXT,
+    issued_at BIGINT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    -- Insertion order within a wallet's pool; issued_at only has second precision.
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX auth_nonces_wallet ON auth_nonces (wallet, created_at);
+
+CREATE TABLE token_families (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    wallet TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    current_hash TEXT NOT NULL,
+    -- JSON array of hashes already rotated out, used for reuse detection.
+    retired_hashes TEXT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    revoked_at BIGINT
+);
+
+CREATE INDEX token_families_user_id ON token_families (user_id);
+
+CREATE TABLE revoked_access_tokens (
+    token_id TEXT PRIMARY KEY,
+    expires_at BIGINT NOT NULL
+);
+
+CREATE TABLE handles (
+    handle TEXT PRIMARY KEY,
+    wallet TEXT NOT NULL
+);
+
+CREATE TABLE pin_profiles (
+    user_id TEXT PRIMARY KEY,
+    pin_hash TEXT NOT NULL,
+    failed_attempts BIGINT NOT NULL,
+    locked_until BIGINT
+);
+
+CREATE TABLE payment_sessions (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL UNIQUE,
+    wallet_pubkey TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    per_tx_limit DOUBLE PRECISION NOT NULL,
+    daily_limit DOUBLE PRECISION NOT NULL,
+    used_today DOUBLE PRECISION NOT NULL,
+    expires_at BIGINT NOT NULL,
+    status TEXT NOT NULL
+);
+
+CREATE TABLE payment_intents (
+    id TEXT PRIMARY KEY,
+    creator_user_id TEXT NOT NULL,
+    recipient_wallet TEXT NOT NULL,
+    token TEXT NOT NULL,
+    amount DOUBLE PRECISION NOT NULL,
+    token_amount DOUBLE PRECISION NOT NULL,
+    memo TEXT,
+    status TEXT NOT NULL,
+    mode TEXT,
+    signature TEXT,
+    ref TEXT NOT NULL,
+    quote_expires_at BIGINT NOT NULL
+);
+
+CREATE INDEX payment_intents_creator ON payment_intents (creator_user_id);
+
+CREATE TABLE payment_events (
+    id TEXT PRIMARY KEY,
+    payment_intent_id TEXT NOT NULL REFERENCES payment_intents (id),
+    signature TEXT,
+    status TEXT NOT NULL,
+    confirmed_at BIGINT
+);
+
+CREATE INDEX payment_events_intent ON payment_events (payment_intent_id);
+
+CREATE TABLE splits (
+    id TEXT PRIMARY KEY,
+    owner_user_id TEXT NOT NULL REFERENCES users (id),
+    token TEXT NOT NULL,
+    total_amount DOUBLE PRECISION NOT NULL,
+    status TEXT NOT NULL
+);
+
+CREATE TABLE split_members (
+    split_id TEXT NOT NULL REFERENCES splits (id),
+    user_id TEXT NOT NULL REFERENCES users (id),
+    share_amount DOUBLE PRECISION NOT NULL,
+    state TEXT NOT NULL,
+    PRIMARY KEY (split_id, user_id)
+);
@@ -0,0 +1,28 @@
+-- Amounts move from floating point to integers: INR as paise, tokens as mint base units.
+
+ALTER TABLE payment_sessions ADD COLUMN per_tx_limit_paise BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE payment_sessions ADD COLUMN daily_limit_paise BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE payment_sessions ADD COLUMN used_today_paise BIGINT NOT NULL DEFAULT 0;
+UPDATE payment_sessions SET
+    per_tx_limit_paise = CAST(ROUND(per_tx_limit * 100) AS BIGINT),
+    daily_limit_paise = CAST(ROUND(daily_limit * 100) AS BIGINT),
+    used_today_paise = CAST(ROUND(used_today * 100) AS BIGINT);
+ALTER TABLE payment_sessions DROP COLUMN per_tx_limit;
+ALTER TABLE payment_sessions DROP COLUMN daily_limit;
+ALTER TABLE payment_sessions DROP COLUMN used_today;
+
+ALTER TABLE payment_intents ADD COLUMN amount_paise BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE payment_intents ADD COLUMN token_base_units BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE payment_intents ADD COLUMN token_decimals BIGINT NOT NULL DEFAULT 6;
+UPDATE payment_intents SET token_decimals = 9 WHERE token = 'SOL';
+UPDATE payment_intents SET
+    amount_paise = CAST(ROUND(amount * 100) AS BIGINT),
+    token_base_units = CAST(ROUND(token_amount * CASE WHEN token = 'SOL' THEN 1000000000 ELSE 1000000 END) AS BIGINT);
+ALTER TABLE payment_intents DROP COLUMN amount;
+ALTER TABLE payment_intents DROP COLUMN token_amount;
+
+-- Split tables are not written yet, so their amounts are replaced outright.
+ALTER TABLE splits DROP COLUMN total_amount;
+ALTER TABLE splits ADD COLUMN total_base_units BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE split_members DROP COLUMN share_amount;
+ALTER TABLE split_members ADD COLUMN share_base_units BIGINT NOT NULL DEFAULT 0;
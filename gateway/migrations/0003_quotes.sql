@@ -0,0 +1,18 @@
+CREATE TABLE quotes (
+    id TEXT PRIMARY KEY,
+    token TEXT NOT NULL,
+    inr_paise BIGINT NOT NULL,
+    token_base_units BIGINT NOT NULL,
+    token_decimals BIGINT NOT NULL,
+    -- Decimal string; the scaled rate can outgrow BIGINT.
+    rate TEXT NOT NULL,
+    source TEXT NOT NULL,
+    as_of BIGINT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    max_slippage_bps BIGINT NOT NULL,
+    intent_id TEXT
+);
+
+CREATE INDEX quotes_expires_at ON quotes (expires_at);
+
+ALTER TABLE payment_intents ADD COLUMN quote_id TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,16 @@
+-- The original events table was never written to; replace it with the status history
+-- kept by the intent state machine. `seq` orders events that share a timestamp.
+DROP TABLE payment_events;
+
+CREATE TABLE payment_events (
+    id TEXT PRIMARY KEY,
+    payment_intent_id TEXT NOT NULL REFERENCES payment_intents (id),
+    seq BIGINT NOT NULL,
+    previous_status TEXT,
+    status TEXT NOT NULL,
+    reason TEXT,
+    signature TEXT,
+    created_at BIGINT NOT NULL
+);
+
+CREATE UNIQUE INDEX payment_events_intent_seq ON payment_events (payment_intent_id, seq);
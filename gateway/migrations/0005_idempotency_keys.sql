@@ -0,0 +1,12 @@
+CREATE TABLE idempotency_keys (
+    user_id TEXT NOT NULL,
+    idempotency_key TEXT NOT NULL,
+    request_hash TEXT NOT NULL,
+    -- NULL while the original request is still being handled.
+    response_status BIGINT,
+    response_body TEXT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    PRIMARY KEY (user_id, idempotency_key)
+);
+
+CREATE INDEX idempotency_keys_expires_at ON idempotency_keys (expires_at);
@@ -0,0 +1,2 @@
+-- The reconciler polls intents by status.
+CREATE INDEX payment_intents_status ON payment_intents (status);
@@ -0,0 +1,10 @@
+-- Signed indexer webhook deliveries, remembered for the replay window.
+CREATE TABLE webhook_deliveries (
+    id TEXT PRIMARY KEY,
+    expires_at BIGINT NOT NULL
+);
+
+CREATE INDEX webhook_deliveries_expires_at ON webhook_deliveries (expires_at);
+
+-- Webhook transactions are matched to intents by reference key.
+CREATE INDEX payment_intents_ref ON payment_intents (ref);
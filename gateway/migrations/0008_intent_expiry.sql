@@ -0,0 +1,2 @@
+-- Session whose daily spend an authorized intent reserved, released if it expires unpaid.
+ALTER TABLE payment_intents ADD COLUMN session_id TEXT;
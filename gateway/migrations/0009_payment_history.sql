@@ -0,0 +1,17 @@
+-- Payment history lists intents by time and matches counterparties by wallet.
+ALTER TABLE payment_intents ADD COLUMN payer_wallet TEXT NOT NULL DEFAULT '';
+ALTER TABLE payment_intents ADD COLUMN created_at BIGINT NOT NULL DEFAULT 0;
+
+-- Earlier intents did not record the paying wallet; the creator's primary wallet is the
+-- best guess. Their creation time is that of their first event.
+UPDATE payment_intents SET payer_wallet = COALESCE(
+    (SELECT pubkey FROM wallet_links
+     WHERE wallet_links.user_id = payment_intents.creator_user_id AND is_primary = 1),
+    '');
+UPDATE payment_intents SET created_at = COALESCE(
+    (SELECT MIN(created_at) FROM payment_events
+     WHERE payment_events.payment_intent_id = payment_intents.id),
+    quote_expires_at);
+
+CREATE INDEX payment_intents_creator_created ON payment_intents (creator_user_id, created_at);
+CREATE INDEX payment_intents_recipient_created ON payment_intents (recipient_wallet, created_at);
@@ -0,0 +1,10 @@
+-- Intents remember the handle they were addressed to, and handles can be looked up by
+-- wallet to name the other side of a payment.
+ALTER TABLE payment_intents ADD COLUMN recipient_handle TEXT;
+
+-- A handle never moves to another wallet, so any handle of the recipient wallet is one the
+-- payer could have used.
+UPDATE payment_intents SET recipient_handle =
+    (SELECT MIN(handle) FROM handles WHERE handles.wallet = payment_intents.recipient_wallet);
+
+CREATE INDEX handles_wallet ON handles (wallet);
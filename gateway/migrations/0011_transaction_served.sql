@@ -0,0 +1,2 @@
+-- When a Solana Pay transaction request last served a transaction for the intent.
+ALTER TABLE payment_intents ADD COLUMN transaction_served_at BIGINT;
@@ -1,38 +1,107 @@
+use crate::idempotency;
 use crate::routes;
 use crate::state::AppState;
-use axum::routing::{get, post};
+use axum::middleware;
+use axum::routing::{delete, get, post};
 use axum::Router;
 use tower_http::trace::TraceLayer;
 
 pub fn build_router(state: AppState) -> Router {
+    let idempotent = || middleware::from_fn_with_state(state.clone(), idempotency::idempotency);
     let api = Router::new()
         .route("/auth/nonce", post(routes::auth::create_nonce))
         .route("/auth/verify", post(routes::auth::verify_auth))
+        .route("/auth/refresh", post(routes::auth::refresh_tokens))
+        .route("/auth/logout", post(routes::auth::logout))
+        .route("/wallets", get(routes::wallets::list_wallets))
+        .route(
+            "/wallets/link/challenge",
+            post(routes::wallets::create_link_challenge),
+        )
+        .route("/wallets/link", post(routes::wallets::link_wallet))
+        .route("/wallets/:wallet", delete(routes::wallets::unlink_wallet))
+        .route(
+            "/wallets/:wallet/primary",
+            post(routes::wallets::set_primary_wallet),
+        )
         .route("/pin/enroll", post(routes::pin::enroll_pin))
         .route("/pin/verify", post(routes::pin::verify_pin))
         .route(
             "/sessions",
-            post(routes::sessions::create_or_refresh_session),
+            post(routes::sessions::create_or_refresh_session).route_layer(idempotent()),
         )
         .route(
             "/sessions/current",
             get(routes::sessions::get_current_session),
         )
-        .route("/handles", post(routes::handles::upsert_handle))
+        .route(
+            "/handles",
+            post(routes::handles::upsert_handle).route_layer(idempotent()),
+        )
         .route("/handles/:handle", get(routes::handles::resolve_handle))
-        .route("/quotes/usdc", get(routes::quotes::get_usdc_quote))
+        .route(
+            "/handles/:handle/solana-pay",
+            get(routes::solana_pay::get_handle_solana_pay),
+        )
+        .route(
+            "/handles/:handle/qr",
+            get(routes::solana_pay::get_handle_qr),
+        )
+        .route(
+            "/solana-pay/parse",
+            post(routes::solana_pay::parse_solana_pay),
+        )
+        .route("/tokens", get(routes::tokens::list_tokens))
+        .route("/quotes", post(routes::quotes::create_quote))
+        .route("/quotes/:token", get(routes::quotes::preview_quote))
         .route(
             "/payment-intents",
-            post(routes::payment_intents::create_payment_intent),
+            post(routes::payment_intents::create_payment_intent)
+                .route_layer(idempotent())
+                .get(routes::payment_intents::list_payment_intents),
         )
         .route(
             "/payment-intents/:id/execute",
-            post(routes::payment_intents::execute_payment_intent),
+            post(routes::payment_intents::execute_payment_intent).route_layer(idempotent()),
+        )
+        .route(
+            "/payment-intents/:id/cancel",
+            post(routes::payment_intents::cancel_payment_intent).route_layer(idempotent()),
         )
         .route(
             "/payment-intents/:id",
             get(routes::payment_intents::get_payment_intent),
-        );
+        )
+        .route(
+            "/payment-intents/:id/receipt",
+            get(routes::payment_intents::get_payment_receipt),
+        )
+        .route(
+            "/payment-intents/:id/events",
+            get(routes::payment_events::stream_payment_events),
+        )
+        .route(
+            "/payment-intents/:id/events/token",
+            post(routes::payment_events::create_events_token),
+        )
+        .route(
+            "/payment-intents/:id/ws",
+            get(routes::payment_events::payment_events_socket),
+        )
+        .route(
+            "/payment-intents/:id/transaction",
+            get(routes::solana_pay::transaction_metadata)
+                .post(routes::solana_pay::create_transaction),
+        )
+        .route(
+            "/payment-intents/:id/solana-pay",
+            get(routes::solana_pay::get_intent_solana_pay),
+        )
+        .route(
+            "/payment-intents/:id/qr",
+            get(routes::solana_pay::get_intent_qr),
+        )
+        .route("/webhooks/solana/tx", post(routes::webhooks::solana_tx));
 
     Router::new()
         .route("/health", get(routes::health::health))
@@ -1,7 +1,105 @@
+use rand_core::{OsRng, RngCore};
+use tracing::warn;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cluster {
+    Devnet,
+    Testnet,
+    Mainnet,
+}
+
+impl Cluster {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "devnet" => Some(Self::Devnet),
+            "testnet" => Some(Self::Testnet),
+            "mainnet" | "mainnet-beta" => Some(Self::Mainnet),
+            _ => None,
+        }
+    }
+
+    /// Chain id as used in Sign-In-With-Solana messages.
+    pub fn chain_id(self) -> &'static str {
+        match self {
+            Self::Devnet => "devnet",
+            Self::Testnet => "testnet",
+            Self::Mainnet => "mainnet",
+        }
+    }
+
+    pub fn default_rpc_url(self) -> &'static str {
+        match self {
+            Self::Devnet => "https://api.devnet.solana.com",
+            Self::Testnet => "https://api.testnet.solana.com",
+            Self::Mainnet => "https://api.mainnet-beta.solana.com",
+        }
+    }
+
+    /// Solscan link for `path` (e.g. `tx/<signature>`) on this cluster.
+    pub fn explorer_url(self, path: &str) -> String {
+        match self {
+            Self::Mainnet => format!("https://solscan.io/{path}"),
+            other => format!("https://solscan.io/{path}?cluster={}", other.chain_id()),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Config {
     pub host: String,
     pub port: u16,
+    pub cluster: Cluster,
+    pub solana_rpc_url: String,
+    /// JSON ledger served by the mock RPC instead of a real node; see `solana::MockRpc`.
+    pub solana_mock_ledger: Option<String>,
+    pub reconcile_interval_secs: u64,
+    /// How long a submitted signature may stay unknown to the node before the intent fails.
+    pub reconcile_drop_after_secs: i64,
+    /// Externally reachable base URL of this gateway, used in links handed to wallets.
+    pub public_url: String,
+    pub solana_pay_label: String,
+    pub solana_pay_icon: String,
+    /// `sqlite:` or `postgres:` URL; the in-memory store is used when unset.
+    pub database_url: Option<String>,
+    /// JSON token registry; the bundled `tokens.json` is used when unset.
+    pub token_registry_path: Option<String>,
+    pub auth_domain: String,
+    pub auth_uri: String,
+    pub auth_statement: String,
+    pub auth_sweep_interval_secs: u64,
+    pub access_token_secret: String,
+    pub access_token_ttl_minutes: i64,
+    pub refresh_token_ttl_days: i64,
+    pub dev_header_auth: bool,
+    /// Price sources fed to the median aggregator: any of `fixed`, `http`, `file`.
+    pub oracle_sources: Vec<String>,
+    pub oracle_fixed_rates: String,
+    pub oracle_http_url: Option<String>,
+    pub oracle_http_pointer: String,
+    pub oracle_file_path: Option<String>,
+    pub oracle_max_age_secs: i64,
+    pub oracle_max_deviation_bps: u32,
+    pub oracle_min_sources: usize,
+    pub quote_lock_secs: i64,
+    /// Default and upper bound for the slippage a quote tolerates, in basis points.
+    pub quote_max_slippage_bps: u32,
+    /// How long a response is replayed for a repeated `Idempotency-Key`.
+    pub idempotency_ttl_hours: i64,
+    /// Shared secret indexer webhooks are signed with; the webhook is disabled when unset.
+    pub indexer_webhook_secret: Option<String>,
+    /// How far a webhook timestamp may be from now, and how long deliveries are remembered.
+    pub webhook_tolerance_secs: i64,
+    /// Idle interval after which event streams send a heartbeat.
+    pub events_heartbeat_secs: u64,
+    pub intent_sweep_interval_secs: u64,
+    /// How long after its quote expires an unpaid intent is still left open.
+    pub intent_expiry_grace_secs: i64,
+    /// How long intents are kept after reaching a terminal status.
+    pub intent_retention_hours: i64,
+    /// Reject memos with personal details or words from `memo_blocklist`.
+    pub memo_filter: bool,
+    /// Lowercase words memos may not contain when `memo_filter` is on.
+    pub memo_blocklist: Vec<String>,
 }
 
 impl Config {
@@ -11,7 +109,201 @@ impl Config {
             .ok()
             .and_then(|v| v.parse::<u16>().ok())
             .unwrap_or(8080);
+        let cluster = std::env::var("MONOPAY_CLUSTER")
+            .ok()
+            .and_then(|v| Cluster::parse(&v))
+            .unwrap_or(Cluster::Devnet);
+        let solana_rpc_url = std::env::var("MONOPAY_SOLANA_RPC_URL")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| cluster.default_rpc_url().to_string());
+        let solana_mock_ledger = std::env::var("MONOPAY_SOLANA_MOCK_LEDGER")
+            .ok()
+            .filter(|v| !v.is_empty());
+        let reconcile_interval_secs = std::env::var("MONOPAY_RECONCILE_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(5);
+        let reconcile_drop_after_secs = std::env::var("MONOPAY_RECONCILE_DROP_AFTER_SECS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(180);
+        let public_url = std::env::var("MONOPAY_PUBLIC_URL")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| format!("http://localhost:{port}"))
+            .trim_end_matches('/')
+            .to_string();
+        let database_url = std::env::var("MONOPAY_DATABASE_URL")
+            .ok()
+            .filter(|v| !v.is_empty());
+        let token_registry_path = std::env::var("MONOPAY_TOKEN_REGISTRY")
+            .ok()
+            .filter(|v| !v.is_empty());
+        let auth_domain =
+            std::env::var("MONOPAY_AUTH_DOMAIN").unwrap_or_else(|_| "monopay.app".to_string());
+        let auth_uri =
+            std::env::var("MONOPAY_AUTH_URI").unwrap_or_else(|_| format!("https://{auth_domain}"));
+        let solana_pay_label =
+            std::env::var("MONOPAY_SOLANA_PAY_LABEL").unwrap_or_else(|_| "monopay".to_string());
+        let solana_pay_icon = std::env::var("MONOPAY_SOLANA_PAY_ICON")
+            .unwrap_or_else(|_| format!("{auth_uri}/icon.png"));
+        let auth_statement = std::env::var("MONOPAY_AUTH_STATEMENT").unwrap_or_else(|_| {
+            "Sign in to monopay to authorize payments from this wallet.".to_string()
+        });
+        let auth_sweep_interval_secs = std::env::var("MONOPAY_AUTH_SWEEP_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(60);
+        let access_token_secret = std::env::var("MONOPAY_ACCESS_TOKEN_SECRET")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| {
+                warn!(
+                    "MONOPAY_ACCESS_TOKEN_SECRET not set, access tokens will not survive a restart"
+                );
+                let mut bytes = [0u8; 32];
+                OsRng.fill_bytes(&mut bytes);
+                bs58::encode(bytes).into_string()
+            });
+        let access_token_ttl_minutes = std::env::var("MONOPAY_ACCESS_TOKEN_TTL_MINUTES")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(15);
+        let refresh_token_ttl_days = std::env::var("MONOPAY_REFRESH_TOKEN_TTL_DAYS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(30);
+        let dev_header_auth = std::env::var("MONOPAY_DEV_HEADER_AUTH")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        let oracle_sources = std::env::var("MONOPAY_ORACLE_SOURCES")
+            .unwrap_or_else(|_| "fixed".to_string())
+            .split(',')
+            .map(|v| v.trim().to_lowercase())
+            .filter(|v| !v.is_empty())
+            .collect();
+        let oracle_fixed_rates = std::env::var("MONOPAY_ORACLE_FIXED_RATES")
+            .unwrap_or_else(|_| "USDC=83.61,SOL=12500".to_string());
+        let oracle_http_url = std::env::var("MONOPAY_ORACLE_HTTP_URL")
+            .ok()
+            .filter(|v| !v.is_empty());
+        let oracle_http_pointer =
+            std::env::var("MONOPAY_ORACLE_HTTP_POINTER").unwrap_or_else(|_| "/price".to_string());
+        let oracle_file_path = std::env::var("MONOPAY_ORACLE_FILE")
+            .ok()
+            .filter(|v| !v.is_empty());
+        let oracle_max_age_secs = std::env::var("MONOPAY_ORACLE_MAX_AGE_SECS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(120);
+        let oracle_max_deviation_bps = std::env::var("MONOPAY_ORACLE_MAX_DEVIATION_BPS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(200);
+        let oracle_min_sources = std::env::var("MONOPAY_ORACLE_MIN_SOURCES")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(1);
+        let quote_lock_secs = std::env::var("MONOPAY_QUOTE_LOCK_SECS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(30);
+        let quote_max_slippage_bps = std::env::var("MONOPAY_QUOTE_MAX_SLIPPAGE_BPS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(50);
+        let idempotency_ttl_hours = std::env::var("MONOPAY_IDEMPOTENCY_TTL_HOURS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(24);
+        let indexer_webhook_secret = std::env::var("INDEXER_WEBHOOK_SECRET")
+            .ok()
+            .filter(|v| !v.is_empty());
+        let webhook_tolerance_secs = std::env::var("MONOPAY_WEBHOOK_TOLERANCE_SECS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(300);
+        let events_heartbeat_secs = std::env::var("MONOPAY_EVENTS_HEARTBEAT_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(15);
+        let intent_sweep_interval_secs = std::env::var("MONOPAY_INTENT_SWEEP_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(30);
+        let intent_expiry_grace_secs = std::env::var("MONOPAY_INTENT_EXPIRY_GRACE_SECS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|v| *v >= 0)
+            .unwrap_or(120);
+        let intent_retention_hours = std::env::var("MONOPAY_INTENT_RETENTION_HOURS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(72);
+        let memo_filter = std::env::var("MONOPAY_MEMO_FILTER")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        let memo_blocklist = std::env::var("MONOPAY_MEMO_BLOCKLIST")
+            .unwrap_or_default()
+            .split(',')
+            .map(|v| v.trim().to_lowercase())
+            .filter(|v| !v.is_empty())
+            .collect();
 
-        Self { host, port }
+        Self {
+            host,
+            port,
+            cluster,
+            solana_rpc_url,
+            solana_mock_ledger,
+            reconcile_interval_secs,
+            reconcile_drop_after_secs,
+            public_url,
+            solana_pay_label,
+            solana_pay_icon,
+            database_url,
+            token_registry_path,
+            auth_domain,
+            auth_uri,
+            auth_statement,
+            auth_sweep_interval_secs,
+            access_token_secret,
+            access_token_ttl_minutes,
+            refresh_token_ttl_days,
+            dev_header_auth,
+            oracle_sources,
+            oracle_fixed_rates,
+            oracle_http_url,
+            oracle_http_pointer,
+            oracle_file_path,
+            oracle_max_age_secs,
+            oracle_max_deviation_bps,
+            oracle_min_sources,
+            quote_lock_secs,
+            quote_max_slippage_bps,
+            idempotency_ttl_hours,
+            indexer_webhook_secret,
+            webhook_tolerance_secs,
+            events_heartbeat_secs,
+            intent_sweep_interval_secs,
+            intent_expiry_grace_secs,
+            intent_retention_hours,
+            memo_filter,
+            memo_blocklist,
+        }
     }
 }
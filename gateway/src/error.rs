@@ -9,6 +9,11 @@ pub enum AppError {
     Unauthorized(String),
     NotFound(String),
     Internal(String),
+    Coded {
+        status: StatusCode,
+        code: &'static str,
+        message: String,
+    },
 }
 
 #[derive(Serialize)]
@@ -38,6 +43,14 @@ impl AppError {
     pub fn internal(message: impl Into<String>) -> Self {
         Self::Internal(message.into())
     }
+
+    pub fn coded(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self::Coded {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
 }
 
 impl IntoResponse for AppError {
@@ -47,6 +60,11 @@ impl IntoResponse for AppError {
             AppError::Unauthorized(message) => (StatusCode::UNAUTHORIZED, "UNAUTHORIZED", message),
             AppError::NotFound(message) => (StatusCode::NOT_FOUND, "NOT_FOUND", message),
             AppError::Internal(message) => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL", message),
+            AppError::Coded {
+                status,
+                code,
+                message,
+            } => (status, code, message),
         };
 
         (
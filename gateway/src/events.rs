@@ -0,0 +1,123 @@
+use crate::error::AppError;
+use crate::state::{AppState, PaymentEventRecord, PaymentIntentRecord};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::debug;
+
+/// Intent changes buffered per subscriber before it lags and re-reads the store.
+const BUS_CAPACITY: usize = 1024;
+
+/// In-process fan-out of payment intent changes to live subscribers.
+///
+/// Whoever writes an intent publishes the stored record; subscribers pick the events they
+/// have not seen from its history, so out-of-order or repeated publishes are harmless.
+#[derive(Clone)]
+pub struct IntentEvents {
+    sender: broadcast::Sender<Arc<PaymentIntentRecord>>,
+}
+
+impl Default for IntentEvents {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(BUS_CAPACITY).0,
+        }
+    }
+}
+
+impl IntentEvents {
+    pub fn publish(&self, intent: &PaymentIntentRecord) {
+        // Nobody listening is fine.
+        let _ = self.sender.send(Arc::new(intent.clone()));
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<Arc<PaymentIntentRecord>> {
+        self.sender.subscribe()
+    }
+}
+
+/// One subscriber's view of an intent's history: every event after `last_event_id`, then
+/// each new one as it is published, until the intent reaches a terminal status.
+pub struct IntentFeed {
+    state: AppState,
+    intent_id: String,
+    receiver: broadcast::Receiver<Arc<PaymentIntentRecord>>,
+    /// How many history entries have been queued so far; history is append-only.
+    seen: usize,
+    pending: VecDeque<PaymentEventRecord>,
+    terminal: bool,
+    /// Set when publishes were missed; the store is re-read before waiting again.
+    stale: bool,
+}
+
+impl IntentFeed {
+    /// Starts after the event `last_event_id`, or from the beginning when it is absent or
+    /// not part of the intent's history.
+    pub async fn open(
+        state: &AppState,
+        intent_id: &str,
+        last_event_id: Option<&str>,
+    ) -> Result<Self, AppError> {
+        // Subscribe before reading so nothing published in between is missed.
+        let receiver = state.events.subscribe();
+        let intent = state
+            .store
+            .payment_intent(intent_id)
+            .await?
+            .ok_or_else(|| AppError::not_found("payment intent not found"))?;
+        let seen = last_event_id
+            .and_then(|last| intent.events.iter().position(|event| event.id == last))
+            .map_or(0, |index| index + 1);
+        let mut feed = Self {
+            state: state.clone(),
+            intent_id: intent_id.to_string(),
+            receiver,
+            seen,
+            pending: VecDeque::new(),
+            terminal: false,
+            stale: false,
+        };
+        feed.catch_up(&intent);
+        Ok(feed)
+    }
+
+    fn catch_up(&mut self, intent: &PaymentIntentRecord) {
+        // A snapshot older than what was already queued says nothing new.
+        let Some(new) = intent.events.get(self.seen..) else {
+            return;
+        };
+        self.pending.extend(new.iter().cloned());
+        self.seen = intent.events.len();
+        self.terminal = intent.status.is_terminal();
+    }
+
+    /// The next event, waiting for one if needed; `None` once the intent is terminal and
+    /// everything was delivered. Safe to cancel, e.g. in `select!`.
+    pub async fn next(&mut self) -> Result<Option<PaymentEventRecord>, AppError> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(Some(event));
+            }
+            if self.terminal {
+                return Ok(None);
+            }
+            if self.stale {
+                match self.state.store.payment_intent(&self.intent_id).await? {
+                    Some(intent) => self.catch_up(&intent),
+                    None => return Ok(None),
+                }
+                self.stale = false;
+                continue;
+            }
+            match self.receiver.recv().await {
+                Ok(intent) if intent.id == self.intent_id => self.catch_up(&intent),
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!("event feed for {} lagged by {skipped}", self.intent_id);
+                    self.stale = true;
+                }
+                Err(RecvError::Closed) => return Ok(None),
+            }
+        }
+    }
+}
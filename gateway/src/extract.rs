@@ -0,0 +1,178 @@
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::tokens;
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+
+/// Caller identity resolved from a `Authorization: Bearer` access token.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub user_id: String,
+    pub wallet: String,
+    /// Access token id and refresh family; `None` for dev header auth.
+    pub token_id: Option<String>,
+    pub family_id: Option<String>,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, AppError> {
+        if let Some(value) = parts.headers.get(AUTHORIZATION) {
+            let token = value
+                .to_str()
+                .ok()
+                .and_then(|v| v.strip_prefix("Bearer "))
+                .map(str::trim)
+                .ok_or_else(|| AppError::unauthorized("authorization must be a bearer token"))?;
+            let claims =
+                tokens::decode_access_token(state.config.access_token_secret.as_bytes(), token)?;
+            if state
+                .store
+                .is_access_token_revoked(&claims.jti, &claims.fam)
+                .await?
+            {
+                return Err(AppError::unauthorized("access token revoked"));
+            }
+            return Ok(Self {
+                user_id: claims.sub,
+                wallet: claims.wallet,
+                token_id: Some(claims.jti),
+                family_id: Some(claims.fam),
+            });
+        }
+
+        // Dev-only escape hatch so local tooling can impersonate users without signing.
+        if state.config.dev_header_auth {
+            if let Some(user_id) = parts.headers.get("x-user-id").and_then(|v| v.to_str().ok()) {
+                let wallet = state
+                    .store
+                    .user_wallets(user_id)
+                    .await?
+                    .into_iter()
+                    .find(|link| link.is_primary)
+                    .map(|link| link.pubkey)
+                    .unwrap_or_default();
+                return Ok(Self {
+                    user_id: user_id.to_string(),
+                    wallet,
+                    token_id: None,
+                    family_id: None,
+                });
+            }
+        }
+
+        Err(AppError::unauthorized("bearer access token is required"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{access_token, state};
+    use axum::http::Request;
+    use chrono::Utc;
+
+    async fn extract(state: &AppState, headers: &[(&str, &str)]) -> Result<AuthUser, AppError> {
+        let mut request = Request::builder();
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+        AuthUser::from_request_parts(&mut parts, state).await
+    }
+
+    fn rejection(result: Result<AuthUser, AppError>) -> String {
+        match result {
+            Err(AppError::Unauthorized(message)) => message,
+            other => panic!("expected a rejection, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_bearer_token_resolves_the_signed_in_user() {
+        let state = state();
+        let token = access_token(&state, "usr_1", "wallet_1").await;
+        let user = extract(&state, &[("authorization", &format!("Bearer {token}"))])
+            .await
+            .unwrap();
+        assert_eq!(user.user_id, "usr_1");
+        assert_eq!(user.wallet, "wallet_1");
+        assert!(user.token_id.is_some());
+        assert!(user.family_id.is_some());
+    }
+
+    #[tokio::test]
+    async fn tokens_of_a_revoked_family_are_rejected() {
+        let state = state();
+        let token = access_token(&state, "usr_1", "wallet_1").await;
+        let bearer = format!("Bearer {token}");
+        let family_id = extract(&state, &[("authorization", &bearer)])
+            .await
+            .unwrap()
+            .family_id;
+        state
+            .store
+            .revoke_token_families("usr_1", family_id.as_deref(), None, Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(
+            rejection(extract(&state, &[("authorization", &bearer)]).await),
+            "access token revoked"
+        );
+    }
+
+    #[tokio::test]
+    async fn tokens_signed_with_another_secret_are_rejected() {
+        let mut other = state();
+        other.config.access_token_secret = "another-secret".to_string();
+        let token = access_token(&other, "usr_1", "wallet_1").await;
+        assert_eq!(
+            rejection(extract(&state(), &[("authorization", &format!("Bearer {token}"))]).await),
+            "invalid access token"
+        );
+    }
+
+    #[tokio::test]
+    async fn other_authorization_schemes_are_rejected() {
+        let state = state();
+        assert_eq!(
+            rejection(extract(&state, &[("authorization", "Basic dXNyXzE6")]).await),
+            "authorization must be a bearer token"
+        );
+        // A bad bearer token does not fall back to the dev header.
+        assert_eq!(
+            rejection(
+                extract(
+                    &state,
+                    &[("authorization", "Bearer nope"), ("x-user-id", "usr_1")]
+                )
+                .await
+            ),
+            "invalid access token"
+        );
+    }
+
+    #[tokio::test]
+    async fn the_dev_header_is_honored_only_when_enabled() {
+        let mut state = state();
+        state
+            .store
+            .link_wallet("usr_1", "wallet_1", Utc::now())
+            .await
+            .unwrap();
+        let user = extract(&state, &[("x-user-id", "usr_1")]).await.unwrap();
+        assert_eq!(user.user_id, "usr_1");
+        assert_eq!(user.wallet, "wallet_1");
+        assert_eq!(user.token_id, None);
+
+        state.config.dev_header_auth = false;
+        assert_eq!(
+            rejection(extract(&state, &[("x-user-id", "usr_1")]).await),
+            "bearer access token is required"
+        );
+    }
+}
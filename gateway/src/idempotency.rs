@@ -0,0 +1,258 @@
+use crate::error::AppError;
+use crate::extract::AuthUser;
+use crate::state::{AppState, IdempotencyRecord};
+use axum::body::{to_bytes, Body};
+use axum::extract::{FromRequestParts, Request, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+/// Set on responses replayed from an earlier request with the same key.
+pub const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+
+const MAX_KEY_LEN: usize = 255;
+const MAX_BODY_BYTES: usize = 64 * 1024;
+/// How long a key stays reserved without a response, so a request dropped mid-flight frees it.
+const IN_FLIGHT_LEASE_SECS: i64 = 60;
+
+fn key_conflict(code: &'static str, message: &str) -> AppError {
+    AppError::coded(StatusCode::CONFLICT, code, message)
+}
+
+fn request_hash(method: &str, path: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(path.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(body);
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn replay(record: IdempotencyRecord) -> Response {
+    let status = record
+        .response_status
+        .and_then(|status| StatusCode::from_u16(status).ok())
+        .unwrap_or(StatusCode::OK);
+    let mut response = (status, record.response_body).into_response();
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    response
+}
+
+/// Honors an `Idempotency-Key` header: the first request under a key runs and its response is
+/// stored; retries with the same body replay that response, and a different body is a conflict.
+///
+/// Keys are scoped to the caller. Server errors are not stored so the request can be retried.
+pub async fn idempotency(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY) else {
+        return Ok(next.run(request).await);
+    };
+    let key = key
+        .to_str()
+        .ok()
+        .map(str::trim)
+        .filter(|key| !key.is_empty() && key.len() <= MAX_KEY_LEN)
+        .ok_or_else(|| {
+            AppError::bad_request(format!(
+                "Idempotency-Key must be 1 to {MAX_KEY_LEN} visible ASCII characters"
+            ))
+        })?
+        .to_string();
+
+    let (mut parts, body) = request.into_parts();
+    let user = AuthUser::from_request_parts(&mut parts, &state).await?;
+    let body = to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|_| AppError::bad_request("request body is too large"))?;
+    let hash = request_hash(parts.method.as_str(), parts.uri.path(), &body);
+
+    let reservation = IdempotencyRecord {
+        user_id: user.user_id.clone(),
+        key: key.clone(),
+        request_hash: hash.clone(),
+        response_status: None,
+        response_body: String::new(),
+        expires_at: Utc::now() + Duration::seconds(IN_FLIGHT_LEASE_SECS),
+    };
+    if let Some(existing) = state.store.reserve_idempotency_key(reservation).await? {
+        if existing.request_hash != hash {
+            return Err(key_conflict(
+                "IDEMPOTENCY_KEY_REUSED",
+                "Idempotency-Key was already used for a different request",
+            ));
+        }
+        if existing.response_status.is_none() {
+            return Err(key_conflict(
+                "IDEMPOTENCY_KEY_IN_USE",
+                "a request with this Idempotency-Key is still in progress",
+            ));
+        }
+        return Ok(replay(existing));
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    let status = response.status();
+    if status.is_server_error() {
+        state
+            .store
+            .release_idempotency_key(&user.user_id, &key)
+            .await?;
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(_) => {
+            state
+                .store
+                .release_idempotency_key(&user.user_id, &key)
+                .await?;
+            return Err(AppError::internal("failed to read response"));
+        }
+    };
+    state
+        .store
+        .complete_idempotency_key(
+            &user.user_id,
+            &key,
+            status.as_u16(),
+            String::from_utf8_lossy(&body).into_owned(),
+            Utc::now() + Duration::hours(state.config.idempotency_ttl_hours),
+        )
+        .await?;
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Call};
+    use serde_json::json;
+
+    async fn state_with_wallet() -> AppState {
+        let state = testing::state();
+        state
+            .store
+            .link_wallet("alice", "alice_wallet", Utc::now())
+            .await
+            .unwrap();
+        state
+    }
+
+    fn claim(handle: &str, key: &str) -> Call {
+        Call::post(
+            "/v1/handles",
+            json!({ "handle": handle, "wallet": "alice_wallet" }),
+        )
+        .user("alice")
+        .header(IDEMPOTENCY_KEY, key)
+    }
+
+    #[tokio::test]
+    async fn retry_replays_the_stored_response() {
+        let state = state_with_wallet().await;
+        let (status, headers, first) = claim("priya", "key-1").send(&state).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(headers.get(IDEMPOTENT_REPLAYED).is_none());
+        assert_eq!(first["handle"], "priya@monopay.app");
+
+        let (status, headers, replayed) = claim("priya", "key-1").send(&state).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[IDEMPOTENT_REPLAYED], "true");
+        assert_eq!(replayed, first);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_replayed_too() {
+        let state = state_with_wallet().await;
+        let (status, _, first) = claim("x", "key-1").send(&state).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, headers, replayed) = claim("x", "key-1").send(&state).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(headers[IDEMPOTENT_REPLAYED], "true");
+        assert_eq!(replayed, first);
+    }
+
+    #[tokio::test]
+    async fn different_body_under_the_same_key_conflicts() {
+        let state = state_with_wallet().await;
+        let (status, _, _) = claim("priya", "key-1").send(&state).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _, body) = claim("priyanka", "key-1").send(&state).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "IDEMPOTENCY_KEY_REUSED");
+        assert_eq!(
+            state
+                .store
+                .handle_wallet("priyanka@monopay.app")
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn keys_are_scoped_to_the_caller() {
+        let state = state_with_wallet().await;
+        state
+            .store
+            .link_wallet("bob", "bob_wallet", Utc::now())
+            .await
+            .unwrap();
+        let (status, _, _) = claim("priya", "key-1").send(&state).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, headers, body) = Call::post(
+            "/v1/handles",
+            json!({ "handle": "bobby", "wallet": "bob_wallet" }),
+        )
+        .user("bob")
+        .header(IDEMPOTENCY_KEY, "key-1")
+        .send(&state)
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(headers.get(IDEMPOTENT_REPLAYED).is_none());
+        assert_eq!(body["handle"], "bobby@monopay.app");
+    }
+
+    #[tokio::test]
+    async fn keys_need_a_signed_in_caller() {
+        let state = state_with_wallet().await;
+        let (status, _, body) = Call::post(
+            "/v1/handles",
+            json!({ "handle": "priya", "wallet": "alice_wallet" }),
+        )
+        .header(IDEMPOTENCY_KEY, "key-1")
+        .send(&state)
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"]["code"], "UNAUTHORIZED");
+    }
+
+    #[tokio::test]
+    async fn malformed_keys_are_rejected() {
+        let state = state_with_wallet().await;
+        let (status, _, _) = claim("priya", &"k".repeat(MAX_KEY_LEN + 1))
+            .send(&state)
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _, _) = claim("priya", " ").send(&state).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::error::AppError;
+use crate::state::{PaymentEventRecord, PaymentIntentRecord};
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
+use uuid::Uuid;
+
+/// Where a payment intent is in its life:
+///
+/// ```text
+/// created -> authorized -> submitted -> confirmed -> finalized
+///    |           |              \-----> failed
+///    \-----------+--> expired | cancelled
+/// ```
+///
+/// A created intent paid straight from its Solana Pay link skips `authorized`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IntentStatus {
+    Created,
+    /// PIN or session checks passed; the payer may now sign.
+    Authorized,
+    /// A transaction carrying the intent's reference has been sent.
+    Submitted,
+    /// The transfer landed at `confirmed` commitment and matches the intent.
+    Confirmed,
+    /// The transfer is rooted and can no longer be rolled back.
+    Finalized,
+    Failed,
+    Expired,
+    Cancelled,
+}
+
+impl IntentStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Authorized => "authorized",
+            Self::Submitted => "submitted",
+            Self::Confirmed => "confirmed",
+            Self::Finalized => "finalized",
+            Self::Failed => "failed",
+            Self::Expired => "expired",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "created" => Some(Self::Created),
+            "authorized" => Some(Self::Authorized),
+            "submitted" => Some(Self::Submitted),
+            "confirmed" => Some(Self::Confirmed),
+            "finalized" => Some(Self::Finalized),
+            "failed" => Some(Self::Failed),
+            "expired" => Some(Self::Expired),
+            "cancelled" => Some(Self::Cancelled),
+            _ => None,
+        }
+    }
+
+    /// Statuses no transition leads out of.
+    pub const TERMINAL: [Self; 4] = [
+        Self::Finalized,
+        Self::Failed,
+        Self::Expired,
+        Self::Cancelled,
+    ];
+
+    pub fn is_terminal(self) -> bool {
+        Self::TERMINAL.contains(&self)
+    }
+
+    pub fn can_become(self, next: Self) -> bool {
+        use IntentStatus::*;
+        matches!(
+            (self, next),
+            (Created, Authorized | Submitted | Expired | Cancelled)
+                | (Authorized, Submitted | Expired | Cancelled)
+                | (Submitted, Confirmed | Failed)
+                | (Confirmed, Finalized)
+        )
+    }
+}
+
+impl fmt::Display for IntentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+pub fn invalid_transition(from: IntentStatus, to: IntentStatus) -> AppError {
+    AppError::coded(
+        StatusCode::CONFLICT,
+        "INVALID_TRANSITION",
+        format!("payment intent is {from} and cannot become {to}"),
+    )
+}
+
+fn event(
+    previous: Option<IntentStatus>,
+    status: IntentStatus,
+    reason: Option<String>,
+    signature: Option<String>,
+    at: DateTime<Utc>,
+) -> PaymentEventRecord {
+    PaymentEventRecord {
+        id: format!("pe_{}", Uuid::new_v4().simple()),
+        previous_status: previous,
+        status,
+        reason,
+        signature,
+        at,
+    }
+}
+
+/// History entry for a newly created intent.
+pub fn created_event(at: DateTime<Utc>) -> PaymentEventRecord {
+    event(None, IntentStatus::Created, None, None, at)
+}
+
+/// Whether the intent ended without paying anything: expired, cancelled, or failed before a
+/// transaction was ever sent for it. Only such intents are purged after the retention.
+pub fn closed_unpaid(intent: &PaymentIntentRecord) -> bool {
+    match intent.status {
+        IntentStatus::Expired | IntentStatus::Cancelled => true,
+        IntentStatus::Failed => intent.signature.is_none(),
+        _ => false,
+    }
+}
+
+/// Moves `intent` to `next` and appends the event; every status change goes through here.
+pub fn transition(
+    intent: &mut PaymentIntentRecord,
+    next: IntentStatus,
+    reason: Option<String>,
+    at: DateTime<Utc>,
+) -> Result<(), AppError> {
+    if !intent.status.can_become(next) {
+        return Err(invalid_transition(intent.status, next));
+    }
+    intent.events.push(event(
+        Some(intent.status),
+        next,
+        reason,
+        intent.signature.clone(),
+        at,
+    ));
+    intent.status = next;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use IntentStatus::*;
+
+    const ALL: [IntentStatus; 8] = [
+        Created, Authorized, Submitted, Confirmed, Finalized, Failed, Expired, Cancelled,
+    ];
+
+    /// Every transition the lifecycle allows; all other pairs must be refused.
+    const ALLOWED: [(IntentStatus, IntentStatus); 10] = [
+        (Created, Authorized),
+        (Created, Submitted),
+        (Created, Expired),
+        (Created, Cancelled),
+        (Authorized, Submitted),
+        (Authorized, Expired),
+        (Authorized, Cancelled),
+        (Submitted, Confirmed),
+        (Submitted, Failed),
+        (Confirmed, Finalized),
+    ];
+
+    fn intent(status: IntentStatus) -> PaymentIntentRecord {
+        let mut record = testing::intent("pi_1", "alice", "bob", 100, Utc::now());
+        record.status = status;
+        record.signature = Some("sig".to_string());
+        record
+    }
+
+    #[test]
+    fn only_listed_transitions_are_allowed() {
+        for from in ALL {
+            for to in ALL {
+                assert_eq!(
+                    from.can_become(to),
+                    ALLOWED.contains(&(from, to)),
+                    "{from} -> {to}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn terminal_statuses_are_absorbing() {
+        for from in ALL {
+            let absorbing = ALL.iter().all(|to| !from.can_become(*to));
+            assert_eq!(from.is_terminal(), absorbing, "{from}");
+        }
+        assert!(!Finalized.can_become(Cancelled));
+        assert!(!Failed.can_become(Submitted));
+        assert!(!Expired.can_become(Authorized));
+    }
+
+    #[test]
+    fn statuses_round_trip_through_their_names() {
+        for status in ALL {
+            assert_eq!(IntentStatus::parse(status.as_str()), Some(status));
+            assert_eq!(
+                serde_json::to_value(status).unwrap(),
+                serde_json::Value::from(status.as_str())
+            );
+        }
+        assert_eq!(IntentStatus::parse("settled"), None);
+    }
+
+    #[test]
+    fn transition_records_the_event() {
+        let mut record = intent(Submitted);
+        let at = Utc::now();
+        transition(&mut record, Failed, Some("reverted".to_string()), at).unwrap();
+        assert_eq!(record.status, Failed);
+        let event = record.events.last().unwrap();
+        assert_eq!(event.previous_status, Some(Submitted));
+        assert_eq!(event.status, Failed);
+        assert_eq!(event.reason.as_deref(), Some("reverted"));
+        assert_eq!(event.signature.as_deref(), Some("sig"));
+        assert_eq!(event.at, at);
+    }
+
+    #[test]
+    fn refused_transition_leaves_the_intent_alone() {
+        let mut record = intent(Finalized);
+        let err = transition(&mut record, Cancelled, None, Utc::now()).unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::Coded {
+                status: StatusCode::CONFLICT,
+                code: "INVALID_TRANSITION",
+                ..
+            }
+        ));
+        assert_eq!(record.status, Finalized);
+        assert_eq!(record.events.len(), 1);
+    }
+}
@@ -1,16 +1,35 @@
 mod app;
 mod config;
 mod error;
+mod events;
+mod extract;
+mod idempotency;
+mod lifecycle;
+mod memo;
 mod models;
+mod money;
+mod oracle;
+mod qr;
+mod reconciler;
+mod registry;
 mod routes;
+mod signing;
+mod siws;
+mod solana;
 mod state;
+mod store;
+mod sweeper;
+#[cfg(test)]
+mod testing;
+mod tokens;
 
 use anyhow::Context;
-use state::{AppState, InMemoryStore};
+use state::AppState;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::info;
+use std::time::Duration;
+use store::{InMemoryStore, SqlStore, Store};
+use tracing::{info, warn};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -22,9 +41,42 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     let cfg = config::Config::from_env();
+    if cfg.dev_header_auth {
+        warn!("MONOPAY_DEV_HEADER_AUTH is enabled, x-user-id headers are trusted");
+    }
+    let store: Arc<dyn Store> = match &cfg.database_url {
+        Some(url) => Arc::new(
+            SqlStore::connect(url)
+                .await
+                .context("failed to open MONOPAY_DATABASE_URL")?,
+        ),
+        None => {
+            warn!("MONOPAY_DATABASE_URL not set, state is kept in memory and lost on restart");
+            Arc::new(InMemoryStore::default())
+        }
+    };
+    let oracle = oracle::from_config(&cfg).context("invalid price oracle configuration")?;
+    let registry = registry::TokenRegistry::from_config(&cfg).context("invalid token registry")?;
     let state = AppState {
-        store: Arc::new(RwLock::new(InMemoryStore::default())),
+        config: cfg.clone(),
+        store,
+        oracle,
+        registry: Arc::new(registry),
+        rpc: solana::rpc_from_config(&cfg).context("invalid Solana RPC configuration")?,
+        events: events::IntentEvents::default(),
     };
+    sweeper::spawn_auth_sweeper(
+        state.clone(),
+        Duration::from_secs(cfg.auth_sweep_interval_secs),
+    );
+    sweeper::spawn_intent_sweeper(
+        state.clone(),
+        Duration::from_secs(cfg.intent_sweep_interval_secs),
+    );
+    reconciler::spawn_reconciler(
+        state.clone(),
+        Duration::from_secs(cfg.reconcile_interval_secs),
+    );
     let router = app::build_router(state);
     let addr: SocketAddr = format!("{}:{}", cfg.host, cfg.port)
         .parse()
@@ -0,0 +1,231 @@
+//! Payment memos. They are written to the chain in a Memo program instruction, where anyone
+//! can read them, so they are kept short and printable and, with `MONOPAY_MEMO_FILTER`, free
+//! of personal details and blocked words.
+
+use crate::config::Config;
+use crate::error::AppError;
+use axum::http::StatusCode;
+
+/// Longest memo in UTF-8 bytes. The largest transaction the gateway builds (an SPL transfer
+/// that also creates the recipient's token account) stays well under the 1232 byte packet
+/// limit with a memo this long, and QR codes carrying it remain easy to scan.
+pub const MAX_MEMO_BYTES: usize = 256;
+
+/// Shortest run of digits treated as a phone, account, card or Aadhaar number.
+const MIN_ID_DIGITS: usize = 9;
+
+fn rejected(message: impl Into<String>) -> AppError {
+    AppError::coded(StatusCode::BAD_REQUEST, "MEMO_REJECTED", message)
+}
+
+/// Zero-width and bidirectional formatting characters, which can make a memo read differently
+/// from what it contains.
+fn is_invisible(c: char) -> bool {
+    matches!(c, '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' | '\u{FEFF}')
+}
+
+/// The memo as stored: control and invisible characters removed, whitespace controls turned
+/// into spaces and the ends trimmed. `None` when nothing is left.
+pub fn clean(config: &Config, input: &str) -> Result<Option<String>, AppError> {
+    let memo: String = input
+        .chars()
+        .filter(|c| !is_invisible(*c))
+        .filter_map(|c| match c {
+            c if c.is_control() && c.is_whitespace() => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect();
+    let memo = memo.trim();
+    if memo.is_empty() {
+        return Ok(None);
+    }
+    if memo.len() > MAX_MEMO_BYTES {
+        return Err(AppError::coded(
+            StatusCode::BAD_REQUEST,
+            "MEMO_TOO_LONG",
+            format!(
+                "memo is {} bytes, at most {MAX_MEMO_BYTES} are allowed",
+                memo.len()
+            ),
+        ));
+    }
+    if config.memo_filter {
+        check(config, memo)?;
+    }
+    Ok(Some(memo.to_string()))
+}
+
+fn check(config: &Config, memo: &str) -> Result<(), AppError> {
+    let words = memo
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase);
+    for word in words {
+        if config.memo_blocklist.contains(&word) {
+            return Err(rejected("memo contains a blocked word"));
+        }
+        if is_pan(&word) {
+            return Err(rejected("memo looks like it contains a PAN"));
+        }
+    }
+    // Emails and UPI ids alike.
+    if memo.split_whitespace().any(|word| {
+        word.split_once('@').is_some_and(|(user, domain)| {
+            user.chars().any(char::is_alphanumeric) && domain.chars().any(char::is_alphanumeric)
+        })
+    }) {
+        return Err(rejected("memo looks like it contains an email or UPI id"));
+    }
+    if longest_digit_run(memo) >= MIN_ID_DIGITS {
+        return Err(rejected(
+            "memo looks like it contains a phone, account or card number",
+        ));
+    }
+    Ok(())
+}
+
+/// Indian PAN: five letters, four digits, one letter.
+fn is_pan(word: &str) -> bool {
+    let bytes = word.as_bytes();
+    bytes.len() == 10
+        && bytes[..5].iter().all(u8::is_ascii_alphabetic)
+        && bytes[5..9].iter().all(u8::is_ascii_digit)
+        && bytes[9].is_ascii_alphabetic()
+}
+
+/// Digits in the longest stretch of digits, spaces and dashes, so `98765 43210` and
+/// `4111-1111-1111-1111` count in full.
+fn longest_digit_run(memo: &str) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in memo.chars() {
+        match c {
+            '0'..='9' => {
+                current += 1;
+                longest = longest.max(current);
+            }
+            ' ' | '-' => {}
+            _ => current = 0,
+        }
+    }
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn config(filter: bool) -> Config {
+        let mut config = testing::config();
+        config.memo_filter = filter;
+        config.memo_blocklist = vec!["scam".to_string()];
+        config
+    }
+
+    fn cleaned(input: &str) -> Option<String> {
+        clean(&config(false), input).unwrap()
+    }
+
+    fn code(result: Result<Option<String>, AppError>) -> &'static str {
+        match result {
+            Err(AppError::Coded { code, .. }) => code,
+            other => panic!("expected a coded error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn memos_are_capped_in_bytes_after_cleaning() {
+        let config = config(false);
+        let longest = "a".repeat(MAX_MEMO_BYTES);
+        assert_eq!(cleaned(&longest), Some(longest.clone()));
+        assert_eq!(
+            cleaned(&format!("  {longest}\u{200B}\n")),
+            Some(longest.clone())
+        );
+        assert_eq!(
+            code(clean(&config, &format!("{longest}a"))),
+            "MEMO_TOO_LONG"
+        );
+
+        // `é` is two bytes, so half as many fit.
+        let accented = "é".repeat(MAX_MEMO_BYTES / 2);
+        assert_eq!(cleaned(&accented), Some(accented.clone()));
+        assert_eq!(
+            code(clean(&config, &format!("{accented}é"))),
+            "MEMO_TOO_LONG"
+        );
+    }
+
+    #[test]
+    fn control_and_invisible_characters_are_stripped() {
+        assert_eq!(
+            cleaned("Tea\u{0}\u{7}\u{1b}[31m"),
+            Some("Tea[31m".to_string())
+        );
+        assert_eq!(
+            cleaned("Tea\tfor\ntwo\r\n"),
+            Some("Tea for two".to_string())
+        );
+        assert_eq!(
+            cleaned("pay\u{200B}ment \u{202E}reversed\u{2069}\u{FEFF}"),
+            Some("payment reversed".to_string())
+        );
+        assert_eq!(cleaned("Chai ☕ at 5"), Some("Chai ☕ at 5".to_string()));
+    }
+
+    #[test]
+    fn nothing_printable_is_no_memo() {
+        for input in [
+            "",
+            "   ",
+            "\n\t\r",
+            "\u{0}\u{7f}",
+            "\u{200B}\u{FEFF} \u{202A}",
+        ] {
+            assert_eq!(cleaned(input), None, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn filter_rejects_personal_details_and_blocked_words() {
+        let config = config(true);
+        let rejected = [
+            "this is a SCAM",
+            "PAN abcde1234f",
+            "mail me at priya@example.com",
+            "upi priya@okbank",
+            "call 98765 43210",
+            "card 4111-1111-1111-1111",
+            "acct 123456789",
+        ];
+        for memo in rejected {
+            assert_eq!(code(clean(&config, memo)), "MEMO_REJECTED", "{memo}");
+        }
+
+        let allowed = [
+            "scampi for dinner",
+            "Rent for 2026-10",
+            "order 12345678",
+            "@ the cafe",
+            "chai @ 5",
+        ];
+        for memo in allowed {
+            assert_eq!(
+                clean(&config, memo).unwrap().as_deref(),
+                Some(memo),
+                "{memo}"
+            );
+        }
+    }
+
+    #[test]
+    fn without_the_filter_only_cleaning_applies() {
+        let config = config(false);
+        assert_eq!(
+            clean(&config, "scam 98765 43210").unwrap().as_deref(),
+            Some("scam 98765 43210")
+        );
+    }
+}
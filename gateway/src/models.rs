@@ -1,3 +1,7 @@
+use crate::lifecycle::IntentStatus;
+use crate::money::{Inr, Rate, TokenAmount};
+use crate::siws::SiwsMessage;
+use crate::store::IntentSort;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -11,23 +15,76 @@ pub struct NonceResponse {
     #[serde(rename = "expiresAt")]
     pub expires_at: DateTime<Utc>,
     pub nonce: String,
+    pub message: String,
+    #[serde(rename = "messageBase64")]
+    pub message_base64: String,
+    pub fields: SiwsMessage,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct VerifyAuthRequest {
     pub wallet: String,
-    pub nonce: String,
+    pub message: String,
     pub signature: String,
+    #[serde(rename = "deviceId")]
+    pub device_id: String,
 }
 
 #[derive(Debug, Serialize)]
 pub struct VerifyAuthResponse {
     #[serde(rename = "accessToken")]
     pub access_token: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+    #[serde(rename = "refreshExpiresAt")]
+    pub refresh_expires_at: DateTime<Utc>,
     #[serde(rename = "userId")]
     pub user_id: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RefreshTokenRequest {
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+    #[serde(rename = "deviceId")]
+    pub device_id: String,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct LogoutRequest {
+    #[serde(rename = "allDevices", default)]
+    pub all_devices: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogoutResponse {
+    pub status: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LinkWalletRequest {
+    pub wallet: String,
+    pub message: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WalletLinkResponse {
+    pub wallet: String,
+    pub chain: String,
+    #[serde(rename = "isPrimary")]
+    pub is_primary: bool,
+    #[serde(rename = "linkedAt")]
+    pub linked_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WalletListResponse {
+    pub wallets: Vec<WalletLinkResponse>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct EnrollPinRequest {
     pub pin: String,
@@ -54,13 +111,15 @@ pub struct VerifyPinResponse {
 
 #[derive(Debug, Deserialize)]
 pub struct CreateSessionRequest {
+    /// Defaults to the wallet the caller signed in with.
+    #[serde(default)]
     pub wallet: String,
     #[serde(rename = "deviceId")]
     pub device_id: String,
     #[serde(rename = "perTxLimitInr")]
-    pub per_tx_limit_inr: f64,
+    pub per_tx_limit_inr: Inr,
     #[serde(rename = "dailyLimitInr")]
-    pub daily_limit_inr: f64,
+    pub daily_limit_inr: Inr,
     #[serde(rename = "ttlMinutes")]
     pub ttl_minutes: i64,
 }
@@ -71,11 +130,11 @@ pub struct SessionResponse {
     pub status: String,
     pub wallet: String,
     #[serde(rename = "perTxLimitInr")]
-    pub per_tx_limit_inr: f64,
+    pub per_tx_limit_inr: Inr,
     #[serde(rename = "dailyLimitInr")]
-    pub daily_limit_inr: f64,
+    pub daily_limit_inr: Inr,
     #[serde(rename = "remainingTodayInr")]
-    pub remaining_today_inr: f64,
+    pub remaining_today_inr: Inr,
     #[serde(rename = "expiresAt")]
     pub expires_at: DateTime<Utc>,
 }
@@ -94,19 +153,70 @@ pub struct HandleResponse {
 
 #[derive(Debug, Deserialize)]
 pub struct QuoteQuery {
-    pub inr: f64,
+    /// Quote INR -> token; exactly one of `inr` and `amount` is required.
+    pub inr: Option<Inr>,
+    /// Quote token -> INR, in whole tokens.
+    pub amount: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateQuoteRequest {
+    pub token: String,
+    /// As in `QuoteQuery`; exactly one of `inr` and `amount` is required.
+    pub inr: Option<Inr>,
+    pub amount: Option<String>,
+    #[serde(rename = "maxSlippageBps")]
+    pub max_slippage_bps: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    pub symbol: String,
+    /// `null` for native SOL.
+    pub mint: Option<String>,
+    #[serde(rename = "tokenProgram")]
+    pub token_program: Option<String>,
+    pub decimals: u8,
+    pub cluster: String,
+    pub enabled: bool,
+    #[serde(rename = "explorerUrl")]
+    pub explorer_url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenListResponse {
+    pub tokens: Vec<TokenResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuotePreviewResponse {
+    pub token: String,
+    pub inr: Inr,
+    #[serde(rename = "tokenAmount")]
+    pub token_amount: TokenAmount,
+    pub rate: Rate,
+    pub source: String,
+    #[serde(rename = "asOf")]
+    pub as_of: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct QuoteResponse {
-    pub inr: String,
-    pub usdc: String,
-    pub rate: String,
+    #[serde(rename = "quoteId")]
+    pub quote_id: String,
+    pub token: String,
+    pub inr: Inr,
+    #[serde(rename = "tokenAmount")]
+    pub token_amount: TokenAmount,
+    pub rate: Rate,
     pub source: String,
     #[serde(rename = "asOf")]
     pub as_of: DateTime<Utc>,
+    /// End of the lock window; the quote can be used by one payment intent until then.
     #[serde(rename = "expiresAt")]
     pub expires_at: DateTime<Utc>,
+    #[serde(rename = "maxSlippageBps")]
+    pub max_slippage_bps: u32,
 }
 
 #[derive(Debug, Deserialize)]
@@ -114,9 +224,15 @@ pub struct CreatePaymentIntentRequest {
     #[serde(rename = "recipientHandle")]
     pub recipient_handle: String,
     #[serde(rename = "inrAmount")]
-    pub inr_amount: f64,
+    pub inr_amount: Inr,
     pub token: String,
+    /// Written on chain with the transfer; see `memo::clean` for what is accepted.
     pub memo: Option<String>,
+    /// Paying wallet; defaults to the active session's wallet.
+    pub wallet: Option<String>,
+    /// Locked quote from `/quotes`; without it the intent is priced at the live rate.
+    #[serde(rename = "quoteId")]
+    pub quote_id: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -126,13 +242,16 @@ pub struct PaymentIntentResponse {
     #[serde(rename = "recipientWallet")]
     pub recipient_wallet: String,
     #[serde(rename = "inrAmount")]
-    pub inr_amount: String,
+    pub inr_amount: Inr,
     #[serde(rename = "tokenAmount")]
-    pub token_amount: String,
+    pub token_amount: TokenAmount,
     pub token: String,
+    #[serde(rename = "quoteId")]
+    pub quote_id: String,
     #[serde(rename = "quoteExpiresAt")]
     pub quote_expires_at: DateTime<Utc>,
     pub reference: String,
+    pub memo: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -143,22 +262,266 @@ pub struct ExecutePaymentIntentRequest {
     pub session_id: String,
 }
 
+/// Why the payer abandoned an intent, recorded as the reason of its `cancelled` event.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelReason {
+    /// Left the pay screen without paying.
+    PayerAbandoned,
+    WrongRecipient,
+    WrongAmount,
+    Duplicate,
+    Other,
+}
+
+impl CancelReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::PayerAbandoned => "payer_abandoned",
+            Self::WrongRecipient => "wrong_recipient",
+            Self::WrongAmount => "wrong_amount",
+            Self::Duplicate => "duplicate",
+            Self::Other => "other",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CancelPaymentIntentRequest {
+    pub reason: CancelReason,
+}
+
+/// Which side of a payment the caller is on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaymentDirection {
+    /// Both; only accepted as a filter.
+    #[default]
+    All,
+    /// Created by the caller.
+    Sent,
+    /// Paying one of the caller's linked wallets.
+    Received,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListPaymentIntentsQuery {
+    /// Page size, 20 by default and at most 100.
+    pub limit: Option<usize>,
+    /// `nextCursor` of the previous page.
+    pub cursor: Option<String>,
+    pub direction: Option<PaymentDirection>,
+    /// Comma-separated statuses, any of which match.
+    pub status: Option<String>,
+    pub token: Option<String>,
+    /// Handle of the other side: the recipient of sent intents, the payer of received ones.
+    pub counterparty: Option<String>,
+    /// Created at or after.
+    pub from: Option<DateTime<Utc>>,
+    /// Created before.
+    pub to: Option<DateTime<Utc>>,
+    #[serde(rename = "minInr")]
+    pub min_inr: Option<Inr>,
+    #[serde(rename = "maxInr")]
+    pub max_inr: Option<Inr>,
+    pub sort: Option<IntentSort>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PaymentIntentSummary {
+    pub id: String,
+    pub direction: PaymentDirection,
+    pub status: IntentStatus,
+    pub token: String,
+    #[serde(rename = "tokenAmount")]
+    pub token_amount: TokenAmount,
+    #[serde(rename = "inrAmount")]
+    pub inr_amount: Inr,
+    #[serde(rename = "payerWallet")]
+    pub payer_wallet: String,
+    #[serde(rename = "recipientWallet")]
+    pub recipient_wallet: String,
+    /// Handle of the other side, when it has one.
+    #[serde(rename = "counterpartyHandle")]
+    pub counterparty_handle: Option<String>,
+    pub memo: Option<String>,
+    pub signature: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PaymentIntentListResponse {
+    pub items: Vec<PaymentIntentSummary>,
+    /// Pass as `cursor` for the next page; `null` on the last one.
+    #[serde(rename = "nextCursor")]
+    pub next_cursor: Option<String>,
+}
+
+/// An intent as either side of the payment sees it; nothing about the payer's session or
+/// device is included.
+#[derive(Debug, Serialize)]
+pub struct PaymentReceiptResponse {
+    pub id: String,
+    pub direction: PaymentDirection,
+    pub status: IntentStatus,
+    pub token: String,
+    #[serde(rename = "tokenAmount")]
+    pub token_amount: TokenAmount,
+    #[serde(rename = "inrAmount")]
+    pub inr_amount: Inr,
+    #[serde(rename = "payerWallet")]
+    pub payer_wallet: String,
+    #[serde(rename = "payerHandle")]
+    pub payer_handle: Option<String>,
+    #[serde(rename = "recipientWallet")]
+    pub recipient_wallet: String,
+    #[serde(rename = "recipientHandle")]
+    pub recipient_handle: Option<String>,
+    pub memo: Option<String>,
+    pub signature: Option<String>,
+    #[serde(rename = "explorerUrl")]
+    pub explorer_url: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    pub events: Vec<PaymentEventResponse>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ExecutePaymentIntentResponse {
     pub id: String,
-    pub status: String,
+    pub status: IntentStatus,
     pub mode: String,
-    pub signature: String,
-    #[serde(rename = "explorerUrl")]
-    pub explorer_url: String,
+    /// Solana Pay transaction request the payer's wallet signs and sends.
+    #[serde(rename = "transactionUrl")]
+    pub transaction_url: String,
+    pub memo: Option<String>,
+}
+
+/// Solana Pay transaction request `GET` response.
+#[derive(Debug, Serialize)]
+pub struct TransactionRequestMetadata {
+    pub label: String,
+    pub icon: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionRequest {
+    /// Payer wallet; it pays the fee and signs the transfer.
+    pub account: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionRequestQuery {
+    /// Recent blockhash to build with instead of fetching one from the RPC node.
+    pub blockhash: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransactionResponse {
+    /// Base64 unsigned legacy transaction.
+    pub transaction: String,
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SolanaPayQuery {
+    /// `transfer` (default) or `transaction`.
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HandleSolanaPayQuery {
+    /// Registry symbol; native SOL when absent.
+    pub token: Option<String>,
+    /// Whole tokens; absent lets the payer choose.
+    pub amount: Option<String>,
+    pub label: Option<String>,
+    pub message: Option<String>,
+    pub memo: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QrQuery {
+    /// `svg` (default) or `png`.
+    pub format: Option<String>,
+    /// Minimum width in pixels.
+    pub size: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ParseSolanaPayRequest {
+    pub url: String,
+}
+
+/// A Solana Pay URL and its fields, as generated for an intent or handle or parsed from a scan.
+#[derive(Debug, Serialize)]
+pub struct SolanaPayResponse {
+    /// `transfer` or `transaction`.
+    pub kind: &'static str,
+    pub url: String,
+    /// https link of a transaction request.
+    pub link: Option<String>,
+    pub recipient: Option<String>,
+    pub amount: Option<String>,
+    /// Registry symbol of the token paid, when the gateway knows it.
+    pub token: Option<String>,
+    #[serde(rename = "splToken")]
+    pub spl_token: Option<String>,
+    pub references: Vec<String>,
+    pub label: Option<String>,
+    pub message: Option<String>,
+    pub memo: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PaymentEventResponse {
+    /// Also the SSE event id, for resuming with `Last-Event-ID`.
+    pub id: String,
+    #[serde(rename = "previousStatus")]
+    pub previous_status: Option<IntentStatus>,
+    pub status: IntentStatus,
+    pub reason: Option<String>,
+    pub signature: Option<String>,
+    pub at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct PaymentIntentStatusResponse {
     pub id: String,
-    pub status: String,
+    pub status: IntentStatus,
     pub mode: String,
+    pub memo: Option<String>,
     pub signature: Option<String>,
     #[serde(rename = "explorerUrl")]
     pub explorer_url: Option<String>,
+    /// Status history, oldest first.
+    pub events: Vec<PaymentEventResponse>,
+}
+
+/// What a webhook delivery did.
+#[derive(Debug, Serialize)]
+pub struct WebhookResponse {
+    /// Transactions in the delivery.
+    pub transactions: usize,
+    /// Transactions carrying the reference of a known intent.
+    pub matched: usize,
+    /// Intents whose status changed.
+    pub updated: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PaymentEventsQuery {
+    /// Resume point for clients that cannot set `Last-Event-ID`, such as WebSockets.
+    #[serde(rename = "lastEventId")]
+    pub last_event_id: Option<String>,
+    /// Events token for clients that cannot set `Authorization`, such as browser `EventSource`s.
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PaymentEventsTokenResponse {
+    pub token: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime<Utc>,
 }
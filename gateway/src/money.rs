@@ -0,0 +1,436 @@
+//! Exact fixed-point money. INR is held as integer paise and token amounts as integer
+//! base units of the mint (6 decimals for USDC, 9 for SOL lamports). Amounts travel
+//! over the API as decimal strings; requests may also send plain JSON numbers.
+//!
+//! Rounding rules:
+//! - INR input with more than 2 decimal places is rejected, never rounded.
+//! - INR -> token rounds up to the next base unit so the payer never sends less than quoted.
+//! - Token -> INR rounds up to the next paisa so the INR charged always covers the tokens.
+//! - Token input with more decimals than the mint supports is rejected.
+//! - Rates keep 8 decimal places; extra digits from price sources are truncated.
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+
+pub const INR_DECIMALS: u32 = 2;
+/// Fractional digits kept for exchange rates.
+pub const RATE_DECIMALS: u32 = 8;
+
+/// Upper bound for a single INR amount (₹1,000 crore) so paise always fit an `i64` column.
+const MAX_INR_PAISE: u64 = 10_000_000_000 * 100;
+
+/// Parses a non-negative decimal with at most `decimals` fractional digits into scaled units.
+fn parse_fixed(input: &str, decimals: u32) -> Result<u128, String> {
+    let input = input.trim();
+    let (whole, fraction) = input.split_once('.').unwrap_or((input, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return Err("amount is empty".to_string());
+    }
+    if !whole
+        .bytes()
+        .chain(fraction.bytes())
+        .all(|b| b.is_ascii_digit())
+    {
+        return Err(format!("`{input}` is not a plain decimal amount"));
+    }
+    if fraction.len() > decimals as usize {
+        return Err(format!("`{input}` has more than {decimals} decimal places"));
+    }
+
+    let scale = 10u128.pow(decimals);
+    let whole: u128 = if whole.is_empty() {
+        0
+    } else {
+        whole
+            .parse()
+            .map_err(|_| format!("`{input}` is too large"))?
+    };
+    let fraction: u128 = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse::<u128>().unwrap_or(0) * 10u128.pow(decimals - fraction.len() as u32)
+    };
+    whole
+        .checked_mul(scale)
+        .and_then(|units| units.checked_add(fraction))
+        .ok_or_else(|| format!("`{input}` is too large"))
+}
+
+fn format_fixed(units: u128, decimals: u32) -> String {
+    if decimals == 0 {
+        return units.to_string();
+    }
+    let scale = 10u128.pow(decimals);
+    format!(
+        "{}.{:0width$}",
+        units / scale,
+        units % scale,
+        width = decimals as usize
+    )
+}
+
+/// Accepts `"120.50"` as well as `120.5`; numbers go through their shortest decimal form.
+struct DecimalVisitor;
+
+impl Visitor<'_> for DecimalVisitor {
+    type Value = String;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a decimal amount as a string or number")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<String, E> {
+        Ok(value.to_string())
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<String, E> {
+        Ok(value.to_string())
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<String, E> {
+        Ok(value.to_string())
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<String, E> {
+        Ok(value.to_string())
+    }
+}
+
+/// Indian rupees as integer paise.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Inr(u64);
+
+impl Inr {
+    pub const ZERO: Self = Self(0);
+
+    pub const fn from_paise(paise: u64) -> Self {
+        Self(paise)
+    }
+
+    pub const fn paise(self) -> u64 {
+        self.0
+    }
+
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let paise = parse_fixed(input, INR_DECIMALS)?;
+        if paise > u128::from(MAX_INR_PAISE) {
+            return Err(format!("`{}` exceeds the maximum INR amount", input.trim()));
+        }
+        Ok(Self(paise as u64))
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.0.checked_add(other.0).map(Self)
+    }
+
+    pub fn saturating_sub(self, other: Self) -> Self {
+        Self(self.0.saturating_sub(other.0))
+    }
+}
+
+impl fmt::Display for Inr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&format_fixed(u128::from(self.0), INR_DECIMALS))
+    }
+}
+
+impl Serialize for Inr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Inr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = deserializer.deserialize_any(DecimalVisitor)?;
+        Self::parse(&raw).map_err(de::Error::custom)
+    }
+}
+
+/// Token amount in the mint's smallest unit, carrying the mint's decimals for display.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TokenAmount {
+    base_units: u64,
+    decimals: u8,
+}
+
+impl TokenAmount {
+    pub const fn new(base_units: u64, decimals: u8) -> Self {
+        Self {
+            base_units,
+            decimals,
+        }
+    }
+
+    pub fn parse(input: &str, decimals: u8) -> Result<Self, String> {
+        let units = parse_fixed(input, u32::from(decimals))?;
+        let base_units =
+            u64::try_from(units).map_err(|_| format!("`{}` is too large", input.trim()))?;
+        Ok(Self::new(base_units, decimals))
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.base_units == 0
+    }
+
+    pub const fn base_units(self) -> u64 {
+        self.base_units
+    }
+
+    pub const fn decimals(self) -> u8 {
+        self.decimals
+    }
+}
+
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&format_fixed(
+            u128::from(self.base_units),
+            u32::from(self.decimals),
+        ))
+    }
+}
+
+impl Serialize for TokenAmount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// INR price of one whole token, scaled by 10^`RATE_DECIMALS`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Rate(u128);
+
+impl Rate {
+    pub const fn from_scaled(scaled: u128) -> Self {
+        Self(scaled)
+    }
+
+    pub const fn scaled(self) -> u128 {
+        self.0
+    }
+
+    /// Parses a decimal rate, truncating digits beyond `RATE_DECIMALS`.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        let truncated = match input.split_once('.') {
+            Some((whole, fraction))
+                if fraction.is_ascii() && fraction.len() > RATE_DECIMALS as usize =>
+            {
+                format!("{whole}.{}", &fraction[..RATE_DECIMALS as usize])
+            }
+            _ => input.to_string(),
+        };
+        parse_fixed(&truncated, RATE_DECIMALS).map(Self)
+    }
+
+    /// Token amount worth `inr` at this rate, rounded up to the next base unit.
+    pub fn inr_to_token(self, inr: Inr, decimals: u8) -> Result<TokenAmount, String> {
+        if self.0 == 0 {
+            return Err("rate is zero".to_string());
+        }
+        // paise * 10^decimals * 10^RATE_DECIMALS / (100 * rate)
+        let numerator = 10u128
+            .checked_pow(u32::from(decimals) + RATE_DECIMALS - INR_DECIMALS)
+            .and_then(|scale| scale.checked_mul(u128::from(inr.paise())))
+            .ok_or("amount is too large")?;
+        let base_units = numerator.div_ceil(self.0);
+        let base_units = u64::try_from(base_units).map_err(|_| "amount is too large")?;
+        Ok(TokenAmount::new(base_units, decimals))
+    }
+
+    /// INR value of `amount` at this rate, rounded up to the next paisa.
+    pub fn token_to_inr(self, amount: TokenAmount) -> Result<Inr, String> {
+        // base_units * rate * 100 / (10^decimals * 10^RATE_DECIMALS)
+        let denominator = 10u128
+            .checked_pow(u32::from(amount.decimals()) + RATE_DECIMALS - INR_DECIMALS)
+            .ok_or("amount is too large")?;
+        let paise = u128::from(amount.base_units())
+            .checked_mul(self.0)
+            .ok_or("amount is too large")?
+            .div_ceil(denominator);
+        if paise > u128::from(MAX_INR_PAISE) {
+            return Err("amount exceeds the maximum INR amount".to_string());
+        }
+        Ok(Inr::from_paise(paise as u64))
+    }
+}
+
+impl fmt::Display for Rate {
+    /// At least two decimals, trailing zeros beyond that trimmed: `83.61`, `13250.125`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let full = format_fixed(self.0, RATE_DECIMALS);
+        let (whole, fraction) = full.split_once('.').unwrap_or((&full, ""));
+        let trimmed = fraction.trim_end_matches('0');
+        write!(f, "{whole}.{trimmed:0<2}")
+    }
+}
+
+impl Serialize for Rate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(input: &str) -> Rate {
+        Rate::parse(input).unwrap()
+    }
+
+    #[test]
+    fn inr_parses_to_the_paisa_and_rejects_finer_amounts() {
+        assert_eq!(Inr::parse("120.50"), Ok(Inr::from_paise(12_050)));
+        assert_eq!(Inr::parse(" 0.01 "), Ok(Inr::from_paise(1)));
+        assert_eq!(Inr::parse("7"), Ok(Inr::from_paise(700)));
+        assert_eq!(Inr::parse(".5"), Ok(Inr::from_paise(50)));
+        assert!(Inr::parse("0.005").is_err());
+        assert!(Inr::parse("1.999").is_err());
+        assert!(Inr::parse("-1").is_err());
+        assert!(Inr::parse("1e3").is_err());
+        assert!(Inr::parse("").is_err());
+        assert_eq!(Inr::from_paise(12_050).to_string(), "120.50");
+    }
+
+    #[test]
+    fn inr_stops_at_the_maximum_and_overflow_is_none() {
+        assert_eq!(
+            Inr::parse("10000000000.00"),
+            Ok(Inr::from_paise(MAX_INR_PAISE))
+        );
+        assert!(Inr::parse("10000000000.01").is_err());
+        assert!(Inr::parse("340282366920938463463374607431768211456").is_err());
+        assert_eq!(
+            Inr::from_paise(u64::MAX).checked_add(Inr::from_paise(1)),
+            None
+        );
+        assert_eq!(
+            Inr::from_paise(u64::MAX - 1).checked_add(Inr::from_paise(1)),
+            Some(Inr::from_paise(u64::MAX))
+        );
+        assert_eq!(
+            Inr::from_paise(5).saturating_sub(Inr::from_paise(9)),
+            Inr::ZERO
+        );
+    }
+
+    #[test]
+    fn token_amounts_respect_the_mint_decimals() {
+        assert_eq!(TokenAmount::parse("3", 0), Ok(TokenAmount::new(3, 0)));
+        assert!(TokenAmount::parse("3.0", 0).is_err());
+        assert_eq!(
+            TokenAmount::parse("1.5", 6),
+            Ok(TokenAmount::new(1_500_000, 6))
+        );
+        assert!(TokenAmount::parse("0.0000001", 6).is_err());
+        assert_eq!(
+            TokenAmount::parse("0.000000001", 9),
+            Ok(TokenAmount::new(1, 9))
+        );
+        assert!(TokenAmount::parse("18446744073.709551616", 9).is_err());
+        assert_eq!(TokenAmount::new(3, 0).to_string(), "3");
+        assert_eq!(TokenAmount::new(1_500_000, 6).to_string(), "1.500000");
+        assert_eq!(TokenAmount::new(1, 9).to_string(), "0.000000001");
+    }
+
+    #[test]
+    fn rates_truncate_beyond_eight_decimals() {
+        assert_eq!(rate("83.123456789").scaled(), 8_312_345_678);
+        assert_eq!(rate("83.61").to_string(), "83.61");
+        assert_eq!(rate("13250.125").to_string(), "13250.125");
+        assert_eq!(rate("100").to_string(), "100.00");
+    }
+
+    #[test]
+    fn inr_to_token_rounds_up_to_the_next_base_unit() {
+        // Exact amounts are not rounded.
+        assert_eq!(
+            rate("100").inr_to_token(Inr::from_paise(10_000), 0),
+            Ok(TokenAmount::new(1, 0))
+        );
+        // One paisa past a whole token costs a second one.
+        assert_eq!(
+            rate("100").inr_to_token(Inr::from_paise(10_001), 0),
+            Ok(TokenAmount::new(2, 0))
+        );
+        assert_eq!(
+            rate("100").inr_to_token(Inr::from_paise(1), 0),
+            Ok(TokenAmount::new(1, 0))
+        );
+        // ₹1 / 83.61 = 0.0119602918...
+        assert_eq!(
+            rate("83.61").inr_to_token(Inr::from_paise(100), 6),
+            Ok(TokenAmount::new(11_961, 6))
+        );
+        // ₹1 / 13250.125 = 0.0000754709...
+        assert_eq!(
+            rate("13250.125").inr_to_token(Inr::from_paise(100), 9),
+            Ok(TokenAmount::new(75_471, 9))
+        );
+        assert_eq!(
+            rate("13250.125").inr_to_token(Inr::from_paise(13_250_125), 9),
+            Ok(TokenAmount::new(10_000_000_000, 9))
+        );
+    }
+
+    #[test]
+    fn token_to_inr_rounds_up_to_the_next_paisa() {
+        assert_eq!(
+            rate("83.61").token_to_inr(TokenAmount::new(1_000_000, 6)),
+            Ok(Inr::from_paise(8_361))
+        );
+        // 0.000001 USDC is worth 0.00008361 INR, charged as a whole paisa.
+        assert_eq!(
+            rate("83.61").token_to_inr(TokenAmount::new(1, 6)),
+            Ok(Inr::from_paise(1))
+        );
+        // 0.119617 USDC at 83.61 is 10.00117737 INR: just past ₹10.00.
+        assert_eq!(
+            rate("83.61").token_to_inr(TokenAmount::new(119_617, 6)),
+            Ok(Inr::from_paise(1_001))
+        );
+        assert_eq!(
+            rate("100").token_to_inr(TokenAmount::new(7, 0)),
+            Ok(Inr::from_paise(70_000))
+        );
+        assert_eq!(
+            rate("13250.125").token_to_inr(TokenAmount::new(1, 9)),
+            Ok(Inr::from_paise(1))
+        );
+        assert_eq!(
+            rate("0").token_to_inr(TokenAmount::new(5, 9)),
+            Ok(Inr::ZERO)
+        );
+    }
+
+    #[test]
+    fn conversions_report_overflow_instead_of_wrapping() {
+        assert!(rate("0").inr_to_token(Inr::from_paise(100), 6).is_err());
+        // 10^10 rupees of a token priced at 10^-8 INR needs more than u64 base units.
+        assert!(rate("0.00000001")
+            .inr_to_token(Inr::from_paise(MAX_INR_PAISE), 9)
+            .is_err());
+        assert!(rate("100000000")
+            .token_to_inr(TokenAmount::new(u64::MAX, 0))
+            .is_err());
+    }
+
+    #[test]
+    fn amounts_deserialize_from_strings_and_numbers() {
+        let inr: Inr = serde_json::from_str("\"120.50\"").unwrap();
+        assert_eq!(inr, Inr::from_paise(12_050));
+        let inr: Inr = serde_json::from_str("120.5").unwrap();
+        assert_eq!(inr, Inr::from_paise(12_050));
+        let inr: Inr = serde_json::from_str("120").unwrap();
+        assert_eq!(inr, Inr::from_paise(12_000));
+        assert!(serde_json::from_str::<Inr>("0.001").is_err());
+        assert_eq!(serde_json::to_string(&inr).unwrap(), "\"120.00\"");
+    }
+}
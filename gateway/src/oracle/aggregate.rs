@@ -0,0 +1,271 @@
+use super::{unavailable, PriceOracle, PricePoint};
+use crate::error::AppError;
+use crate::money::Rate;
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+use tokio::task::JoinSet;
+use tracing::warn;
+
+/// Queries every source concurrently and returns the median of the fresh, agreeing prices.
+///
+/// Prices older than `max_age` are dropped, then any price more than `max_deviation_bps`
+/// away from the median of the rest is rejected as an outlier and the median recomputed.
+/// The reported source lists the contributors, e.g. `median(fixed,http)`.
+pub struct MedianOracle {
+    pub sources: Vec<Arc<dyn PriceOracle>>,
+    pub max_age: Duration,
+    pub max_deviation_bps: u32,
+    pub min_sources: usize,
+}
+
+fn median(points: &[PricePoint]) -> u128 {
+    let mut rates: Vec<u128> = points.iter().map(|point| point.rate.scaled()).collect();
+    rates.sort_unstable();
+    let mid = rates.len() / 2;
+    if rates.len().is_multiple_of(2) {
+        (rates[mid - 1] + rates[mid]) / 2
+    } else {
+        rates[mid]
+    }
+}
+
+#[async_trait]
+impl PriceOracle for MedianOracle {
+    fn name(&self) -> &str {
+        "median"
+    }
+
+    async fn price(&self, token: &str) -> Result<PricePoint, AppError> {
+        let mut lookups = JoinSet::new();
+        for source in &self.sources {
+            let source = source.clone();
+            let token = token.to_string();
+            lookups.spawn(async move {
+                let result = source.price(&token).await;
+                (source.name().to_string(), result)
+            });
+        }
+
+        let now = Utc::now();
+        let mut fresh = Vec::new();
+        while let Some(joined) = lookups.join_next().await {
+            match joined {
+                Ok((_, Ok(point))) if now - point.as_of <= self.max_age => fresh.push(point),
+                Ok((name, Ok(point))) => {
+                    warn!(
+                        "price source {name} is stale for {token}, as of {}",
+                        point.as_of
+                    )
+                }
+                Ok((name, Err(err))) => warn!("price source {name} failed for {token}: {err:?}"),
+                Err(err) => warn!("price source task failed: {err}"),
+            }
+        }
+        if fresh.is_empty() {
+            return Err(unavailable(format!("no fresh price for {token}")));
+        }
+
+        let center = median(&fresh);
+        let (agreeing, outliers): (Vec<_>, Vec<_>) = fresh.into_iter().partition(|point| {
+            point.rate.scaled().abs_diff(center) * 10_000
+                <= center * u128::from(self.max_deviation_bps)
+        });
+        for point in &outliers {
+            warn!(
+                "price source {} rejected as outlier for {token}: {} vs median {}",
+                point.source,
+                point.rate,
+                Rate::from_scaled(center)
+            );
+        }
+        if agreeing.len() < self.min_sources.max(1) {
+            return Err(unavailable(format!(
+                "only {} of {} required price sources agree for {token}",
+                agreeing.len(),
+                self.min_sources
+            )));
+        }
+
+        let mut names: Vec<&str> = agreeing.iter().map(|point| point.source.as_str()).collect();
+        names.sort_unstable();
+        Ok(PricePoint {
+            rate: Rate::from_scaled(median(&agreeing)),
+            // The aggregate is only as fresh as its oldest contributor.
+            as_of: agreeing
+                .iter()
+                .map(|point| point.as_of)
+                .min()
+                .unwrap_or(now),
+            source: format!("{}({})", self.name(), names.join(",")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    /// A source answering with a fixed rate, `age` old, or failing.
+    struct Stub {
+        name: &'static str,
+        rate: Option<&'static str>,
+        age: Duration,
+    }
+
+    #[async_trait]
+    impl PriceOracle for Stub {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn price(&self, _token: &str) -> Result<PricePoint, AppError> {
+            let rate = self
+                .rate
+                .ok_or_else(|| unavailable(format!("{} is down", self.name)))?;
+            Ok(PricePoint {
+                rate: Rate::parse(rate).unwrap(),
+                as_of: Utc::now() - self.age,
+                source: self.name.to_string(),
+            })
+        }
+    }
+
+    fn fresh(name: &'static str, rate: &'static str) -> Arc<dyn PriceOracle> {
+        Arc::new(Stub {
+            name,
+            rate: Some(rate),
+            age: Duration::seconds(1),
+        })
+    }
+
+    fn stale(name: &'static str, rate: &'static str) -> Arc<dyn PriceOracle> {
+        Arc::new(Stub {
+            name,
+            rate: Some(rate),
+            age: Duration::minutes(10),
+        })
+    }
+
+    fn down(name: &'static str) -> Arc<dyn PriceOracle> {
+        Arc::new(Stub {
+            name,
+            rate: None,
+            age: Duration::zero(),
+        })
+    }
+
+    async fn aggregate(
+        sources: Vec<Arc<dyn PriceOracle>>,
+        min_sources: usize,
+    ) -> Result<PricePoint, AppError> {
+        MedianOracle {
+            sources,
+            max_age: Duration::seconds(60),
+            max_deviation_bps: 500,
+            min_sources,
+        }
+        .price("SOL")
+        .await
+    }
+
+    fn unavailable_message(result: Result<PricePoint, AppError>) -> String {
+        match result {
+            Err(AppError::Coded {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                code: "PRICE_UNAVAILABLE",
+                message,
+            }) => message,
+            other => panic!("expected PRICE_UNAVAILABLE, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn odd_count_takes_the_middle_price() {
+        let point = aggregate(
+            vec![fresh("c", "102"), fresh("a", "100"), fresh("b", "101")],
+            1,
+        )
+        .await
+        .unwrap();
+        assert_eq!(point.rate, Rate::parse("101").unwrap());
+        assert_eq!(point.source, "median(a,b,c)");
+    }
+
+    #[tokio::test]
+    async fn even_count_averages_the_middle_prices() {
+        let point = aggregate(
+            vec![
+                fresh("a", "100"),
+                fresh("b", "101"),
+                fresh("c", "102.5"),
+                fresh("d", "103"),
+            ],
+            1,
+        )
+        .await
+        .unwrap();
+        assert_eq!(point.rate, Rate::parse("101.75").unwrap());
+
+        let point = aggregate(vec![fresh("a", "0.00000001"), fresh("b", "0.00000002")], 1)
+            .await
+            .unwrap();
+        // Halves of a unit are truncated.
+        assert_eq!(point.rate, Rate::from_scaled(1));
+    }
+
+    #[tokio::test]
+    async fn stale_and_failed_sources_are_dropped() {
+        let point = aggregate(
+            vec![
+                fresh("a", "100"),
+                stale("b", "90"),
+                down("c"),
+                fresh("d", "102"),
+            ],
+            2,
+        )
+        .await
+        .unwrap();
+        assert_eq!(point.rate, Rate::parse("101").unwrap());
+        assert_eq!(point.source, "median(a,d)");
+        assert!(Utc::now() - point.as_of < Duration::seconds(60));
+    }
+
+    #[tokio::test]
+    async fn outliers_are_rejected_before_the_final_median() {
+        // The median of all four is 101.5; 150 is far outside 5% of it.
+        let point = aggregate(
+            vec![
+                fresh("a", "100"),
+                fresh("b", "101"),
+                fresh("c", "102"),
+                fresh("d", "150"),
+            ],
+            3,
+        )
+        .await
+        .unwrap();
+        assert_eq!(point.rate, Rate::parse("101").unwrap());
+        assert_eq!(point.source, "median(a,b,c)");
+    }
+
+    #[tokio::test]
+    async fn too_few_agreeing_sources_is_an_error() {
+        let message =
+            unavailable_message(aggregate(vec![fresh("a", "100"), fresh("b", "200")], 2).await);
+        assert!(message.contains("only 0 of 2"), "{message}");
+
+        let message = unavailable_message(aggregate(vec![fresh("a", "100"), down("b")], 2).await);
+        assert!(message.contains("only 1 of 2"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn no_fresh_source_is_an_error() {
+        let message = unavailable_message(aggregate(vec![stale("a", "100"), down("b")], 1).await);
+        assert_eq!(message, "no fresh price for SOL");
+        let message = unavailable_message(aggregate(Vec::new(), 1).await);
+        assert_eq!(message, "no fresh price for SOL");
+    }
+}
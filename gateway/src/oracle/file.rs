@@ -0,0 +1,60 @@
+use super::{rate_from_json, unavailable, PriceOracle, PricePoint};
+use crate::error::AppError;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Prices written to a JSON file by an external job, re-read on every lookup:
+///
+/// ```json
+/// { "USDC": { "rate": "83.61", "asOf": "2026-02-10T19:00:00Z" }, "SOL": { "rate": 12500 } }
+/// ```
+///
+/// Entries without `asOf` are dated by the file's modification time.
+#[derive(Debug, Clone)]
+pub struct FileOracle {
+    path: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FileEntry {
+    rate: Value,
+    #[serde(rename = "asOf")]
+    as_of: Option<DateTime<Utc>>,
+}
+
+impl FileOracle {
+    pub fn new(path: String) -> Self {
+        Self { path }
+    }
+}
+
+#[async_trait]
+impl PriceOracle for FileOracle {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    async fn price(&self, token: &str) -> Result<PricePoint, AppError> {
+        let read_failed =
+            |err: std::io::Error| unavailable(format!("price file unreadable: {err}"));
+        let contents = tokio::fs::read(&self.path).await.map_err(read_failed)?;
+        let modified = tokio::fs::metadata(&self.path)
+            .await
+            .and_then(|meta| meta.modified())
+            .map_err(read_failed)?;
+
+        let mut entries: HashMap<String, FileEntry> = serde_json::from_slice(&contents)
+            .map_err(|err| unavailable(format!("price file is invalid: {err}")))?;
+        let entry = entries
+            .remove(token)
+            .ok_or_else(|| unavailable(format!("price file has no entry for {token}")))?;
+        Ok(PricePoint {
+            rate: rate_from_json(&entry.rate).map_err(unavailable)?,
+            as_of: entry.as_of.unwrap_or_else(|| modified.into()),
+            source: self.name().to_string(),
+        })
+    }
+}
@@ -0,0 +1,50 @@
+use super::{unavailable, PriceOracle, PricePoint};
+use crate::error::AppError;
+use crate::money::Rate;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::HashMap;
+
+/// Static rates from configuration; always fresh. Useful for local development and as a floor source.
+#[derive(Debug, Clone)]
+pub struct FixedOracle {
+    rates: HashMap<String, Rate>,
+}
+
+impl FixedOracle {
+    /// Parses `USDC=83.61,SOL=12500`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut rates = HashMap::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (symbol, rate) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("`{entry}` is not SYMBOL=RATE"))?;
+            let rate = Rate::parse(rate)?;
+            if rate.scaled() == 0 {
+                return Err(format!("rate for {symbol} is zero"));
+            }
+            rates.insert(symbol.trim().to_uppercase(), rate);
+        }
+        Ok(Self { rates })
+    }
+}
+
+#[async_trait]
+impl PriceOracle for FixedOracle {
+    fn name(&self) -> &str {
+        "fixed"
+    }
+
+    async fn price(&self, token: &str) -> Result<PricePoint, AppError> {
+        let rate = self
+            .rates
+            .get(token)
+            .copied()
+            .ok_or_else(|| unavailable(format!("no fixed rate for {token}")))?;
+        Ok(PricePoint {
+            rate,
+            as_of: Utc::now(),
+            source: self.name().to_string(),
+        })
+    }
+}
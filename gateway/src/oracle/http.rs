@@ -0,0 +1,168 @@
+use super::{rate_from_json, unavailable, PriceOracle, PricePoint};
+use crate::error::AppError;
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::Value;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Fetches a JSON document per token and reads the INR price at a JSON pointer.
+///
+/// `{token}` in both the URL and the pointer is replaced with the token symbol, e.g.
+/// `https://prices.example/v1/inr?symbol={token}` with pointer `/data/{token}/price`.
+#[derive(Debug, Clone)]
+pub struct HttpOracle {
+    client: reqwest::Client,
+    url: String,
+    pointer: String,
+}
+
+impl HttpOracle {
+    pub fn new(url: String, pointer: String) -> anyhow::Result<Self> {
+        Self::with_timeout(url, pointer, REQUEST_TIMEOUT)
+    }
+
+    fn with_timeout(url: String, pointer: String, timeout: Duration) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(Self {
+            client,
+            url,
+            pointer,
+        })
+    }
+}
+
+#[async_trait]
+impl PriceOracle for HttpOracle {
+    fn name(&self) -> &str {
+        "http"
+    }
+
+    async fn price(&self, token: &str) -> Result<PricePoint, AppError> {
+        let url = self.url.replace("{token}", token);
+        let body: Value = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| unavailable(format!("http price source failed: {err}")))?
+            .json()
+            .await
+            .map_err(|err| unavailable(format!("http price source sent invalid json: {err}")))?;
+
+        let pointer = self.pointer.replace("{token}", token);
+        let rate = body
+            .pointer(&pointer)
+            .ok_or_else(|| unavailable(format!("http price source has no value at {pointer}")))
+            .and_then(|value| rate_from_json(value).map_err(unavailable))?;
+        Ok(PricePoint {
+            rate,
+            as_of: Utc::now(),
+            source: self.name().to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::Rate;
+    use axum::extract::Path;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use serde_json::json;
+
+    /// Serves canned price responses on an ephemeral local port; returns its base URL.
+    async fn stub() -> String {
+        let router = Router::new()
+            .route(
+                "/prices/:token",
+                get(|Path(token): Path<String>| async move {
+                    Json(json!({ "data": { token: { "price": "13250.125" } } }))
+                }),
+            )
+            .route("/number", get(|| async { Json(json!({ "price": 83.61 })) }))
+            .route("/zero", get(|| async { Json(json!({ "price": "0" })) }))
+            .route(
+                "/down",
+                get(|| async { (StatusCode::SERVICE_UNAVAILABLE, "maintenance") }),
+            )
+            .route("/html", get(|| async { "<html>not a price</html>" }))
+            .route("/object", get(|| async { Json(json!({ "price": {} })) }))
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Json(json!({ "price": "1" }))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        format!("http://{addr}")
+    }
+
+    async fn price(path: &str, pointer: &str) -> Result<PricePoint, AppError> {
+        let oracle = HttpOracle::with_timeout(
+            format!("{}{path}", stub().await),
+            pointer.to_string(),
+            Duration::from_millis(200),
+        )
+        .unwrap();
+        oracle.price("SOL").await
+    }
+
+    fn unavailable_message(result: Result<PricePoint, AppError>) -> String {
+        match result {
+            Err(AppError::Coded {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                code: "PRICE_UNAVAILABLE",
+                message,
+            }) => message,
+            other => panic!("expected PRICE_UNAVAILABLE, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_the_price_at_the_pointer() {
+        let point = price("/prices/{token}", "/data/{token}/price")
+            .await
+            .unwrap();
+        assert_eq!(point.rate, Rate::parse("13250.125").unwrap());
+        assert_eq!(point.source, "http");
+
+        let point = price("/number", "/price").await.unwrap();
+        assert_eq!(point.rate, Rate::parse("83.61").unwrap());
+    }
+
+    #[tokio::test]
+    async fn non_success_status_is_unavailable() {
+        let message = unavailable_message(price("/down", "/price").await);
+        assert!(message.contains("503"), "{message}");
+        let message = unavailable_message(price("/missing", "/price").await);
+        assert!(message.contains("404"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn malformed_bodies_are_unavailable() {
+        let message = unavailable_message(price("/html", "/price").await);
+        assert!(message.contains("invalid json"), "{message}");
+        let message = unavailable_message(price("/number", "/data/SOL/price").await);
+        assert!(message.contains("no value at /data/SOL/price"), "{message}");
+        let message = unavailable_message(price("/object", "/price").await);
+        assert!(message.contains("expected a price"), "{message}");
+        let message = unavailable_message(price("/zero", "/price").await);
+        assert!(message.contains("zero"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn slow_sources_time_out() {
+        let started = std::time::Instant::now();
+        let message = unavailable_message(price("/slow", "/price").await);
+        assert!(message.contains("http price source failed"), "{message}");
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,96 @@
+mod aggregate;
+mod file;
+mod fixed;
+mod http;
+
+pub use aggregate::MedianOracle;
+pub use file::FileOracle;
+pub use fixed::FixedOracle;
+pub use http::HttpOracle;
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::money::Rate;
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// INR price of one whole token as reported by a source.
+#[derive(Debug, Clone)]
+pub struct PricePoint {
+    pub rate: Rate,
+    pub as_of: DateTime<Utc>,
+    /// Source name, or the contributing sources for an aggregate.
+    pub source: String,
+}
+
+#[async_trait]
+pub trait PriceOracle: Send + Sync {
+    fn name(&self) -> &str;
+    async fn price(&self, token: &str) -> Result<PricePoint, AppError>;
+}
+
+pub(crate) fn unavailable(message: impl Into<String>) -> AppError {
+    AppError::coded(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "PRICE_UNAVAILABLE",
+        message,
+    )
+}
+
+/// Reads a rate that a source may encode as a JSON string or number.
+pub(crate) fn rate_from_json(value: &Value) -> Result<Rate, String> {
+    let rate = match value {
+        Value::String(raw) => Rate::parse(raw)?,
+        Value::Number(number) => Rate::parse(&number.to_string())?,
+        other => return Err(format!("expected a price, found {other}")),
+    };
+    if rate.scaled() == 0 {
+        return Err("price is zero".to_string());
+    }
+    Ok(rate)
+}
+
+/// Builds the median aggregator over the sources named in `MONOPAY_ORACLE_SOURCES`.
+pub fn from_config(config: &Config) -> anyhow::Result<Arc<dyn PriceOracle>> {
+    let mut sources: Vec<Arc<dyn PriceOracle>> = Vec::new();
+    for name in &config.oracle_sources {
+        match name.as_str() {
+            "fixed" => sources.push(Arc::new(
+                FixedOracle::parse(&config.oracle_fixed_rates)
+                    .map_err(|err| anyhow::anyhow!("invalid MONOPAY_ORACLE_FIXED_RATES: {err}"))?,
+            )),
+            "http" => {
+                let url = config
+                    .oracle_http_url
+                    .clone()
+                    .context("MONOPAY_ORACLE_HTTP_URL is required for the http source")?;
+                sources.push(Arc::new(HttpOracle::new(
+                    url,
+                    config.oracle_http_pointer.clone(),
+                )?));
+            }
+            "file" => {
+                let path = config
+                    .oracle_file_path
+                    .clone()
+                    .context("MONOPAY_ORACLE_FILE is required for the file source")?;
+                sources.push(Arc::new(FileOracle::new(path)));
+            }
+            other => bail!("unknown price source `{other}`"),
+        }
+    }
+    if sources.is_empty() {
+        bail!("MONOPAY_ORACLE_SOURCES must name at least one source");
+    }
+
+    Ok(Arc::new(MedianOracle {
+        sources,
+        max_age: chrono::Duration::seconds(config.oracle_max_age_secs),
+        max_deviation_bps: config.oracle_max_deviation_bps,
+        min_sources: config.oracle_min_sources,
+    }))
+}
@@ -0,0 +1,61 @@
+use crate::error::AppError;
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use axum::response::{IntoResponse, Response};
+use image::{ImageFormat, Luma};
+use qrcode::render::svg;
+use qrcode::{EcLevel, QrCode};
+use std::io::Cursor;
+
+const DEFAULT_SIZE: u32 = 512;
+const MIN_SIZE: u32 = 128;
+const MAX_SIZE: u32 = 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QrFormat {
+    Svg,
+    Png,
+}
+
+impl QrFormat {
+    pub fn parse(value: Option<&str>) -> Result<Self, AppError> {
+        match value.map(str::to_ascii_lowercase).as_deref() {
+            None | Some("svg") => Ok(Self::Svg),
+            Some("png") => Ok(Self::Png),
+            Some(other) => Err(AppError::bad_request(format!(
+                "format `{other}` is not supported, use svg or png"
+            ))),
+        }
+    }
+}
+
+/// Renders `data` as a QR code image response at least `size` pixels wide, with medium error
+/// correction so long Solana Pay URLs stay at a scannable module size.
+pub fn render(data: &str, format: QrFormat, size: Option<u32>) -> Result<Response, AppError> {
+    let size = size.unwrap_or(DEFAULT_SIZE).clamp(MIN_SIZE, MAX_SIZE);
+    let code = QrCode::with_error_correction_level(data.as_bytes(), EcLevel::M)
+        .map_err(|err| AppError::bad_request(format!("cannot encode QR code: {err}")))?;
+
+    let (content_type, body) = match format {
+        QrFormat::Svg => {
+            let image = code
+                .render::<svg::Color>()
+                .min_dimensions(size, size)
+                .build();
+            ("image/svg+xml", image.into_bytes())
+        }
+        QrFormat::Png => {
+            let image = code.render::<Luma<u8>>().min_dimensions(size, size).build();
+            let mut png = Vec::new();
+            image
+                .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+                .map_err(|err| AppError::internal(format!("failed to encode png: {err}")))?;
+            ("image/png", png)
+        }
+    };
+    // Intent codes go stale once the intent is paid or expires.
+    Ok((
+        [(CONTENT_TYPE, content_type), (CACHE_CONTROL, "no-store")],
+        body,
+    )
+        .into_response())
+}
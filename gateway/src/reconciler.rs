@@ -0,0 +1,699 @@
+use crate::error::AppError;
+use crate::lifecycle::{self, IntentStatus};
+use crate::money::TokenAmount;
+use crate::registry::TokenInfo;
+use crate::routes::payment_intents;
+use crate::solana::{
+    Commitment, ConfirmedTransaction, Pubkey, SignatureStatus, TokenBalance, MAX_STATUS_BATCH,
+};
+use crate::state::{AppState, PaymentIntentRecord};
+use crate::store::Mutation;
+use chrono::{DateTime, Duration, Utc};
+use tracing::{debug, warn};
+
+/// Periodically matches open intents against the chain; see [`reconcile`].
+pub fn spawn_reconciler(
+    state: AppState,
+    every: std::time::Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(every);
+        loop {
+            ticker.tick().await;
+            match reconcile(&state, Utc::now()).await {
+                Ok(0) => {}
+                Ok(changed) => debug!("applied {changed} payment intent updates from chain"),
+                Err(err) => warn!("payment reconciliation failed: {err:?}"),
+            }
+        }
+    })
+}
+
+/// Checks that `transaction` pays what `intent` asks, like Solana Pay's `validateTransfer`:
+/// it succeeded, carries the intent's reference, and the recipient's balance of the intent's
+/// token grew by at least the intent amount.
+pub fn verify_transfer(
+    intent: &PaymentIntentRecord,
+    token: &TokenInfo,
+    transaction: &ConfirmedTransaction,
+) -> Result<(), String> {
+    if let Some(err) = &transaction.err {
+        return Err(format!("transaction failed: {err}"));
+    }
+    if !transaction.account_keys.contains(&intent.reference) {
+        return Err("transaction does not carry the intent reference".to_string());
+    }
+
+    let received = match &token.mint {
+        None => {
+            let index = transaction
+                .account_keys
+                .iter()
+                .position(|key| *key == intent.recipient_wallet)
+                .ok_or("recipient is not part of the transaction")?;
+            let pre = transaction.pre_balances.get(index).copied().unwrap_or(0);
+            let post = transaction.post_balances.get(index).copied().unwrap_or(0);
+            post.saturating_sub(pre)
+        }
+        Some(mint) => {
+            // Token accounts created by the transaction have no pre balance.
+            let held = |balances: &[TokenBalance]| -> u64 {
+                balances
+                    .iter()
+                    .filter(|balance| {
+                        balance.mint == *mint
+                            && balance.owner.as_deref() == Some(intent.recipient_wallet.as_str())
+                    })
+                    .map(|balance| balance.amount)
+                    .sum()
+            };
+            held(&transaction.post_token_balances)
+                .saturating_sub(held(&transaction.pre_token_balances))
+        }
+    };
+    if received < intent.token_amount.base_units() {
+        return Err(format!(
+            "recipient received {} {} of {}",
+            TokenAmount::new(received, intent.token_amount.decimals()),
+            intent.token,
+            intent.token_amount
+        ));
+    }
+    Ok(())
+}
+
+/// Applies `mutation` to the intent; `false` when the intent has moved on in the meantime.
+async fn advance(
+    state: &AppState,
+    id: &str,
+    mutation: Mutation<'_, PaymentIntentRecord>,
+) -> Result<bool, AppError> {
+    match state.store.update_payment_intent(id, mutation).await {
+        Ok(intent) => {
+            state.events.publish(&intent);
+            Ok(true)
+        }
+        Err(AppError::Coded {
+            code: "INVALID_TRANSITION",
+            ..
+        }) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Fails the intent and gives any session spend it reserved back, since nothing was paid;
+/// `false` when the intent has moved on in the meantime.
+async fn fail(
+    state: &AppState,
+    intent: &PaymentIntentRecord,
+    reason: String,
+    now: DateTime<Utc>,
+) -> Result<bool, AppError> {
+    let mutation: Mutation<'_, PaymentIntentRecord> = Box::new(move |intent| {
+        lifecycle::transition(intent, IntentStatus::Failed, Some(reason), now)
+    });
+    match payment_intents::close_unpaid(state, intent, mutation).await {
+        Ok(_) => Ok(true),
+        Err(AppError::Coded {
+            code: "INVALID_TRANSITION",
+            ..
+        }) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+fn configured_token<'a>(
+    state: &'a AppState,
+    intent: &PaymentIntentRecord,
+) -> Option<&'a TokenInfo> {
+    let token = state.registry.get(&intent.token);
+    if token.is_none() {
+        warn!(
+            "payment intent {} is in {}, which is no longer configured",
+            intent.id, intent.token
+        );
+    }
+    token
+}
+
+/// Looks for a transfer paying an open intent by its reference and marks the intent submitted.
+async fn find_payment(
+    state: &AppState,
+    intent: &PaymentIntentRecord,
+    now: DateTime<Utc>,
+) -> Result<bool, AppError> {
+    // Intents from before references were keys cannot be found on chain.
+    if Pubkey::parse(&intent.reference).is_err() {
+        return Ok(false);
+    }
+    let Some(token) = configured_token(state, intent) else {
+        return Ok(false);
+    };
+    let signatures = state.rpc.signatures_for_address(&intent.reference).await?;
+    // Oldest first, so a retry after an underpayment does not hide the first valid transfer.
+    for candidate in signatures.iter().rev().filter(|entry| entry.err.is_none()) {
+        let Some(transaction) = state.rpc.transaction(&candidate.signature).await? else {
+            continue;
+        };
+        if let Err(reason) = verify_transfer(intent, token, &transaction) {
+            debug!(
+                "{} does not pay payment intent {}: {reason}",
+                candidate.signature, intent.id
+            );
+            continue;
+        }
+        let signature = candidate.signature.clone();
+        return advance(
+            state,
+            &intent.id,
+            Box::new(move |intent| {
+                intent.signature = Some(signature);
+                lifecycle::transition(
+                    intent,
+                    IntentStatus::Submitted,
+                    Some("payment found on chain".to_string()),
+                    now,
+                )
+            }),
+        )
+        .await;
+    }
+    Ok(false)
+}
+
+/// Moves a submitted or confirmed intent along with its signature's status.
+async fn settle(
+    state: &AppState,
+    intent: &PaymentIntentRecord,
+    signature: &str,
+    status: Option<SignatureStatus>,
+    now: DateTime<Utc>,
+) -> Result<bool, AppError> {
+    let status = match status {
+        Some(status) => status,
+        None => {
+            let submitted_at = intent
+                .events
+                .iter()
+                .rev()
+                .find(|event| event.status == IntentStatus::Submitted)
+                .map_or(now, |event| event.at);
+            let drop_after = Duration::seconds(state.config.reconcile_drop_after_secs);
+            if intent.status != IntentStatus::Submitted || now - submitted_at < drop_after {
+                return Ok(false);
+            }
+            let reason = "transaction was never seen on chain".to_string();
+            return fail(state, intent, reason, now).await;
+        }
+    };
+    if let Some(err) = status.err {
+        return fail(state, intent, format!("transaction failed: {err}"), now).await;
+    }
+    let finalized = status.confirmation == Commitment::Finalized;
+    match (intent.status, status.confirmation) {
+        (_, Commitment::Processed) => return Ok(false),
+        (IntentStatus::Confirmed, _) if !finalized => return Ok(false),
+        (IntentStatus::Submitted, _) => {
+            // The signature may have come from outside, e.g. a webhook; check what it paid.
+            let Some(token) = configured_token(state, intent) else {
+                return Ok(false);
+            };
+            let Some(transaction) = state.rpc.transaction(signature).await? else {
+                return Ok(false);
+            };
+            if let Err(reason) = verify_transfer(intent, token, &transaction) {
+                let reason = format!("transaction does not match the payment intent: {reason}");
+                return fail(state, intent, reason, now).await;
+            }
+        }
+        _ => {}
+    }
+    advance(
+        state,
+        &intent.id,
+        Box::new(move |intent| {
+            if intent.status == IntentStatus::Submitted {
+                lifecycle::transition(intent, IntentStatus::Confirmed, None, now)?;
+            }
+            if finalized {
+                lifecycle::transition(intent, IntentStatus::Finalized, None, now)?;
+            }
+            Ok(())
+        }),
+    )
+    .await
+}
+
+/// Applies a transaction an indexer saw land at `confirmed` commitment to an intent whose
+/// reference it carries. A matching payment submits and confirms an open intent; a failed or
+/// short one only fails the intent when it is the transaction already submitted for it.
+pub async fn record_transfer(
+    state: &AppState,
+    intent: &PaymentIntentRecord,
+    transaction: &ConfirmedTransaction,
+    now: DateTime<Utc>,
+) -> Result<bool, AppError> {
+    let ours = intent.signature.as_deref() == Some(transaction.signature.as_str());
+    let open = matches!(
+        intent.status,
+        IntentStatus::Created | IntentStatus::Authorized
+    );
+    let resubmitted = ours && intent.status == IntentStatus::Submitted;
+    if !(open || resubmitted) {
+        return Ok(false);
+    }
+    let Some(token) = configured_token(state, intent) else {
+        return Ok(false);
+    };
+    if let Err(reason) = verify_transfer(intent, token, transaction) {
+        if !ours {
+            debug!(
+                "{} does not pay payment intent {}: {reason}",
+                transaction.signature, intent.id
+            );
+            return Ok(false);
+        }
+        let reason = format!("transaction does not match the payment intent: {reason}");
+        return fail(state, intent, reason, now).await;
+    }
+
+    let signature = transaction.signature.clone();
+    advance(
+        state,
+        &intent.id,
+        Box::new(move |intent| {
+            if matches!(
+                intent.status,
+                IntentStatus::Created | IntentStatus::Authorized
+            ) {
+                intent.signature = Some(signature);
+                lifecycle::transition(
+                    intent,
+                    IntentStatus::Submitted,
+                    Some("payment reported by indexer".to_string()),
+                    now,
+                )?;
+            } else if intent.signature != Some(signature) {
+                // Another transaction was submitted for the intent in the meantime.
+                return Err(lifecycle::invalid_transition(
+                    intent.status,
+                    IntentStatus::Confirmed,
+                ));
+            }
+            lifecycle::transition(intent, IntentStatus::Confirmed, None, now)
+        }),
+    )
+    .await
+}
+
+/// One reconciliation pass: open intents are matched to transfers carrying their reference,
+/// and submitted ones follow their signature to confirmed, finalized or failed. Returns how
+/// many intents changed.
+pub async fn reconcile(state: &AppState, now: DateTime<Utc>) -> Result<usize, AppError> {
+    let mut changed = 0;
+    let open = state
+        .store
+        .payment_intents_with_status(&[IntentStatus::Created, IntentStatus::Authorized])
+        .await?;
+    for intent in &open {
+        changed += usize::from(find_payment(state, intent, now).await?);
+    }
+
+    let landed: Vec<(PaymentIntentRecord, String)> = state
+        .store
+        .payment_intents_with_status(&[IntentStatus::Submitted, IntentStatus::Confirmed])
+        .await?
+        .into_iter()
+        .filter_map(|intent| {
+            let signature = intent.signature.clone()?;
+            Some((intent, signature))
+        })
+        .collect();
+    for batch in landed.chunks(MAX_STATUS_BATCH) {
+        let signatures: Vec<String> = batch
+            .iter()
+            .map(|(_, signature)| signature.clone())
+            .collect();
+        let statuses = state.rpc.signature_statuses(&signatures).await?;
+        for ((intent, signature), status) in batch.iter().zip(statuses) {
+            changed += usize::from(settle(state, intent, signature, status, now).await?);
+        }
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::InMemoryStore;
+    use crate::testing::{self, key, Ledger};
+    use serde_json::json;
+    use std::sync::Arc;
+    use IntentStatus::*;
+
+    const USDC_MINT: &str = "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU";
+
+    struct Chain {
+        ledger: Ledger,
+        state: AppState,
+        payer: String,
+        recipient: String,
+    }
+
+    impl Chain {
+        fn new() -> Self {
+            let ledger = Ledger::new();
+            let state = testing::state_with(Arc::new(InMemoryStore::default()), ledger.path());
+            Self {
+                ledger,
+                state,
+                payer: key(1).to_string(),
+                recipient: key(2).to_string(),
+            }
+        }
+
+        /// A SOL intent of ₹10 (10^7 lamports) whose reference is `key(reference)`.
+        async fn intent(&self, id: &str, reference: u8) -> PaymentIntentRecord {
+            let mut intent = testing::intent(id, "alice", &self.recipient, 1_000, Utc::now());
+            intent.payer_wallet = self.payer.clone();
+            intent.reference = key(reference).to_string();
+            intent.token_amount = TokenAmount::new(10_000_000, 9);
+            self.state
+                .store
+                .insert_payment_intent(intent.clone())
+                .await
+                .unwrap();
+            intent
+        }
+
+        async fn usdc_intent(&self, id: &str, reference: u8) -> PaymentIntentRecord {
+            let mut intent = testing::intent(id, "alice", &self.recipient, 1_000, Utc::now());
+            intent.reference = key(reference).to_string();
+            intent.token = "USDC".to_string();
+            intent.token_amount = TokenAmount::new(119_604, 6);
+            self.state
+                .store
+                .insert_payment_intent(intent.clone())
+                .await
+                .unwrap();
+            intent
+        }
+
+        /// Authorizes the intent against alice's session, reserving its amount of the daily limit.
+        async fn authorize(&self, intent: &PaymentIntentRecord) {
+            testing::put_session(&self.state, "ses_1", 0).await;
+            testing::authorize(&self.state, &intent.id, Utc::now()).await;
+        }
+
+        /// Marks the intent submitted with `signature`, as a wallet or webhook would.
+        async fn submit(&self, intent: &PaymentIntentRecord, signature: &str) {
+            let signature = signature.to_string();
+            self.state
+                .store
+                .update_payment_intent(
+                    &intent.id,
+                    Box::new(move |intent| {
+                        intent.signature = Some(signature);
+                        lifecycle::transition(intent, IntentStatus::Submitted, None, Utc::now())
+                    }),
+                )
+                .await
+                .unwrap();
+        }
+
+        async fn reconcile(&self) -> usize {
+            reconcile(&self.state, Utc::now()).await.unwrap()
+        }
+
+        async fn reconcile_at(&self, now: DateTime<Utc>) -> usize {
+            reconcile(&self.state, now).await.unwrap()
+        }
+
+        /// The intent's statuses so far, oldest first, and its signature.
+        async fn history(&self, id: &str) -> (Vec<IntentStatus>, Option<String>) {
+            let intent = self.state.store.payment_intent(id).await.unwrap().unwrap();
+            let statuses = intent.events.iter().map(|event| event.status).collect();
+            (statuses, intent.signature)
+        }
+
+        async fn last_reason(&self, id: &str) -> String {
+            let intent = self.state.store.payment_intent(id).await.unwrap().unwrap();
+            intent
+                .events
+                .last()
+                .unwrap()
+                .reason
+                .clone()
+                .unwrap_or_default()
+        }
+
+        async fn used_today(&self) -> u64 {
+            testing::used_today(&self.state).await
+        }
+    }
+
+    #[tokio::test]
+    async fn confirmed_payment_settles_and_then_finalizes() {
+        let mut chain = Chain::new();
+        let intent = chain.intent("pi_1", 3).await;
+        chain.authorize(&intent).await;
+        let (payer, recipient, reference) = (
+            chain.payer.clone(),
+            chain.recipient.clone(),
+            intent.reference.clone(),
+        );
+        let signature = chain.ledger.land_sol(
+            &payer,
+            &recipient,
+            &reference,
+            10_000_000,
+            "confirmed",
+            None,
+        );
+
+        // Found and then confirmed in the same pass.
+        assert_eq!(chain.reconcile().await, 2);
+        assert_eq!(
+            chain.history("pi_1").await,
+            (
+                vec![Created, Authorized, Submitted, Confirmed],
+                Some(signature.clone())
+            )
+        );
+        assert_eq!(chain.used_today().await, 1_000);
+        assert_eq!(chain.reconcile().await, 0);
+
+        chain.ledger.set_status(&signature, "finalized");
+        assert_eq!(chain.reconcile().await, 1);
+        assert_eq!(
+            chain.history("pi_1").await.0,
+            vec![Created, Authorized, Submitted, Confirmed, Finalized]
+        );
+        assert_eq!(chain.used_today().await, 1_000);
+        assert_eq!(chain.reconcile().await, 0);
+    }
+
+    #[tokio::test]
+    async fn finalized_payment_goes_all_the_way_in_one_pass() {
+        let mut chain = Chain::new();
+        let intent = chain.usdc_intent("pi_1", 3).await;
+        let (payer, recipient) = (chain.payer.clone(), chain.recipient.clone());
+        let signature = chain.ledger.land_token(
+            &payer,
+            &recipient,
+            &intent.reference,
+            USDC_MINT,
+            119_604,
+            "finalized",
+        );
+
+        assert_eq!(chain.reconcile().await, 2);
+        assert_eq!(
+            chain.history("pi_1").await,
+            (
+                vec![Created, Submitted, Confirmed, Finalized],
+                Some(signature)
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn processed_transactions_wait() {
+        let mut chain = Chain::new();
+        let intent = chain.intent("pi_1", 3).await;
+        let (payer, recipient) = (chain.payer.clone(), chain.recipient.clone());
+        let signature = chain.ledger.land_sol(
+            &payer,
+            &recipient,
+            &intent.reference,
+            10_000_000,
+            "processed",
+            None,
+        );
+
+        assert_eq!(chain.reconcile().await, 1);
+        assert_eq!(chain.history("pi_1").await.0, vec![Created, Submitted]);
+        chain.ledger.set_status(&signature, "confirmed");
+        assert_eq!(chain.reconcile().await, 1);
+        assert_eq!(
+            chain.history("pi_1").await.0,
+            vec![Created, Submitted, Confirmed]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_transaction_fails_the_intent_and_releases_the_spend() {
+        let mut chain = Chain::new();
+        let intent = chain.intent("pi_1", 3).await;
+        chain.authorize(&intent).await;
+        let (payer, recipient) = (chain.payer.clone(), chain.recipient.clone());
+        let err = json!({ "InstructionError": [0, { "Custom": 1 }] });
+        let signature = chain.ledger.land_sol(
+            &payer,
+            &recipient,
+            &intent.reference,
+            10_000_000,
+            "confirmed",
+            Some(err),
+        );
+
+        // A failed transaction is never taken for the payment.
+        assert_eq!(chain.reconcile().await, 0);
+        assert_eq!(chain.history("pi_1").await.0, vec![Created, Authorized]);
+        assert_eq!(chain.used_today().await, 1_000);
+
+        // Once submitted by the wallet, its failure fails the intent.
+        chain.submit(&intent, &signature).await;
+        assert_eq!(chain.reconcile().await, 1);
+        assert_eq!(
+            chain.history("pi_1").await.0,
+            vec![Created, Authorized, Submitted, Failed]
+        );
+        assert!(chain
+            .last_reason("pi_1")
+            .await
+            .starts_with("transaction failed:"));
+        assert_eq!(chain.used_today().await, 0);
+        assert_eq!(chain.reconcile().await, 0);
+    }
+
+    #[tokio::test]
+    async fn underpayment_is_not_taken_for_the_payment() {
+        let mut chain = Chain::new();
+        let intent = chain.intent("pi_1", 3).await;
+        chain.authorize(&intent).await;
+        let (payer, recipient) = (chain.payer.clone(), chain.recipient.clone());
+        let short = chain.ledger.land_sol(
+            &payer,
+            &recipient,
+            &intent.reference,
+            9_999_999,
+            "confirmed",
+            None,
+        );
+
+        assert_eq!(chain.reconcile().await, 0);
+        assert_eq!(chain.history("pi_1").await.0, vec![Created, Authorized]);
+
+        // A full payment after the short one still settles the intent.
+        let full = chain.ledger.land_sol(
+            &payer,
+            &recipient,
+            &intent.reference,
+            10_000_000,
+            "confirmed",
+            None,
+        );
+        assert_eq!(chain.reconcile().await, 2);
+        assert_eq!(chain.history("pi_1").await.1, Some(full));
+
+        // A short transfer submitted for another intent fails it instead.
+        let other = chain.intent("pi_2", 4).await;
+        let short_other =
+            chain
+                .ledger
+                .land_sol(&payer, &recipient, &other.reference, 1, "confirmed", None);
+        chain.submit(&other, &short_other).await;
+        assert_eq!(chain.reconcile().await, 1);
+        assert_eq!(
+            chain.history("pi_2").await.0,
+            vec![Created, Submitted, Failed]
+        );
+        assert!(chain
+            .last_reason("pi_2")
+            .await
+            .contains("recipient received 0.000000001 SOL of 0.010000000"));
+        assert_ne!(short, short_other);
+    }
+
+    #[tokio::test]
+    async fn wrong_mint_or_recipient_is_not_taken_for_the_payment() {
+        let mut chain = Chain::new();
+        let usdc = chain.usdc_intent("pi_1", 3).await;
+        let sol = chain.intent("pi_2", 4).await;
+        let (payer, recipient) = (chain.payer.clone(), chain.recipient.clone());
+        let stranger = key(9).to_string();
+        let other_mint = key(8).to_string();
+
+        chain.ledger.land_token(
+            &payer,
+            &recipient,
+            &usdc.reference,
+            &other_mint,
+            119_604,
+            "confirmed",
+        );
+        chain.ledger.land_sol(
+            &payer,
+            &stranger,
+            &sol.reference,
+            10_000_000,
+            "confirmed",
+            None,
+        );
+        assert_eq!(chain.reconcile().await, 0);
+        assert_eq!(chain.history("pi_1").await.0, vec![Created]);
+        assert_eq!(chain.history("pi_2").await.0, vec![Created]);
+
+        // Submitted from outside, the mismatch fails the intent.
+        let wrong = chain.ledger.land_token(
+            &payer,
+            &stranger,
+            &usdc.reference,
+            USDC_MINT,
+            119_604,
+            "confirmed",
+        );
+        chain.submit(&usdc, &wrong).await;
+        assert_eq!(chain.reconcile().await, 1);
+        assert_eq!(
+            chain.history("pi_1").await.0,
+            vec![Created, Submitted, Failed]
+        );
+        assert!(chain
+            .last_reason("pi_1")
+            .await
+            .starts_with("transaction does not match the payment intent"));
+    }
+
+    #[tokio::test]
+    async fn unknown_signature_fails_after_the_drop_window() {
+        let chain = Chain::new();
+        let intent = chain.intent("pi_1", 3).await;
+        chain.authorize(&intent).await;
+        chain.submit(&intent, "never-landed").await;
+
+        assert_eq!(chain.reconcile().await, 0);
+        let drop_after = Duration::seconds(chain.state.config.reconcile_drop_after_secs);
+        assert_eq!(chain.reconcile_at(Utc::now() + drop_after).await, 1);
+        assert_eq!(
+            chain.history("pi_1").await.0,
+            vec![Created, Authorized, Submitted, Failed]
+        );
+        assert_eq!(
+            chain.last_reason("pi_1").await,
+            "transaction was never seen on chain"
+        );
+        assert_eq!(chain.used_today().await, 0);
+    }
+}
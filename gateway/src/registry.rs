@@ -0,0 +1,284 @@
+use crate::config::{Cluster, Config};
+use crate::error::AppError;
+use anyhow::{bail, Context};
+use axum::http::StatusCode;
+use serde::Deserialize;
+
+/// Registry shipped with the gateway, used unless `MONOPAY_TOKEN_REGISTRY` names another file.
+const DEFAULT_REGISTRY: &str = include_str!("../tokens.json");
+
+/// Wrapped SOL mint, used to link native SOL on the explorer.
+const NATIVE_SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// Program that owns classic SPL mints; Token-2022 mints must name their program explicitly.
+pub const SPL_TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+/// A token the gateway can quote and settle in on the configured cluster.
+#[derive(Debug, Clone)]
+pub struct TokenInfo {
+    pub symbol: String,
+    /// SPL mint address; `None` for native SOL.
+    pub mint: Option<String>,
+    /// Program owning the mint; `None` for native SOL.
+    pub token_program: Option<String>,
+    pub decimals: u8,
+    pub cluster: Cluster,
+    pub enabled: bool,
+}
+
+impl TokenInfo {
+    /// Explorer page for the mint, or for wrapped SOL when native.
+    pub fn explorer_url(&self) -> String {
+        let mint = self.mint.as_deref().unwrap_or(NATIVE_SOL_MINT);
+        self.cluster.explorer_url(&format!("token/{mint}"))
+    }
+}
+
+/// One entry of the registry file:
+///
+/// ```json
+/// { "symbol": "USDC", "mint": "4zMM...", "decimals": 6, "cluster": "devnet", "enabled": true }
+/// ```
+///
+/// `mint` is omitted for native SOL and `tokenProgram` defaults to the classic SPL token program.
+#[derive(Debug, Deserialize)]
+struct RegistryEntry {
+    symbol: String,
+    mint: Option<String>,
+    #[serde(rename = "tokenProgram")]
+    token_program: Option<String>,
+    decimals: u8,
+    cluster: String,
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// Tokens configured for the gateway's cluster, in registry file order.
+#[derive(Debug, Clone)]
+pub struct TokenRegistry {
+    tokens: Vec<TokenInfo>,
+}
+
+pub fn unsupported_token(message: impl Into<String>) -> AppError {
+    AppError::coded(StatusCode::BAD_REQUEST, "UNSUPPORTED_TOKEN", message)
+}
+
+fn ensure_pubkey(field: &str, value: &str) -> anyhow::Result<()> {
+    let bytes = bs58::decode(value)
+        .into_vec()
+        .with_context(|| format!("{field} `{value}` is not base58"))?;
+    if bytes.len() != 32 {
+        bail!("{field} `{value}` is not a 32-byte public key");
+    }
+    Ok(())
+}
+
+impl TokenRegistry {
+    /// Loads `MONOPAY_TOKEN_REGISTRY`, or the bundled `tokens.json`, keeping the entries for `config.cluster`.
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let contents = match &config.token_registry_path {
+            Some(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read token registry {path}"))?,
+            None => DEFAULT_REGISTRY.to_string(),
+        };
+        Self::parse(&contents, config.cluster)
+    }
+
+    pub fn parse(contents: &str, cluster: Cluster) -> anyhow::Result<Self> {
+        let entries: Vec<RegistryEntry> =
+            serde_json::from_str(contents).context("token registry is not valid JSON")?;
+
+        let mut tokens: Vec<TokenInfo> = Vec::new();
+        for entry in entries {
+            let symbol = entry.symbol.trim().to_uppercase();
+            let entry_cluster = Cluster::parse(&entry.cluster)
+                .with_context(|| format!("{symbol} has unknown cluster `{}`", entry.cluster))?;
+            if entry.decimals > 18 {
+                bail!(
+                    "{symbol} has {} decimals, at most 18 are supported",
+                    entry.decimals
+                );
+            }
+            if let Some(mint) = &entry.mint {
+                ensure_pubkey("mint", mint)?;
+            }
+            let token_program = match (&entry.mint, entry.token_program) {
+                (Some(_), Some(program)) => {
+                    ensure_pubkey("tokenProgram", &program)?;
+                    Some(program)
+                }
+                (Some(_), None) => Some(SPL_TOKEN_PROGRAM.to_string()),
+                (None, Some(_)) => bail!("{symbol} names a tokenProgram but no mint"),
+                (None, None) => None,
+            };
+            if entry_cluster != cluster {
+                continue;
+            }
+            if tokens.iter().any(|token| token.symbol == symbol) {
+                bail!("{symbol} is listed twice for {}", cluster.chain_id());
+            }
+            tokens.push(TokenInfo {
+                symbol,
+                mint: entry.mint,
+                token_program,
+                decimals: entry.decimals,
+                cluster,
+                enabled: entry.enabled,
+            });
+        }
+        Ok(Self { tokens })
+    }
+
+    pub fn tokens(&self) -> &[TokenInfo] {
+        &self.tokens
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&TokenInfo> {
+        self.tokens.iter().find(|token| token.symbol == symbol)
+    }
+
+    /// Looks up a token that can be quoted and paid in, case-insensitively.
+    pub fn enabled(&self, symbol: &str) -> Result<&TokenInfo, AppError> {
+        let symbol = symbol.trim().to_uppercase();
+        match self.get(&symbol) {
+            Some(token) if token.enabled => Ok(token),
+            Some(_) => Err(unsupported_token(format!("{symbol} is disabled"))),
+            None => Err(unsupported_token(format!(
+                "{symbol} is not a supported token, see /v1/tokens"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINT: &str = "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU";
+
+    fn error(contents: &str) -> String {
+        TokenRegistry::parse(contents, Cluster::Devnet)
+            .expect_err("the registry was accepted")
+            .to_string()
+    }
+
+    #[test]
+    fn the_bundled_registry_loads_for_every_cluster() {
+        for cluster in [Cluster::Devnet, Cluster::Testnet, Cluster::Mainnet] {
+            let registry = TokenRegistry::parse(DEFAULT_REGISTRY, cluster).unwrap();
+            let sol = registry.enabled("sol").unwrap();
+            assert_eq!((sol.decimals, sol.mint.as_deref()), (9, None));
+            assert!(registry.tokens().iter().all(|t| t.cluster == cluster));
+        }
+        let mainnet = TokenRegistry::parse(DEFAULT_REGISTRY, Cluster::Mainnet).unwrap();
+        let pyusd = mainnet.get("PYUSD").unwrap();
+        assert_eq!(
+            pyusd.token_program.as_deref(),
+            Some("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb")
+        );
+        let usdc = mainnet.enabled(" usdc ").unwrap();
+        assert_eq!(usdc.token_program.as_deref(), Some(SPL_TOKEN_PROGRAM));
+        assert_eq!(
+            usdc.explorer_url(),
+            "https://solscan.io/token/EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+        );
+    }
+
+    #[test]
+    fn disabled_and_unknown_tokens_are_unsupported() {
+        let registry = TokenRegistry::parse(DEFAULT_REGISTRY, Cluster::Mainnet).unwrap();
+        for symbol in ["USDT", "DOGE"] {
+            match registry.enabled(symbol) {
+                Err(AppError::Coded { status, code, .. }) => {
+                    assert_eq!(
+                        (status, code),
+                        (StatusCode::BAD_REQUEST, "UNSUPPORTED_TOKEN")
+                    )
+                }
+                other => panic!("{symbol} was {other:?}"),
+            }
+        }
+        // Entries for other clusters are dropped rather than disabled.
+        let devnet = TokenRegistry::parse(DEFAULT_REGISTRY, Cluster::Devnet).unwrap();
+        assert!(devnet.get("USDT").is_none());
+    }
+
+    #[test]
+    fn decimals_stop_at_eighteen() {
+        let entry = |decimals: u8| {
+            format!(
+                r#"[{{ "symbol": "big", "mint": "{MINT}", "decimals": {decimals}, "cluster": "devnet" }}]"#
+            )
+        };
+        let registry = TokenRegistry::parse(&entry(18), Cluster::Devnet).unwrap();
+        assert_eq!(registry.enabled("BIG").unwrap().decimals, 18);
+        assert_eq!(
+            error(&entry(19)),
+            "BIG has 19 decimals, at most 18 are supported"
+        );
+        // Beyond a u8 the entry does not even deserialize.
+        let huge = r#"[{ "symbol": "BIG", "decimals": 256, "cluster": "devnet" }]"#;
+        assert_eq!(error(huge), "token registry is not valid JSON");
+    }
+
+    #[test]
+    fn malformed_entries_are_rejected() {
+        assert_eq!(
+            error(r#"[{ "symbol": "SOL", "decimals": 9, "cluster": "moonnet" }]"#),
+            "SOL has unknown cluster `moonnet`"
+        );
+        assert_eq!(
+            error(
+                r#"[{ "symbol": "USDC", "mint": "not-base58!", "decimals": 6, "cluster": "devnet" }]"#
+            ),
+            "mint `not-base58!` is not base58"
+        );
+        assert_eq!(
+            error(r#"[{ "symbol": "USDC", "mint": "abc", "decimals": 6, "cluster": "devnet" }]"#),
+            "mint `abc` is not a 32-byte public key"
+        );
+        assert_eq!(
+            error(&format!(
+                r#"[{{ "symbol": "USDC", "mint": "{MINT}", "tokenProgram": "abc", "decimals": 6, "cluster": "devnet" }}]"#
+            )),
+            "tokenProgram `abc` is not a 32-byte public key"
+        );
+        assert_eq!(
+            error(&format!(
+                r#"[{{ "symbol": "SOL", "tokenProgram": "{SPL_TOKEN_PROGRAM}", "decimals": 9, "cluster": "devnet" }}]"#
+            )),
+            "SOL names a tokenProgram but no mint"
+        );
+        assert_eq!(
+            error(r#"[{ "symbol": "SOL", "decimals": 9 }]"#).as_str(),
+            "token registry is not valid JSON"
+        );
+    }
+
+    #[test]
+    fn a_symbol_is_listed_once_per_cluster() {
+        let twice = format!(
+            r#"[
+                {{ "symbol": "usdc", "mint": "{MINT}", "decimals": 6, "cluster": "devnet" }},
+                {{ "symbol": "USDC", "mint": "{MINT}", "decimals": 6, "cluster": "devnet" }}
+            ]"#
+        );
+        assert_eq!(error(&twice), "USDC is listed twice for devnet");
+        // Once per cluster is fine, and entries for other clusters are still validated.
+        let per_cluster = format!(
+            r#"[
+                {{ "symbol": "USDC", "mint": "{MINT}", "decimals": 6, "cluster": "devnet" }},
+                {{ "symbol": "USDC", "mint": "{MINT}", "decimals": 6, "cluster": "mainnet-beta" }},
+                {{ "symbol": "BAD", "mint": "{MINT}", "decimals": 30, "cluster": "mainnet" }}
+            ]"#
+        );
+        assert_eq!(
+            error(&per_cluster),
+            "BAD has 30 decimals, at most 18 are supported"
+        );
+    }
+}
@@ -1,64 +1,786 @@
+use crate::config::Config;
 use crate::error::AppError;
-use crate::models::{NonceRequest, NonceResponse, VerifyAuthRequest, VerifyAuthResponse};
-use crate::state::{AppState, NonceRecord};
+use crate::extract::AuthUser;
+use crate::models::{
+    LogoutRequest, LogoutResponse, NonceRequest, NonceResponse, RefreshTokenRequest,
+    VerifyAuthRequest, VerifyAuthResponse,
+};
+use crate::signing;
+use crate::siws::{self, SiwsMessage};
+use crate::state::{AppState, NonceRecord, TokenFamilyRecord};
+use crate::store::Store;
+use crate::tokens;
 use axum::extract::State;
+use axum::http::StatusCode;
 use axum::Json;
-use chrono::{Duration, Utc};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{Duration, SubsecRound, Utc};
+use tracing::warn;
 use uuid::Uuid;
 
+/// Tolerated clock skew between the gateway and whoever rendered the challenge.
+const CLOCK_SKEW_SECS: i64 = 30;
+
+/// Rotated-out refresh hashes kept per family to detect reuse. Older tokens are merely
+/// invalid rather than treated as theft.
+const MAX_RETIRED_HASHES: usize = 16;
+
+/// Canonical challenge the wallet signs; rebuilt server-side from the stored nonce.
+fn challenge_message(config: &Config, wallet: &str, record: &NonceRecord) -> SiwsMessage {
+    let statement = match &record.link_user_id {
+        Some(user_id) => format!("Link this wallet to monopay account {user_id}."),
+        None => config.auth_statement.clone(),
+    };
+    SiwsMessage {
+        domain: config.auth_domain.clone(),
+        address: wallet.to_string(),
+        statement: Some(statement),
+        uri: config.auth_uri.clone(),
+        version: siws::VERSION.to_string(),
+        chain_id: config.cluster.chain_id().to_string(),
+        nonce: record.nonce.clone(),
+        issued_at: record.issued_at,
+        expiration_time: Some(record.expires_at),
+        request_id: Some(record.request_id.clone()),
+    }
+}
+
+fn challenge_rejected(message: &str) -> AppError {
+    AppError::coded(StatusCode::UNAUTHORIZED, "CHALLENGE_REJECTED", message)
+}
+
+/// Issues a challenge for `wallet`; `link_user_id` turns it into a wallet-link proof.
+pub async fn issue_challenge(
+    config: &Config,
+    store: &dyn Store,
+    wallet: &str,
+    link_user_id: Option<String>,
+) -> Result<NonceResponse, AppError> {
+    if wallet.trim().is_empty() {
+        return Err(AppError::bad_request("wallet is required"));
+    }
+    signing::decode_pubkey(wallet)?;
+
+    let issued_at = Utc::now().trunc_subsecs(0);
+    let record = NonceRecord {
+        nonce: Uuid::new_v4().simple().to_string(),
+        request_id: format!("req_{}", Uuid::new_v4().simple()),
+        link_user_id,
+        issued_at,
+        expires_at: issued_at + Duration::minutes(5),
+    };
+    let fields = challenge_message(config, wallet, &record);
+    let message = fields.to_message();
+    store.push_nonce(wallet, record.clone()).await?;
+
+    Ok(NonceResponse {
+        nonce: record.nonce,
+        expires_at: record.expires_at,
+        message_base64: BASE64.encode(message.as_bytes()),
+        message,
+        fields,
+    })
+}
+
+/// Validates a signed challenge against the issued nonce and consumes it.
+pub async fn redeem_challenge(
+    config: &Config,
+    store: &dyn Store,
+    wallet: &str,
+    message: &str,
+    signature: &str,
+) -> Result<NonceRecord, AppError> {
+    if signature.trim().is_empty() {
+        return Err(AppError::bad_request("signature is required"));
+    }
+    let signed = SiwsMessage::parse(message)?;
+
+    if signed.domain != config.auth_domain {
+        return Err(challenge_rejected(
+            "message domain does not match this service",
+        ));
+    }
+    if signed.chain_id != config.cluster.chain_id() {
+        return Err(challenge_rejected(
+            "message chain id does not match this cluster",
+        ));
+    }
+    if signed.address != wallet {
+        return Err(challenge_rejected("message address does not match wallet"));
+    }
+    let now = Utc::now();
+    if signed.issued_at > now + Duration::seconds(CLOCK_SKEW_SECS) {
+        return Err(challenge_rejected("message issued in the future"));
+    }
+    if signed.expiration_time.is_some_and(|exp| now > exp) {
+        return Err(challenge_rejected("message expired"));
+    }
+
+    let nonce_record = store
+        .find_nonce(wallet, &signed.nonce)
+        .await?
+        .ok_or_else(|| AppError::unauthorized("nonce not found or already used"))?;
+
+    if now > nonce_record.expires_at {
+        return Err(AppError::unauthorized("nonce expired"));
+    }
+    if challenge_message(config, wallet, &nonce_record) != signed {
+        return Err(challenge_rejected(
+            "message does not match the issued challenge",
+        ));
+    }
+
+    signing::verify_wallet_signature(wallet, message.as_bytes(), signature)?;
+    store
+        .consume_nonce(wallet, &signed.nonce)
+        .await?
+        .ok_or_else(|| AppError::unauthorized("nonce not found or already used"))
+}
+
 pub async fn create_nonce(
     State(state): State<AppState>,
     Json(payload): Json<NonceRequest>,
 ) -> Result<Json<NonceResponse>, AppError> {
-    if payload.wallet.trim().is_empty() {
-        return Err(AppError::bad_request("wallet is required"));
+    let response =
+        issue_challenge(&state.config, state.store.as_ref(), &payload.wallet, None).await?;
+    Ok(Json(response))
+}
+
+pub async fn verify_auth(
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyAuthRequest>,
+) -> Result<Json<VerifyAuthResponse>, AppError> {
+    if payload.device_id.trim().is_empty() {
+        return Err(AppError::bad_request("deviceId is required"));
     }
 
-    let nonce = Uuid::new_v4().to_string();
-    let expires_at = Utc::now() + Duration::minutes(5);
+    let challenge = redeem_challenge(
+        &state.config,
+        state.store.as_ref(),
+        &payload.wallet,
+        &payload.message,
+        &payload.signature,
+    )
+    .await?;
+    if challenge.link_user_id.is_some() {
+        return Err(challenge_rejected("wallet link challenges cannot sign in"));
+    }
 
-    let mut store = state.store.write().await;
-    store.wallet_nonces.insert(
-        payload.wallet,
-        NonceRecord {
-            nonce: nonce.clone(),
-            expires_at,
-        },
-    );
+    let now = Utc::now();
+    let user_id = match state.store.wallet_link(&payload.wallet).await? {
+        Some(link) => link.user_id,
+        None => {
+            let user_id = format!("usr_{}", Uuid::new_v4().simple());
+            match state
+                .store
+                .link_wallet(&user_id, &payload.wallet, now)
+                .await
+            {
+                Ok(link) => link.user_id,
+                // Lost a race with a concurrent first sign-in of the same wallet.
+                Err(_) => state
+                    .store
+                    .wallet_link(&payload.wallet)
+                    .await?
+                    .map(|link| link.user_id)
+                    .ok_or_else(|| AppError::internal("failed to link wallet"))?,
+            }
+        }
+    };
+    let family_id = Uuid::new_v4().simple().to_string();
+    let (refresh_token, hash) = tokens::new_refresh_token(&family_id);
+    let family = TokenFamilyRecord {
+        id: family_id,
+        user_id,
+        wallet: payload.wallet,
+        device_id: payload.device_id,
+        current_hash: hash,
+        retired_hashes: Vec::new(),
+        expires_at: now + Duration::days(state.config.refresh_token_ttl_days),
+        revoked_at: None,
+    };
+    state.store.insert_token_family(family.clone()).await?;
 
-    Ok(Json(NonceResponse { nonce, expires_at }))
+    Ok(Json(issue_tokens(&state.config, &family, refresh_token)?))
 }
 
-pub async fn verify_auth(
+/// Pairs a fresh access token with the family's current refresh token.
+fn issue_tokens(
+    config: &Config,
+    family: &TokenFamilyRecord,
+    refresh_token: String,
+) -> Result<VerifyAuthResponse, AppError> {
+    let access = tokens::issue_access_token(
+        config.access_token_secret.as_bytes(),
+        Duration::minutes(config.access_token_ttl_minutes),
+        &family.user_id,
+        &family.wallet,
+        &family.id,
+    )?;
+
+    Ok(VerifyAuthResponse {
+        access_token: access.token,
+        expires_at: access.expires_at,
+        refresh_token,
+        refresh_expires_at: family.expires_at,
+        user_id: family.user_id.clone(),
+    })
+}
+
+pub async fn refresh_tokens(
     State(state): State<AppState>,
-    Json(payload): Json<VerifyAuthRequest>,
+    Json(payload): Json<RefreshTokenRequest>,
 ) -> Result<Json<VerifyAuthResponse>, AppError> {
-    if payload.signature.trim().is_empty() {
-        return Err(AppError::bad_request("signature is required"));
+    let (family_id, hash) = tokens::parse_refresh_token(&payload.refresh_token)?;
+    let now = Utc::now();
+
+    let (refresh_token, next_hash) = tokens::new_refresh_token(&family_id);
+    let mut reused = false;
+    let family = state
+        .store
+        .update_token_family(
+            &family_id,
+            Box::new(|family| {
+                if family.revoked_at.is_some() {
+                    return Err(AppError::unauthorized("refresh token revoked"));
+                }
+                if now > family.expires_at {
+                    return Err(AppError::unauthorized("refresh token expired"));
+                }
+                if family.retired_hashes.contains(&hash) {
+                    // A rotated-out token came back: assume theft and kill every token in
+                    // the family. Committed, so the caller's error must not roll it back.
+                    family.revoked_at = Some(now);
+                    reused = true;
+                    return Ok(());
+                }
+                if family.current_hash != hash {
+                    return Err(AppError::unauthorized("invalid refresh token"));
+                }
+                if family.device_id != payload.device_id {
+                    return Err(AppError::unauthorized(
+                        "refresh token is bound to another device",
+                    ));
+                }
+                let retired = std::mem::replace(&mut family.current_hash, next_hash);
+                family.retired_hashes.push(retired);
+                let excess = family
+                    .retired_hashes
+                    .len()
+                    .saturating_sub(MAX_RETIRED_HASHES);
+                family.retired_hashes.drain(..excess);
+                Ok(())
+            }),
+        )
+        .await
+        .map_err(|err| match err {
+            AppError::NotFound(_) => AppError::unauthorized("invalid refresh token"),
+            other => other,
+        })?;
+
+    if reused {
+        warn!("refresh token reuse detected for family {family_id}, family revoked");
+        return Err(AppError::coded(
+            StatusCode::UNAUTHORIZED,
+            "REFRESH_TOKEN_REUSED",
+            "refresh token already used, sign in again",
+        ));
     }
+    Ok(Json(issue_tokens(&state.config, &family, refresh_token)?))
+}
 
-    let mut store = state.store.write().await;
-    let nonce_record = store
-        .wallet_nonces
-        .get(&payload.wallet)
-        .ok_or_else(|| AppError::unauthorized("nonce not found"))?;
+pub async fn logout(
+    State(state): State<AppState>,
+    user: AuthUser,
+    payload: Option<Json<LogoutRequest>>,
+) -> Result<Json<LogoutResponse>, AppError> {
+    let Json(payload) = payload.unwrap_or_default();
+    let now = Utc::now();
 
-    if nonce_record.nonce != payload.nonce {
-        return Err(AppError::unauthorized("nonce mismatch"));
+    if payload.all_devices {
+        state
+            .store
+            .revoke_token_families(&user.user_id, None, None, now)
+            .await?;
+    } else if let Some(family_id) = &user.family_id {
+        state
+            .store
+            .revoke_token_families(&user.user_id, Some(family_id), None, now)
+            .await?;
     }
-    if Utc::now() > nonce_record.expires_at {
-        return Err(AppError::unauthorized("nonce expired"));
+    if let Some(token_id) = user.token_id {
+        let expires_at = now + Duration::minutes(state.config.access_token_ttl_minutes);
+        state
+            .store
+            .revoke_access_token(&token_id, expires_at)
+            .await?;
     }
 
-    let user_id = store
-        .wallet_users
-        .entry(payload.wallet.clone())
-        .or_insert_with(|| format!("usr_{}", Uuid::new_v4().simple()))
-        .clone();
-    let access_token = format!("acc_{}", Uuid::new_v4().simple());
-
-    Ok(Json(VerifyAuthResponse {
-        access_token,
-        user_id,
+    Ok(Json(LogoutResponse {
+        status: "logged_out".to_string(),
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{InMemoryStore, MAX_NONCES_PER_WALLET};
+    use crate::testing::{config, state, Wallet};
+
+    fn rejected(result: Result<NonceRecord, AppError>) -> String {
+        match result.expect_err("the challenge was accepted") {
+            AppError::Coded { code, message, .. } => format!("{code}: {message}"),
+            AppError::Unauthorized(message) => format!("UNAUTHORIZED: {message}"),
+            other => panic!("unexpected error {other:?}"),
+        }
+    }
+
+    /// Issues a challenge to `wallet`, lets `edit` change it, then signs and redeems it.
+    async fn redeem_edited(
+        store: &InMemoryStore,
+        wallet: &Wallet,
+        edit: impl FnOnce(&mut SiwsMessage),
+    ) -> Result<NonceRecord, AppError> {
+        let config = config();
+        let challenge = issue_challenge(&config, store, &wallet.address, None)
+            .await
+            .unwrap();
+        let mut fields = challenge.fields;
+        edit(&mut fields);
+        let message = fields.to_message();
+        redeem_challenge(
+            &config,
+            store,
+            &wallet.address,
+            &message,
+            &wallet.sign(&message),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn the_issued_challenge_signed_by_the_wallet_is_accepted() {
+        let store = InMemoryStore::default();
+        let wallet = Wallet::new(1);
+        let config = config();
+        let challenge = issue_challenge(&config, &store, &wallet.address, None)
+            .await
+            .unwrap();
+        assert_eq!(challenge.fields.domain, config.auth_domain);
+        assert_eq!(challenge.fields.chain_id, config.cluster.chain_id());
+        assert_eq!(
+            BASE64.decode(&challenge.message_base64).unwrap(),
+            challenge.message.as_bytes()
+        );
+
+        let record = redeem_challenge(
+            &config,
+            &store,
+            &wallet.address,
+            &challenge.message,
+            &wallet.sign(&challenge.message),
+        )
+        .await
+        .unwrap();
+        assert_eq!(record.nonce, challenge.nonce);
+        assert_eq!(record.link_user_id, None);
+    }
+
+    #[tokio::test]
+    async fn a_message_for_another_domain_or_cluster_is_rejected() {
+        let store = InMemoryStore::default();
+        let wallet = Wallet::new(1);
+        let result = redeem_edited(&store, &wallet, |fields| {
+            fields.domain = "monopay.app.evil.example".to_string()
+        })
+        .await;
+        assert_eq!(
+            rejected(result),
+            "CHALLENGE_REJECTED: message domain does not match this service"
+        );
+
+        let result = redeem_edited(&store, &wallet, |fields| {
+            fields.chain_id = "solana:testnet-fork".to_string()
+        })
+        .await;
+        assert_eq!(
+            rejected(result),
+            "CHALLENGE_REJECTED: message chain id does not match this cluster"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_message_for_another_address_is_rejected() {
+        let store = InMemoryStore::default();
+        let wallet = Wallet::new(1);
+        let other = Wallet::new(2).address;
+        let result = redeem_edited(&store, &wallet, |fields| fields.address = other).await;
+        assert_eq!(
+            rejected(result),
+            "CHALLENGE_REJECTED: message address does not match wallet"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_nonce_the_gateway_never_issued_is_rejected() {
+        let store = InMemoryStore::default();
+        let wallet = Wallet::new(1);
+        let result = redeem_edited(&store, &wallet, |fields| {
+            fields.nonce = Uuid::new_v4().simple().to_string()
+        })
+        .await;
+        assert_eq!(
+            rejected(result),
+            "UNAUTHORIZED: nonce not found or already used"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_message_that_differs_from_the_challenge_is_rejected() {
+        let store = InMemoryStore::default();
+        let wallet = Wallet::new(1);
+        let result = redeem_edited(&store, &wallet, |fields| {
+            fields.statement = Some("Approve a payment of 10000 INR.".to_string())
+        })
+        .await;
+        assert_eq!(
+            rejected(result),
+            "CHALLENGE_REJECTED: message does not match the issued challenge"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_message_past_its_expiration_time_is_rejected() {
+        let store = InMemoryStore::default();
+        let wallet = Wallet::new(1);
+        let config = config();
+        let issued_at = Utc::now().trunc_subsecs(0) - Duration::minutes(10);
+        let record = NonceRecord {
+            nonce: "stale".to_string(),
+            request_id: "req_stale".to_string(),
+            link_user_id: None,
+            issued_at,
+            expires_at: issued_at + Duration::minutes(5),
+        };
+        store
+            .push_nonce(&wallet.address, record.clone())
+            .await
+            .unwrap();
+        let message = challenge_message(&config, &wallet.address, &record).to_message();
+        let result = redeem_challenge(
+            &config,
+            &store,
+            &wallet.address,
+            &message,
+            &wallet.sign(&message),
+        )
+        .await;
+        assert_eq!(rejected(result), "CHALLENGE_REJECTED: message expired");
+    }
+
+    /// Issues a challenge to `wallet` and returns the message with the wallet's signature.
+    async fn signed_challenge(store: &InMemoryStore, wallet: &Wallet) -> (String, String) {
+        let challenge = issue_challenge(&config(), store, &wallet.address, None)
+            .await
+            .unwrap();
+        let signature = wallet.sign(&challenge.message);
+        (challenge.message, signature)
+    }
+
+    async fn redeem(
+        store: &InMemoryStore,
+        wallet: &Wallet,
+        (message, signature): &(String, String),
+    ) -> Result<NonceRecord, AppError> {
+        redeem_challenge(&config(), store, &wallet.address, message, signature).await
+    }
+
+    #[tokio::test]
+    async fn a_signed_challenge_cannot_be_replayed() {
+        let store = InMemoryStore::default();
+        let wallet = Wallet::new(1);
+        let signed = signed_challenge(&store, &wallet).await;
+        redeem(&store, &wallet, &signed).await.unwrap();
+        assert_eq!(
+            rejected(redeem(&store, &wallet, &signed).await),
+            "UNAUTHORIZED: nonce not found or already used"
+        );
+    }
+
+    #[tokio::test]
+    async fn nonces_are_scoped_to_the_wallet_they_were_issued_to() {
+        let store = InMemoryStore::default();
+        let wallet = Wallet::new(1);
+        let other = Wallet::new(2);
+        let (message, _) = signed_challenge(&store, &wallet).await;
+        // The other wallet signs the first wallet's challenge with its own address swapped in.
+        let message = message.replace(&wallet.address, &other.address);
+        let signature = other.sign(&message);
+        assert_eq!(
+            rejected(redeem(&store, &other, &(message, signature)).await),
+            "UNAUTHORIZED: nonce not found or already used"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_bad_signature_does_not_consume_the_nonce() {
+        let store = InMemoryStore::default();
+        let wallet = Wallet::new(1);
+        let (message, signature) = signed_challenge(&store, &wallet).await;
+        let forged = Wallet::new(2).sign(&message);
+        assert_eq!(
+            rejected(redeem(&store, &wallet, &(message.clone(), forged)).await),
+            "INVALID_SIGNATURE: wallet signature verification failed"
+        );
+        redeem(&store, &wallet, &(message, signature))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn each_wallet_keeps_only_its_newest_nonces() {
+        let store = InMemoryStore::default();
+        let wallet = Wallet::new(1);
+        let other = Wallet::new(2);
+        let oldest = signed_challenge(&store, &wallet).await;
+        let others = signed_challenge(&store, &other).await;
+        let mut newest = Vec::new();
+        for _ in 0..MAX_NONCES_PER_WALLET {
+            newest.push(signed_challenge(&store, &wallet).await);
+        }
+
+        assert_eq!(
+            rejected(redeem(&store, &wallet, &oldest).await),
+            "UNAUTHORIZED: nonce not found or already used"
+        );
+        for signed in &newest {
+            redeem(&store, &wallet, signed).await.unwrap();
+        }
+        // Another wallet's pool is untouched.
+        redeem(&store, &other, &others).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn expired_nonces_are_evicted() {
+        let store = InMemoryStore::default();
+        let wallet = Wallet::new(1);
+        let signed = signed_challenge(&store, &wallet).await;
+        assert_eq!(store.evict_expired_nonces(Utc::now()).await.unwrap(), 0);
+        let evicted = store
+            .evict_expired_nonces(Utc::now() + Duration::minutes(6))
+            .await
+            .unwrap();
+        assert_eq!(evicted, 1);
+        assert_eq!(
+            rejected(redeem(&store, &wallet, &signed).await),
+            "UNAUTHORIZED: nonce not found or already used"
+        );
+    }
+
+    async fn sign_in(state: &AppState, wallet: &Wallet, device_id: &str) -> VerifyAuthResponse {
+        let challenge = issue_challenge(&state.config, state.store.as_ref(), &wallet.address, None)
+            .await
+            .unwrap();
+        let request = VerifyAuthRequest {
+            wallet: wallet.address.clone(),
+            signature: wallet.sign(&challenge.message),
+            message: challenge.message,
+            device_id: device_id.to_string(),
+        };
+        verify_auth(State(state.clone()), Json(request))
+            .await
+            .unwrap()
+            .0
+    }
+
+    async fn refresh(
+        state: &AppState,
+        refresh_token: &str,
+        device_id: &str,
+    ) -> Result<VerifyAuthResponse, AppError> {
+        let request = RefreshTokenRequest {
+            refresh_token: refresh_token.to_string(),
+            device_id: device_id.to_string(),
+        };
+        refresh_tokens(State(state.clone()), Json(request))
+            .await
+            .map(|json| json.0)
+    }
+
+    async fn authenticate(state: &AppState, access_token: &str) -> Result<AuthUser, AppError> {
+        let (mut parts, _) = axum::http::Request::builder()
+            .header("authorization", format!("Bearer {access_token}"))
+            .body(())
+            .unwrap()
+            .into_parts();
+        axum::extract::FromRequestParts::from_request_parts(&mut parts, state).await
+    }
+
+    fn refused(result: Result<impl std::fmt::Debug, AppError>) -> String {
+        match result.expect_err("the request was accepted") {
+            AppError::Coded { code, message, .. } => format!("{code}: {message}"),
+            AppError::Unauthorized(message) => format!("UNAUTHORIZED: {message}"),
+            other => panic!("unexpected error {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn refreshing_rotates_tokens_within_a_fixed_deadline() {
+        let state = state();
+        let wallet = Wallet::new(1);
+        let signed_in = sign_in(&state, &wallet, "phone").await;
+
+        let mut current = signed_in.refresh_token.clone();
+        for _ in 0..3 {
+            let rotated = refresh(&state, &current, "phone").await.unwrap();
+            assert_ne!(rotated.refresh_token, current);
+            assert_eq!(rotated.user_id, signed_in.user_id);
+            // Rotation never extends the family past its first sign-in deadline.
+            assert_eq!(rotated.refresh_expires_at, signed_in.refresh_expires_at);
+            let user = authenticate(&state, &rotated.access_token).await.unwrap();
+            assert_eq!(user.wallet, wallet.address);
+            current = rotated.refresh_token;
+        }
+    }
+
+    #[tokio::test]
+    async fn reusing_a_rotated_token_revokes_the_whole_family() {
+        let state = state();
+        let wallet = Wallet::new(1);
+        let stolen = sign_in(&state, &wallet, "phone").await;
+        let laptop = sign_in(&state, &wallet, "laptop").await;
+        let rotated = refresh(&state, &stolen.refresh_token, "phone")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            refused(refresh(&state, &stolen.refresh_token, "phone").await),
+            "REFRESH_TOKEN_REUSED: refresh token already used, sign in again"
+        );
+        assert_eq!(
+            refused(refresh(&state, &rotated.refresh_token, "phone").await),
+            "UNAUTHORIZED: refresh token revoked"
+        );
+        for access_token in [&stolen.access_token, &rotated.access_token] {
+            assert_eq!(
+                refused(authenticate(&state, access_token).await),
+                "UNAUTHORIZED: access token revoked"
+            );
+        }
+        // Families on other devices are untouched.
+        authenticate(&state, &laptop.access_token).await.unwrap();
+        refresh(&state, &laptop.refresh_token, "laptop")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn concurrent_refreshes_of_one_token_rotate_it_once() {
+        let state = state();
+        let signed_in = sign_in(&state, &Wallet::new(1), "phone").await;
+        let (first, second) = tokio::join!(
+            refresh(&state, &signed_in.refresh_token, "phone"),
+            refresh(&state, &signed_in.refresh_token, "phone"),
+        );
+        let mut outcomes = [first, second].map(|result| match result {
+            Ok(_) => "rotated".to_string(),
+            Err(err) => refused(Err::<(), _>(err)),
+        });
+        outcomes.sort();
+        assert_eq!(
+            outcomes,
+            [
+                "REFRESH_TOKEN_REUSED: refresh token already used, sign in again".to_string(),
+                "rotated".to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn only_recent_rotations_are_remembered_for_reuse_detection() {
+        let state = state();
+        let signed_in = sign_in(&state, &Wallet::new(1), "phone").await;
+        let mut tokens = vec![signed_in.refresh_token];
+        for _ in 0..=MAX_RETIRED_HASHES {
+            let rotated = refresh(&state, tokens.last().unwrap(), "phone")
+                .await
+                .unwrap();
+            tokens.push(rotated.refresh_token);
+        }
+
+        // Forgotten tokens are plain invalid and leave the family alive.
+        assert_eq!(
+            refused(refresh(&state, &tokens[0], "phone").await),
+            "UNAUTHORIZED: invalid refresh token"
+        );
+        assert_eq!(
+            refused(refresh(&state, &tokens[1], "phone").await),
+            "REFRESH_TOKEN_REUSED: refresh token already used, sign in again"
+        );
+    }
+
+    #[tokio::test]
+    async fn refresh_tokens_are_bound_to_their_device_and_deadline() {
+        let mut state = state();
+        let signed_in = sign_in(&state, &Wallet::new(1), "phone").await;
+        assert_eq!(
+            refused(refresh(&state, &signed_in.refresh_token, "laptop").await),
+            "UNAUTHORIZED: refresh token is bound to another device"
+        );
+        // The rejected attempt did not rotate the token.
+        refresh(&state, &signed_in.refresh_token, "phone")
+            .await
+            .unwrap();
+
+        state.config.refresh_token_ttl_days = -1;
+        let expired = sign_in(&state, &Wallet::new(2), "phone").await;
+        assert_eq!(
+            refused(refresh(&state, &expired.refresh_token, "phone").await),
+            "UNAUTHORIZED: refresh token expired"
+        );
+        assert_eq!(
+            refused(refresh(&state, "rt_unknown.secret", "phone").await),
+            "UNAUTHORIZED: invalid refresh token"
+        );
+    }
+
+    #[tokio::test]
+    async fn logout_revokes_the_current_device_or_all_of_them() {
+        let state = state();
+        let wallet = Wallet::new(1);
+        let phone = sign_in(&state, &wallet, "phone").await;
+        let laptop = sign_in(&state, &wallet, "laptop").await;
+        let tablet = sign_in(&state, &wallet, "tablet").await;
+
+        let user = authenticate(&state, &phone.access_token).await.unwrap();
+        let logged_out = logout(State(state.clone()), user, None).await.unwrap();
+        assert_eq!(logged_out.status, "logged_out");
+        assert_eq!(
+            refused(authenticate(&state, &phone.access_token).await),
+            "UNAUTHORIZED: access token revoked"
+        );
+        assert_eq!(
+            refused(refresh(&state, &phone.refresh_token, "phone").await),
+            "UNAUTHORIZED: refresh token revoked"
+        );
+        authenticate(&state, &laptop.access_token).await.unwrap();
+
+        let user = authenticate(&state, &laptop.access_token).await.unwrap();
+        let everywhere = Json(LogoutRequest { all_devices: true });
+        let logged_out = logout(State(state.clone()), user, Some(everywhere))
+            .await
+            .unwrap();
+        assert_eq!(logged_out.status, "logged_out");
+        for signed_in in [&laptop, &tablet] {
+            assert_eq!(
+                refused(authenticate(&state, &signed_in.access_token).await),
+                "UNAUTHORIZED: access token revoked"
+            );
+        }
+        assert_eq!(
+            refused(refresh(&state, &tablet.refresh_token, "tablet").await),
+            "UNAUTHORIZED: refresh token revoked"
+        );
+    }
+}
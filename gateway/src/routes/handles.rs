@@ -1,4 +1,5 @@
 use crate::error::AppError;
+use crate::extract::AuthUser;
 use crate::models::{HandleResponse, UpsertHandleRequest};
 use crate::state::AppState;
 use axum::extract::{Path, State};
@@ -6,12 +7,12 @@ use axum::Json;
 
 const VPA_DOMAIN: &str = "@monopay.app";
 
-fn normalize_handle(value: &str) -> String {
+pub fn normalize_handle(value: &str) -> String {
     let mut trimmed = value.trim().to_lowercase();
     if trimmed.starts_with('@') {
         trimmed.remove(0);
     }
-    
+
     if trimmed.ends_with(VPA_DOMAIN) {
         trimmed
     } else {
@@ -20,34 +21,46 @@ fn normalize_handle(value: &str) -> String {
 }
 
 fn is_valid_handle_part(part: &str) -> bool {
-    part.len() >= 3 && part.len() <= 32 && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_')
+    part.len() >= 3
+        && part.len() <= 32
+        && part
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_')
 }
 
 pub async fn upsert_handle(
     State(state): State<AppState>,
+    user: AuthUser,
     Json(payload): Json<UpsertHandleRequest>,
 ) -> Result<Json<HandleResponse>, AppError> {
     if payload.wallet.trim().is_empty() {
         return Err(AppError::bad_request("wallet is required"));
     }
-    
+    let linked = state
+        .store
+        .wallet_link(&payload.wallet)
+        .await?
+        .is_some_and(|link| link.user_id == user.user_id);
+    if !linked {
+        return Err(AppError::unauthorized(
+            "wallet is not linked to this account",
+        ));
+    }
+
     let full_handle = normalize_handle(&payload.handle);
     let handle_part = full_handle.strip_suffix(VPA_DOMAIN).unwrap_or(&full_handle);
 
     if !is_valid_handle_part(handle_part) {
-        return Err(AppError::bad_request("handle must be 3-32 characters (alphanumeric, dots, underscores)"));
-    }
-
-    let mut store = state.store.write().await;
-    
-    // Check if handle is already taken by another wallet
-    if let Some(existing_wallet) = store.handles.get(&full_handle) {
-        if existing_wallet != &payload.wallet {
-            return Err(AppError::bad_request("handle is already taken"));
-        }
+        return Err(AppError::bad_request(
+            "handle must be 3-32 characters (alphanumeric, dots, underscores)",
+        ));
     }
 
-    store.handles.insert(full_handle.clone(), payload.wallet.clone());
+    // Fails if the handle is already taken by another wallet
+    state
+        .store
+        .claim_handle(&full_handle, &payload.wallet)
+        .await?;
 
     Ok(Json(HandleResponse {
         handle: full_handle,
@@ -61,15 +74,121 @@ pub async fn resolve_handle(
 ) -> Result<Json<HandleResponse>, AppError> {
     let normalized = normalize_handle(&handle);
 
-    let store = state.store.read().await;
-    let wallet = store
-        .handles
-        .get(&normalized)
-        .ok_or_else(|| AppError::not_found("handle not found"))?
-        .clone();
+    let wallet = state
+        .store
+        .handle_wallet(&normalized)
+        .await?
+        .ok_or_else(|| AppError::not_found("handle not found"))?;
 
     Ok(Json(HandleResponse {
         handle: normalized,
         wallet,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::idempotency::{IDEMPOTENCY_KEY, IDEMPOTENT_REPLAYED};
+    use crate::testing::{self, Call, Wallet};
+    use axum::http::StatusCode;
+    use chrono::Utc;
+    use serde_json::json;
+
+    #[test]
+    fn handles_are_normalized_to_the_vpa_domain() {
+        assert_eq!(normalize_handle("Priya"), "priya@monopay.app");
+        assert_eq!(normalize_handle(" @priya "), "priya@monopay.app");
+        assert_eq!(normalize_handle("priya@monopay.app"), "priya@monopay.app");
+    }
+
+    #[tokio::test]
+    async fn claiming_a_handle_needs_a_linked_wallet() {
+        let state = testing::state();
+        state
+            .store
+            .link_wallet("alice", "alice_wallet", Utc::now())
+            .await
+            .unwrap();
+        let body = json!({ "handle": "priya", "wallet": "alice_wallet" });
+
+        let (status, _, _) = Call::post("/v1/handles", body.clone()).send(&state).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _, _) = Call::post("/v1/handles", body.clone())
+            .user("mallory")
+            .send(&state)
+            .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            state
+                .store
+                .handle_wallet("priya@monopay.app")
+                .await
+                .unwrap(),
+            None
+        );
+
+        let (status, _, claimed) = Call::post("/v1/handles", body)
+            .user("alice")
+            .send(&state)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            claimed,
+            json!({ "handle": "priya@monopay.app", "wallet": "alice_wallet" })
+        );
+    }
+
+    /// The app's import flow: sign in with the new wallet, then claim with the
+    /// bearer token and one idempotency key that its retries reuse.
+    #[tokio::test]
+    async fn a_freshly_signed_in_wallet_claims_its_handle() {
+        let state = testing::state();
+        let wallet = Wallet::new(7);
+        let claim = json!({ "handle": "priya", "wallet": wallet.address });
+
+        let (status, _, _) = Call::post("/v1/handles", claim.clone())
+            .header(IDEMPOTENCY_KEY, "claim-1")
+            .send(&state)
+            .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _, challenge) =
+            Call::post("/v1/auth/nonce", json!({ "wallet": wallet.address }))
+                .send(&state)
+                .await;
+        assert_eq!(status, StatusCode::OK);
+        let message = challenge["message"].as_str().unwrap();
+        let (status, _, tokens) = Call::post(
+            "/v1/auth/verify",
+            json!({
+                "wallet": wallet.address,
+                "message": message,
+                "signature": wallet.sign(message),
+                "deviceId": "device-1",
+            }),
+        )
+        .send(&state)
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let bearer = format!("Bearer {}", tokens["accessToken"].as_str().unwrap());
+
+        let (status, headers, claimed) = Call::post("/v1/handles", claim.clone())
+            .header("authorization", &bearer)
+            .header(IDEMPOTENCY_KEY, "claim-1")
+            .send(&state)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(headers.get(IDEMPOTENT_REPLAYED).is_none());
+        assert_eq!(claimed["handle"], "priya@monopay.app");
+
+        let (status, headers, retried) = Call::post("/v1/handles", claim)
+            .header("authorization", &bearer)
+            .header(IDEMPOTENCY_KEY, "claim-1")
+            .send(&state)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[IDEMPOTENT_REPLAYED], "true");
+        assert_eq!(retried, claimed);
+    }
+}
@@ -1,7 +1,12 @@
 pub mod auth;
 pub mod handles;
 pub mod health;
+pub mod payment_events;
 pub mod payment_intents;
 pub mod pin;
 pub mod quotes;
 pub mod sessions;
+pub mod solana_pay;
+pub mod tokens;
+pub mod wallets;
+pub mod webhooks;
@@ -0,0 +1,286 @@
+use crate::error::AppError;
+use crate::events::IntentFeed;
+use crate::extract::AuthUser;
+use crate::models::{PaymentEventsQuery, PaymentEventsTokenResponse};
+use crate::routes::payment_intents::{event_response, owned_intent};
+use crate::state::AppState;
+use crate::tokens;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::Response;
+use axum::Json;
+use futures_util::stream::{self, Stream};
+use std::convert::Infallible;
+use std::time::Duration;
+use tokio::time::{interval_at, Instant};
+use tracing::warn;
+
+const LAST_EVENT_ID: &str = "last-event-id";
+/// Long enough to open a stream and ride out a reconnect or two.
+const EVENTS_TOKEN_TTL_SECS: i64 = 300;
+
+/// `POST /v1/payment-intents/{id}/events/token`: a short-lived token that opens the intent's
+/// event stream as `?token=`, for clients that cannot send `Authorization` with it.
+pub async fn create_events_token(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<PaymentEventsTokenResponse>, AppError> {
+    owned_intent(&state, &user.user_id, &id).await?;
+    let issued = tokens::issue_events_token(
+        state.config.access_token_secret.as_bytes(),
+        chrono::Duration::seconds(EVENTS_TOKEN_TTL_SECS),
+        &user.user_id,
+        &id,
+    )?;
+    Ok(Json(PaymentEventsTokenResponse {
+        token: issued.token,
+        expires_at: issued.expires_at,
+    }))
+}
+
+/// Checks ownership like `GET /payment-intents/{id}` and opens the feed after the event the
+/// client last saw: the `Last-Event-ID` header set by reconnecting `EventSource`s, or the
+/// `lastEventId` query parameter. The caller is identified by an events token in the query
+/// when one is given, and by the usual bearer auth otherwise.
+async fn open_feed(
+    state: &AppState,
+    user: Result<AuthUser, AppError>,
+    id: &str,
+    headers: &HeaderMap,
+    query: &PaymentEventsQuery,
+) -> Result<IntentFeed, AppError> {
+    let user_id = match &query.token {
+        Some(token) => {
+            tokens::decode_events_token(state.config.access_token_secret.as_bytes(), token, id)?
+        }
+        None => user?.user_id,
+    };
+    owned_intent(state, &user_id, id).await?;
+    let last_event_id = headers
+        .get(LAST_EVENT_ID)
+        .and_then(|value| value.to_str().ok())
+        .or(query.last_event_id.as_deref());
+    IntentFeed::open(state, id, last_event_id).await
+}
+
+/// `GET /v1/payment-intents/{id}/events`: the intent's history and then each status change
+/// as a `status` event, ending after a terminal status.
+pub async fn stream_payment_events(
+    State(state): State<AppState>,
+    user: Result<AuthUser, AppError>,
+    Path(id): Path<String>,
+    Query(query): Query<PaymentEventsQuery>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let feed = open_feed(&state, user, &id, &headers, &query).await?;
+    let events = stream::unfold(feed, |mut feed| async move {
+        let event = match feed.next().await {
+            Ok(event) => event?,
+            Err(err) => {
+                warn!("payment event stream failed: {err:?}");
+                return None;
+            }
+        };
+        let sse = Event::default()
+            .id(event.id.clone())
+            .event("status")
+            .json_data(event_response(event))
+            .ok()?;
+        Some((Ok(sse), feed))
+    });
+    let heartbeat = Duration::from_secs(state.config.events_heartbeat_secs);
+    Ok(Sse::new(events).keep_alive(KeepAlive::new().interval(heartbeat).text("heartbeat")))
+}
+
+/// `GET /v1/payment-intents/{id}/ws`: the same events as JSON text messages over a
+/// WebSocket, closed by the server after a terminal status.
+pub async fn payment_events_socket(
+    State(state): State<AppState>,
+    user: Result<AuthUser, AppError>,
+    Path(id): Path<String>,
+    Query(query): Query<PaymentEventsQuery>,
+    headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, AppError> {
+    let feed = open_feed(&state, user, &id, &headers, &query).await?;
+    let heartbeat = Duration::from_secs(state.config.events_heartbeat_secs);
+    Ok(upgrade.on_upgrade(move |socket| forward_events(socket, feed, heartbeat)))
+}
+
+async fn forward_events(mut socket: WebSocket, mut feed: IntentFeed, heartbeat: Duration) {
+    let mut ticker = interval_at(Instant::now() + heartbeat, heartbeat);
+    loop {
+        tokio::select! {
+            event = feed.next() => {
+                let event = match event {
+                    Ok(Some(event)) => event,
+                    Ok(None) => {
+                        let _ = socket.send(Message::Close(None)).await;
+                        return;
+                    }
+                    Err(err) => {
+                        warn!("payment event socket failed: {err:?}");
+                        return;
+                    }
+                };
+                let Ok(text) = serde_json::to_string(&event_response(event)) else {
+                    return;
+                };
+                if socket.send(Message::Text(text)).await.is_err() {
+                    return;
+                }
+                ticker.reset();
+            }
+            _ = ticker.tick() => {
+                if socket.send(Message::Ping(Vec::new())).await.is_err() {
+                    return;
+                }
+            }
+            message = socket.recv() => {
+                // Clients only ever close; anything else is ignored.
+                if matches!(message, None | Some(Err(_)) | Some(Ok(Message::Close(_)))) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lifecycle::{self, IntentStatus};
+    use crate::testing::{self, Call};
+    use axum::http::StatusCode;
+    use chrono::Utc;
+    use serde_json::{json, Value};
+
+    /// Two cancelled intents of alice's, so their streams end after the history.
+    async fn cancelled_intents() -> AppState {
+        let state = testing::state();
+        for id in ["pi_1", "pi_2"] {
+            let mut intent = testing::intent(id, "alice", "bob_wallet", 1_000, Utc::now());
+            lifecycle::transition(
+                &mut intent,
+                IntentStatus::Cancelled,
+                Some("payer_abandoned".to_string()),
+                Utc::now(),
+            )
+            .unwrap();
+            state.store.insert_payment_intent(intent).await.unwrap();
+        }
+        state
+    }
+
+    async fn events_token(state: &AppState, user: &str, id: &str) -> (StatusCode, Value) {
+        let (status, _, body) =
+            Call::post(&format!("/v1/payment-intents/{id}/events/token"), json!({}))
+                .user(user)
+                .send(state)
+                .await;
+        (status, body)
+    }
+
+    async fn stream(state: &AppState, uri: &str) -> (StatusCode, String) {
+        let (status, _, body) = Call::new(axum::http::Method::GET, uri)
+            .send_text(state)
+            .await;
+        (status, body)
+    }
+
+    fn issue(state: &AppState, user: &str, id: &str, ttl: chrono::Duration) -> String {
+        tokens::issue_events_token(state.config.access_token_secret.as_bytes(), ttl, user, id)
+            .unwrap()
+            .token
+    }
+
+    #[tokio::test]
+    async fn events_token_opens_the_stream_without_headers() {
+        let state = cancelled_intents().await;
+        let (status, body) = events_token(&state, "alice", "pi_1").await;
+        assert_eq!(status, StatusCode::OK);
+        let token = body["token"].as_str().unwrap();
+        assert!(body["expiresAt"].is_string());
+
+        let (status, events) = stream(
+            &state,
+            &format!("/v1/payment-intents/pi_1/events?token={token}"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(events.contains("event: status"), "{events}");
+        assert!(events.contains("\"status\":\"cancelled\""), "{events}");
+    }
+
+    #[tokio::test]
+    async fn only_the_creator_gets_an_events_token() {
+        let state = cancelled_intents().await;
+        let (status, _) = events_token(&state, "mallory", "pi_1").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = events_token(&state, "alice", "pi_missing").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn events_token_is_bound_to_its_intent_and_lifetime() {
+        let state = cancelled_intents().await;
+        let other = issue(&state, "alice", "pi_2", chrono::Duration::minutes(5));
+        let (status, _) = stream(
+            &state,
+            &format!("/v1/payment-intents/pi_1/events?token={other}"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let expired = issue(&state, "alice", "pi_1", chrono::Duration::seconds(-1));
+        let (status, _) = stream(
+            &state,
+            &format!("/v1/payment-intents/pi_1/events?token={expired}"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // Tokens are checked against the intent's owner again when the stream opens.
+        let stranger = issue(&state, "mallory", "pi_1", chrono::Duration::minutes(5));
+        let (status, _) = stream(
+            &state,
+            &format!("/v1/payment-intents/pi_1/events?token={stranger}"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = stream(&state, "/v1/payment-intents/pi_1/events?token=garbage").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = stream(&state, "/v1/payment-intents/pi_1/events").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn access_and_events_tokens_are_not_interchangeable() {
+        let state = cancelled_intents().await;
+        let access = testing::access_token(&state, "alice", "alice_wallet").await;
+        let (status, _) = stream(
+            &state,
+            &format!("/v1/payment-intents/pi_1/events?token={access}"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let events = issue(&state, "alice", "pi_1", chrono::Duration::minutes(5));
+        let (status, _, _) = Call::new(axum::http::Method::GET, "/v1/payment-intents/pi_1")
+            .header("authorization", &format!("Bearer {events}"))
+            .send(&state)
+            .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // The bearer header keeps working for the stream itself.
+        let (status, _, _) = Call::new(axum::http::Method::GET, "/v1/payment-intents/pi_1/events")
+            .header("authorization", &format!("Bearer {access}"))
+            .send_text(&state)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
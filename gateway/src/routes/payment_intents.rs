@@ -1,125 +1,248 @@
 use crate::error::AppError;
+use crate::extract::AuthUser;
+use crate::lifecycle::{self, IntentStatus};
+use crate::memo;
 use crate::models::{
-    CreatePaymentIntentRequest, ExecutePaymentIntentRequest, ExecutePaymentIntentResponse,
-    PaymentIntentResponse, PaymentIntentStatusResponse,
+    CancelPaymentIntentRequest, CreatePaymentIntentRequest, ExecutePaymentIntentRequest,
+    ExecutePaymentIntentResponse, ListPaymentIntentsQuery, PaymentDirection, PaymentEventResponse,
+    PaymentIntentListResponse, PaymentIntentResponse, PaymentIntentStatusResponse,
+    PaymentIntentSummary, PaymentReceiptResponse,
 };
-use crate::state::{AppState, PaymentIntentRecord};
-use axum::extract::{Path, State};
-use axum::http::HeaderMap;
+use crate::routes::handles::normalize_handle;
+use crate::routes::quotes::{self, QuoteAmount};
+use crate::routes::solana_pay;
+use crate::solana::Pubkey;
+use crate::state::{AppState, PaymentEventRecord, PaymentIntentRecord};
+use crate::store::{IntentCursor, IntentQuery, IntentSort, Mutation};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
 use axum::Json;
-use chrono::{Duration, Utc};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use std::collections::HashMap;
 use uuid::Uuid;
 
-const INR_PER_USDC: f64 = 83.61;
-
-fn user_id_from_headers(headers: &HeaderMap) -> Result<String, AppError> {
-    headers
-        .get("x-user-id")
-        .and_then(|v| v.to_str().ok())
-        .map(|v| v.to_string())
-        .ok_or_else(|| AppError::unauthorized("x-user-id header is required"))
-}
-
 pub async fn create_payment_intent(
     State(state): State<AppState>,
-    headers: HeaderMap,
+    user: AuthUser,
     Json(payload): Json<CreatePaymentIntentRequest>,
 ) -> Result<Json<PaymentIntentResponse>, AppError> {
-    let user_id = user_id_from_headers(&headers)?;
-    if payload.inr_amount <= 0.0 {
+    let user_id = user.user_id;
+    if payload.inr_amount.is_zero() {
         return Err(AppError::bad_request("inrAmount must be positive"));
     }
-    let normalized_token = payload.token.to_uppercase();
-    if normalized_token != "USDC" && normalized_token != "SOL" {
-        return Err(AppError::bad_request("token must be USDC or SOL"));
-    }
+    let normalized_token = state.registry.enabled(&payload.token)?.symbol.clone();
+    let memo = match &payload.memo {
+        Some(memo) => memo::clean(&state.config, memo)?,
+        None => None,
+    };
 
-    let mut store = state.store.write().await;
-    let recipient_wallet = store
-        .handles
-        .get(&payload.recipient_handle.to_lowercase())
-        .or_else(|| {
-            store
-                .handles
-                .get(&format!("@{}", payload.recipient_handle.to_lowercase()))
-        })
-        .ok_or_else(|| AppError::not_found("recipient handle not found"))?
-        .clone();
+    let recipient_handle = normalize_handle(&payload.recipient_handle);
+    let recipient_wallet = state
+        .store
+        .handle_wallet(&recipient_handle)
+        .await?
+        .ok_or_else(|| AppError::not_found("recipient handle not found"))?;
 
-    let session = store
-        .sessions
-        .get(&user_id)
+    let session = state
+        .store
+        .session(&user_id)
+        .await?
         .ok_or_else(|| AppError::unauthorized("active session required"))?;
+    let wallet = payload.wallet.unwrap_or(session.wallet);
+    let linked = state
+        .store
+        .wallet_link(&wallet)
+        .await?
+        .is_some_and(|link| link.user_id == user_id);
+    if !linked {
+        return Err(AppError::unauthorized(
+            "wallet is not linked to this account",
+        ));
+    }
+
+    let quote = match &payload.quote_id {
+        Some(quote_id) => {
+            let quote = state
+                .store
+                .quote(quote_id)
+                .await?
+                .ok_or_else(|| AppError::not_found("quote not found"))?;
+            if quote.token != normalized_token || quote.inr_amount != payload.inr_amount {
+                return Err(quotes::quote_mismatch(format!(
+                    "quote is for {} {}, not {} {}",
+                    quote.inr_amount, quote.token, payload.inr_amount, normalized_token
+                )));
+            }
+            if Utc::now() > quote.expires_at {
+                return Err(quotes::quote_expired());
+            }
+            quotes::ensure_within_slippage(&state, &quote).await?;
+            quote
+        }
+        None => {
+            quotes::lock_quote(
+                &state,
+                &normalized_token,
+                QuoteAmount::Inr(payload.inr_amount),
+                state.config.quote_max_slippage_bps,
+            )
+            .await?
+        }
+    };
 
     let id = format!("pi_{}", Uuid::new_v4().simple());
-    let token_amount = payload.inr_amount / INR_PER_USDC;
-    let quote_expires_at = Utc::now() + Duration::seconds(30);
-    let reference = format!("ref_{}", Uuid::new_v4().simple());
+    let claimed_by = id.clone();
+    // A quote prices exactly one intent; claiming it is atomic so concurrent creates cannot share it.
+    let quote = state
+        .store
+        .update_quote(
+            &quote.id,
+            Box::new(move |quote| {
+                if quote.intent_id.is_some() {
+                    return Err(quotes::quote_mismatch("quote has already been used"));
+                }
+                if Utc::now() > quote.expires_at {
+                    return Err(quotes::quote_expired());
+                }
+                quote.intent_id = Some(claimed_by);
+                Ok(())
+            }),
+        )
+        .await?;
+    let token_amount = quote.token_amount;
+    let quote_expires_at = quote.expires_at;
+    let reference = Pubkey::new_unique().to_string();
+    let created_at = Utc::now();
 
     let record = PaymentIntentRecord {
         id: id.clone(),
         creator_user_id: user_id,
+        payer_wallet: wallet.clone(),
         recipient_wallet: recipient_wallet.clone(),
+        recipient_handle: Some(recipient_handle),
         inr_amount: payload.inr_amount,
         token_amount,
         token: normalized_token.clone(),
+        quote_id: quote.id.clone(),
         quote_expires_at,
-        status: "created".to_string(),
+        memo: memo.clone(),
+        status: IntentStatus::Created,
         mode: None,
+        session_id: None,
         signature: None,
         reference: reference.clone(),
+        transaction_served_at: None,
+        created_at,
+        events: vec![lifecycle::created_event(created_at)],
     };
 
-    let wallet = session.wallet.clone();
-    store.payment_intents.insert(id.clone(), record);
+    state.store.insert_payment_intent(record.clone()).await?;
+    state.events.publish(&record);
 
     Ok(Json(PaymentIntentResponse {
         id,
         wallet,
         recipient_wallet,
-        inr_amount: format!("{:.2}", payload.inr_amount),
-        token_amount: format!("{:.6}", token_amount),
+        inr_amount: payload.inr_amount,
+        token_amount,
         token: normalized_token,
+        quote_id: quote.id,
         quote_expires_at,
         reference,
+        memo,
     }))
 }
 
 pub async fn execute_payment_intent(
     State(state): State<AppState>,
-    headers: HeaderMap,
+    user: AuthUser,
     Path(id): Path<String>,
     Json(payload): Json<ExecutePaymentIntentRequest>,
 ) -> Result<Json<ExecutePaymentIntentResponse>, AppError> {
-    let user_id = user_id_from_headers(&headers)?;
+    let user_id = user.user_id;
     if !payload.pin_token.starts_with("pin_tok_") {
         return Err(AppError::unauthorized("invalid pin token"));
     }
 
-    let mut store = state.store.write().await;
-    
-    // Scoped block to handle session checks and get limits
-    let (per_tx_limit, daily_limit, used_today) = {
-        let session = store
-            .sessions
-            .get_mut(&user_id)
-            .ok_or_else(|| AppError::unauthorized("active session required"))?;
+    let session = state
+        .store
+        .session(&user_id)
+        .await?
+        .ok_or_else(|| AppError::unauthorized("active session required"))?;
+    if session.id != payload.session_id {
+        return Err(AppError::unauthorized("session mismatch"));
+    }
+    if Utc::now() > session.expires_at {
+        let mut session = session;
+        session.status = "expired".to_string();
+        state.store.put_session(&user_id, session).await?;
+        return Err(AppError::unauthorized(
+            "session expired, wallet re-authorization required",
+        ));
+    }
 
-        if session.id != payload.session_id {
-            return Err(AppError::unauthorized("session mismatch"));
-        }
-        if Utc::now() > session.expires_at {
-            session.status = "expired".to_string();
-            return Err(AppError::unauthorized(
-                "session expired, wallet re-authorization required",
-            ));
-        }
-        (session.per_tx_limit_inr, session.daily_limit_inr, session.used_today_inr)
-    };
+    let session_id = payload.session_id;
+    let owner_id = user_id.clone();
+    // Limits are re-checked inside the store update so concurrent executes cannot overspend.
+    let (_, intent) = state
+        .store
+        .spend_from_session(
+            &user_id,
+            &id,
+            Box::new(move |session, intent| {
+                if session.id != session_id || Utc::now() > session.expires_at {
+                    return Err(AppError::unauthorized("session mismatch"));
+                }
+                if intent.creator_user_id != owner_id {
+                    return Err(AppError::unauthorized(
+                        "payment intent does not belong to user",
+                    ));
+                }
+                if Utc::now() > intent.quote_expires_at {
+                    return Err(quotes::quote_expired());
+                }
+                if intent.inr_amount > session.per_tx_limit_inr {
+                    return Err(AppError::unauthorized(
+                        "session per transaction limit exceeded",
+                    ));
+                }
+                let used_today = session
+                    .used_today_inr
+                    .checked_add(intent.inr_amount)
+                    .filter(|used| *used <= session.daily_limit_inr)
+                    .ok_or_else(|| AppError::unauthorized("session daily limit exceeded"))?;
 
-    let intent = store
-        .payment_intents
-        .get_mut(&id)
+                lifecycle::transition(intent, IntentStatus::Authorized, None, Utc::now())?;
+                intent.mode = Some("session_fast_path".to_string());
+                intent.session_id = Some(session.id.clone());
+                session.used_today_inr = used_today;
+                Ok(())
+            }),
+        )
+        .await?;
+    state.events.publish(&intent);
+
+    Ok(Json(ExecutePaymentIntentResponse {
+        transaction_url: solana_pay::transaction_url(&state, &intent.id),
+        id: intent.id,
+        status: intent.status,
+        mode: intent.mode.unwrap_or_else(|| "unknown".to_string()),
+        memo: intent.memo,
+    }))
+}
+
+/// The intent, if `user_id` created it.
+pub async fn owned_intent(
+    state: &AppState,
+    user_id: &str,
+    id: &str,
+) -> Result<PaymentIntentRecord, AppError> {
+    let intent = state
+        .store
+        .payment_intent(id)
+        .await?
         .ok_or_else(|| AppError::not_found("payment intent not found"))?;
 
     if intent.creator_user_id != user_id {
@@ -127,76 +250,905 @@ pub async fn execute_payment_intent(
             "payment intent does not belong to user",
         ));
     }
-    if Utc::now() > intent.quote_expires_at {
-        return Err(AppError::bad_request(
-            "quote expired, create new payment intent",
-        ));
+    Ok(intent)
+}
+
+/// The intent and the caller's side of it, if they created it or it pays one of their
+/// linked wallets. Owning the intent's `recipient_handle` needs no check of its own: a handle
+/// is only claimed for a wallet linked to the claimer and never moves to another wallet, so
+/// its owner is linked to `recipient_wallet`. Once they unlink that wallet, its payments go
+/// with it, since the funds did.
+async fn visible_intent(
+    state: &AppState,
+    user_id: &str,
+    id: &str,
+) -> Result<(PaymentIntentRecord, PaymentDirection), AppError> {
+    let intent = state
+        .store
+        .payment_intent(id)
+        .await?
+        .ok_or_else(|| AppError::not_found("payment intent not found"))?;
+
+    if intent.creator_user_id == user_id {
+        return Ok((intent, PaymentDirection::Sent));
     }
-    if intent.inr_amount > per_tx_limit {
+    let recipient = state
+        .store
+        .wallet_link(&intent.recipient_wallet)
+        .await?
+        .is_some_and(|link| link.user_id == user_id);
+    if !recipient {
         return Err(AppError::unauthorized(
-            "session per transaction limit exceeded",
+            "payment intent does not belong to user",
         ));
     }
-    if used_today + intent.inr_amount > daily_limit {
-        return Err(AppError::unauthorized("session daily limit exceeded"));
-    }
+    Ok((intent, PaymentDirection::Received))
+}
 
-    // Now update session and intent
-    let inr_amount = intent.inr_amount;
-    let signature = format!("sig_{}", Uuid::new_v4().simple());
-    intent.status = "submitted".to_string();
-    intent.mode = Some("session_fast_path".to_string());
-    intent.signature = Some(signature.clone());
+/// Handles of the wallets on a page, each looked up once.
+struct HandleNames<'a> {
+    state: &'a AppState,
+    known: HashMap<String, Option<String>>,
+}
 
-    let intent_id = intent.id.clone();
-    let intent_status = intent.status.clone();
-    let intent_mode = intent.mode.clone().unwrap_or_else(|| "unknown".to_string());
+impl<'a> HandleNames<'a> {
+    fn new(state: &'a AppState) -> Self {
+        Self {
+            state,
+            known: HashMap::new(),
+        }
+    }
 
-    if let Some(session) = store.sessions.get_mut(&user_id) {
-        session.used_today_inr += inr_amount;
+    async fn of(&mut self, wallet: &str) -> Result<Option<String>, AppError> {
+        if let Some(handle) = self.known.get(wallet) {
+            return Ok(handle.clone());
+        }
+        let handle = self.state.store.wallet_handle(wallet).await?;
+        self.known.insert(wallet.to_string(), handle.clone());
+        Ok(handle)
     }
 
-    Ok(Json(ExecutePaymentIntentResponse {
-        id: intent_id,
-        status: intent_status,
-        mode: intent_mode,
-        signature: signature.clone(),
-        explorer_url: format!("https://solscan.io/tx/{signature}"),
-    }))
+    /// The handle the payer addressed, or one of the recipient wallet's for older intents.
+    async fn recipient(
+        &mut self,
+        intent: &PaymentIntentRecord,
+    ) -> Result<Option<String>, AppError> {
+        match &intent.recipient_handle {
+            Some(handle) => Ok(Some(handle.clone())),
+            None => self.of(&intent.recipient_wallet).await,
+        }
+    }
 }
 
-pub async fn get_payment_intent(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    Path(id): Path<String>,
-) -> Result<Json<PaymentIntentStatusResponse>, AppError> {
-    let user_id = user_id_from_headers(&headers)?;
-
-    let store = state.store.read().await;
-    let intent = store
-        .payment_intents
-        .get(&id)
-        .ok_or_else(|| AppError::not_found("payment intent not found"))?;
-
-    if intent.creator_user_id != user_id {
-        return Err(AppError::unauthorized(
-            "payment intent does not belong to user",
-        ));
+pub fn event_response(event: PaymentEventRecord) -> PaymentEventResponse {
+    PaymentEventResponse {
+        id: event.id,
+        previous_status: event.previous_status,
+        status: event.status,
+        reason: event.reason,
+        signature: event.signature,
+        at: event.at,
     }
+}
 
-    let signature = intent.signature.clone();
+fn status_response(state: &AppState, intent: PaymentIntentRecord) -> PaymentIntentStatusResponse {
+    let signature = intent.signature;
     let explorer_url = signature
         .as_ref()
-        .map(|sig| format!("https://solscan.io/tx/{sig}"));
+        .map(|sig| state.config.cluster.explorer_url(&format!("tx/{sig}")));
 
-    Ok(Json(PaymentIntentStatusResponse {
-        id: intent.id.clone(),
-        status: intent.status.clone(),
+    PaymentIntentStatusResponse {
+        id: intent.id,
+        status: intent.status,
         mode: intent
             .mode
-            .clone()
             .unwrap_or_else(|| "wallet_fallback_path".to_string()),
+        memo: intent.memo,
         signature,
         explorer_url,
+        events: intent.events.into_iter().map(event_response).collect(),
+    }
+}
+
+/// Applies `close`, which moves an unpaid intent to `expired`, `cancelled` or `failed`, and
+/// gives the daily spend the intent reserved back to the session that authorized it, if that
+/// session is still current.
+pub async fn close_unpaid(
+    state: &AppState,
+    intent: &PaymentIntentRecord,
+    close: Mutation<'_, PaymentIntentRecord>,
+) -> Result<PaymentIntentRecord, AppError> {
+    let reserved = match &intent.session_id {
+        Some(session_id) => state
+            .store
+            .session(&intent.creator_user_id)
+            .await?
+            .is_some_and(|session| session.id == *session_id),
+        None => false,
+    };
+    let closed = if reserved {
+        let (_, closed) = state
+            .store
+            .spend_from_session(
+                &intent.creator_user_id,
+                &intent.id,
+                Box::new(move |session, intent| {
+                    close(intent)?;
+                    // The session may have been replaced since it was read.
+                    if intent.session_id.as_deref() == Some(session.id.as_str()) {
+                        session.used_today_inr =
+                            session.used_today_inr.saturating_sub(intent.inr_amount);
+                    }
+                    Ok(())
+                }),
+            )
+            .await?;
+        closed
+    } else {
+        state.store.update_payment_intent(&intent.id, close).await?
+    };
+    state.events.publish(&closed);
+    Ok(closed)
+}
+
+pub async fn get_payment_intent(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<PaymentIntentStatusResponse>, AppError> {
+    let intent = owned_intent(&state, &user.user_id, &id).await?;
+    Ok(Json(status_response(&state, intent)))
+}
+
+/// The intent as its payer or recipient sees it, with both sides named by handle.
+pub async fn get_payment_receipt(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<PaymentReceiptResponse>, AppError> {
+    let (intent, direction) = visible_intent(&state, &user.user_id, &id).await?;
+    let mut names = HandleNames::new(&state);
+    let payer_handle = names.of(&intent.payer_wallet).await?;
+    let recipient_handle = names.recipient(&intent).await?;
+    let explorer_url = intent
+        .signature
+        .as_ref()
+        .map(|sig| state.config.cluster.explorer_url(&format!("tx/{sig}")));
+
+    Ok(Json(PaymentReceiptResponse {
+        id: intent.id,
+        direction,
+        status: intent.status,
+        token: intent.token,
+        token_amount: intent.token_amount,
+        inr_amount: intent.inr_amount,
+        payer_wallet: intent.payer_wallet,
+        payer_handle,
+        recipient_wallet: intent.recipient_wallet,
+        recipient_handle,
+        memo: intent.memo,
+        signature: intent.signature,
+        explorer_url,
+        created_at: intent.created_at,
+        events: intent.events.into_iter().map(event_response).collect(),
     }))
 }
+
+/// Abandons an intent the payer has not sent a transaction for. Once a Solana Pay wallet
+/// was served a transaction it may still submit it, so such an intent can no longer be
+/// cancelled and is left to the reconciler or the expiry sweep.
+pub async fn cancel_payment_intent(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(id): Path<String>,
+    Json(payload): Json<CancelPaymentIntentRequest>,
+) -> Result<Json<PaymentIntentStatusResponse>, AppError> {
+    let intent = owned_intent(&state, &user.user_id, &id).await?;
+    let reason = payload.reason.as_str().to_string();
+    let cancelled = close_unpaid(
+        &state,
+        &intent,
+        Box::new(move |intent| {
+            if intent.signature.is_some() {
+                return Err(AppError::coded(
+                    StatusCode::CONFLICT,
+                    "INTENT_SIGNED",
+                    "a transaction was already sent for this payment intent",
+                ));
+            }
+            if intent.transaction_served_at.is_some() {
+                return Err(AppError::coded(
+                    StatusCode::CONFLICT,
+                    "TRANSACTION_SERVED",
+                    "a wallet was already served a transaction for this payment intent",
+                ));
+            }
+            lifecycle::transition(intent, IntentStatus::Cancelled, Some(reason), Utc::now())
+        }),
+    )
+    .await?;
+    Ok(Json(status_response(&state, cancelled)))
+}
+
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 100;
+
+/// Opaque to clients: the sort it was issued for, then the last intent's sort key and id.
+fn encode_cursor(sort: IntentSort, cursor: &IntentCursor) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{}.{}.{}", sort.as_str(), cursor.key, cursor.id))
+}
+
+fn decode_cursor(sort: IntentSort, value: &str) -> Result<IntentCursor, AppError> {
+    let invalid = || AppError::coded(StatusCode::BAD_REQUEST, "INVALID_CURSOR", "invalid cursor");
+    let raw = URL_SAFE_NO_PAD
+        .decode(value)
+        .ok()
+        .and_then(|raw| String::from_utf8(raw).ok())
+        .ok_or_else(invalid)?;
+    let mut parts = raw.splitn(3, '.');
+    let (Some(issued_for), Some(key), Some(id)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(invalid());
+    };
+    if IntentSort::parse(issued_for) != Some(sort) {
+        return Err(AppError::coded(
+            StatusCode::BAD_REQUEST,
+            "INVALID_CURSOR",
+            "cursor was issued for another sort order",
+        ));
+    }
+    Ok(IntentCursor {
+        key: key.parse().map_err(|_| invalid())?,
+        id: id.to_string(),
+    })
+}
+
+/// The caller's payment history: intents they created and intents paying their wallets.
+pub async fn list_payment_intents(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<ListPaymentIntentsQuery>,
+) -> Result<Json<PaymentIntentListResponse>, AppError> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(AppError::bad_request(format!(
+            "limit must be between 1 and {MAX_PAGE_SIZE}"
+        )));
+    }
+    let sort = query.sort.unwrap_or_default();
+    let after = query
+        .cursor
+        .as_deref()
+        .map(|cursor| decode_cursor(sort, cursor))
+        .transpose()?;
+    let statuses = match &query.status {
+        Some(list) => list
+            .split(',')
+            .map(|status| {
+                IntentStatus::parse(status.trim())
+                    .ok_or_else(|| AppError::bad_request(format!("unknown status `{status}`")))
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Vec::new(),
+    };
+    let direction = query.direction.unwrap_or_default();
+    let received_wallets = if direction == PaymentDirection::Sent {
+        Vec::new()
+    } else {
+        state
+            .store
+            .user_wallets(&user.user_id)
+            .await?
+            .into_iter()
+            .map(|link| link.pubkey)
+            .collect()
+    };
+    let counterparty_wallet = match &query.counterparty {
+        Some(handle) => match state.store.handle_wallet(&normalize_handle(handle)).await? {
+            Some(wallet) => Some(wallet),
+            // Nobody under that handle has paid or been paid.
+            None => {
+                return Ok(Json(PaymentIntentListResponse {
+                    items: Vec::new(),
+                    next_cursor: None,
+                }))
+            }
+        },
+        None => None,
+    };
+
+    let intent_query = IntentQuery {
+        user_id: user.user_id,
+        sent: direction != PaymentDirection::Received,
+        received_wallets,
+        statuses,
+        token: query.token.map(|token| token.to_uppercase()),
+        counterparty_wallet,
+        created_from: query.from,
+        created_before: query.to,
+        min_inr: query.min_inr,
+        max_inr: query.max_inr,
+        sort,
+        after,
+        // One extra tells whether another page follows.
+        limit: limit + 1,
+    };
+    let mut intents = state.store.list_payment_intents(&intent_query).await?;
+    let next_cursor = if intents.len() > limit {
+        intents.truncate(limit);
+        intents.last().map(|last| {
+            let cursor = IntentCursor {
+                key: sort.key(last),
+                id: last.id.clone(),
+            };
+            encode_cursor(sort, &cursor)
+        })
+    } else {
+        None
+    };
+
+    let mut names = HandleNames::new(&state);
+    let mut items = Vec::with_capacity(intents.len());
+    for intent in intents {
+        // Paying yourself lists as sent.
+        let (direction, counterparty_handle) = if intent_query.is_sent(&intent) {
+            (PaymentDirection::Sent, names.recipient(&intent).await?)
+        } else {
+            (
+                PaymentDirection::Received,
+                names.of(&intent.payer_wallet).await?,
+            )
+        };
+        items.push(PaymentIntentSummary {
+            id: intent.id,
+            direction,
+            status: intent.status,
+            token: intent.token,
+            token_amount: intent.token_amount,
+            inr_amount: intent.inr_amount,
+            payer_wallet: intent.payer_wallet,
+            recipient_wallet: intent.recipient_wallet,
+            counterparty_handle,
+            memo: intent.memo,
+            signature: intent.signature,
+            created_at: intent.created_at,
+        });
+    }
+    Ok(Json(PaymentIntentListResponse { items, next_cursor }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::Inr;
+    use crate::oracle::FixedOracle;
+    use crate::testing::{self, at, authorize, insert, put_session, status, used_today, Call};
+    use axum::http::Method;
+    use chrono::SecondsFormat;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    async fn create(state: &AppState, body: Value) -> (StatusCode, Value) {
+        let (status, _, body) = Call::post("/v1/payment-intents", body)
+            .user("alice")
+            .send(state)
+            .await;
+        (status, body)
+    }
+
+    fn paying(inr: &str, quote_id: &str) -> Value {
+        json!({
+            "recipientHandle": "bobby@monopay.app",
+            "inrAmount": inr,
+            "token": "SOL",
+            "quoteId": quote_id,
+        })
+    }
+
+    async fn lock(state: &AppState, paise: u64) -> String {
+        quotes::lock_quote(state, "SOL", QuoteAmount::Inr(Inr::from_paise(paise)), 50)
+            .await
+            .unwrap()
+            .id
+    }
+
+    #[tokio::test]
+    async fn an_intent_is_priced_at_its_locked_quote() {
+        let state = testing::payer_and_recipient().await;
+        let quote_id = lock(&state, 1_000).await;
+        let quote = state.store.quote(&quote_id).await.unwrap().unwrap();
+
+        let (status, created) = create(&state, paying("10.00", &quote_id)).await;
+        assert_eq!(status, StatusCode::OK, "{created}");
+        assert_eq!(created["quoteId"], quote_id);
+        assert_eq!(created["tokenAmount"], quote.token_amount.to_string());
+        let claimed = state.store.quote(&quote_id).await.unwrap().unwrap();
+        assert_eq!(
+            claimed.intent_id.as_ref(),
+            created["id"].as_str().map(String::from).as_ref()
+        );
+
+        // Without a quote the live rate is locked for the intent.
+        let mut unquoted = paying("10.00", "");
+        unquoted.as_object_mut().unwrap().remove("quoteId");
+        let (status, created) = create(&state, unquoted).await;
+        assert_eq!(status, StatusCode::OK, "{created}");
+        assert_ne!(created["quoteId"], quote_id);
+        assert_eq!(created["tokenAmount"], quote.token_amount.to_string());
+    }
+
+    #[tokio::test]
+    async fn a_quote_backs_a_single_intent() {
+        let state = testing::payer_and_recipient().await;
+        let quote_id = lock(&state, 1_000).await;
+        let (status, _) = create(&state, paying("10.00", &quote_id)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = create(&state, paying("10.00", &quote_id)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "QUOTE_MISMATCH");
+    }
+
+    #[tokio::test]
+    async fn a_quote_for_another_amount_or_token_is_refused() {
+        let state = testing::payer_and_recipient().await;
+        let quote_id = lock(&state, 1_000).await;
+        let (status, body) = create(&state, paying("10.01", &quote_id)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "QUOTE_MISMATCH");
+
+        let mut usdc = paying("10.00", &quote_id);
+        usdc["token"] = json!("USDC");
+        let (status, body) = create(&state, usdc).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "QUOTE_MISMATCH");
+
+        let (status, _) = create(&state, paying("10.00", "qt_unknown")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        // The refusals left the quote usable.
+        let (status, _) = create(&state, paying("10.00", &quote_id)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn an_expired_quote_is_refused() {
+        let mut state = testing::payer_and_recipient().await;
+        state.config.quote_lock_secs = -1;
+        let quote_id = lock(&state, 1_000).await;
+        let (status, body) = create(&state, paying("10.00", &quote_id)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "QUOTE_EXPIRED");
+    }
+
+    #[tokio::test]
+    async fn a_quote_is_refused_once_the_price_moves_past_its_slippage() {
+        let mut state = testing::payer_and_recipient().await;
+        let within = lock(&state, 1_000).await;
+        let beyond = lock(&state, 1_000).await;
+
+        // SOL was locked at 12500 with 50 bps of tolerance.
+        state.oracle = Arc::new(FixedOracle::parse("SOL=12550").unwrap());
+        let (status, body) = create(&state, paying("10.00", &within)).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+
+        state.oracle = Arc::new(FixedOracle::parse("SOL=12400").unwrap());
+        let (status, body) = create(&state, paying("10.00", &beyond)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "QUOTE_MISMATCH");
+    }
+
+    async fn cancel(state: &AppState, id: &str) -> (StatusCode, Value) {
+        let (status, _, body) = Call::post(
+            &format!("/v1/payment-intents/{id}/cancel"),
+            json!({ "reason": "wrong_amount" }),
+        )
+        .user("alice")
+        .send(state)
+        .await;
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn created_intents_are_cancelled_with_the_reason() {
+        let state = testing::state();
+        insert(&state, "pi_1", &[], at(0)).await;
+
+        let (code, body) = cancel(&state, "pi_1").await;
+        assert_eq!(code, StatusCode::OK, "{body}");
+        assert_eq!(body["status"], "cancelled");
+        let intent = state.store.payment_intent("pi_1").await.unwrap().unwrap();
+        let event = intent.events.last().unwrap();
+        assert_eq!(event.status, IntentStatus::Cancelled);
+        assert_eq!(event.reason.as_deref(), Some("wrong_amount"));
+
+        let (code, body) = cancel(&state, "pi_1").await;
+        assert_eq!(code, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "INVALID_TRANSITION");
+    }
+
+    #[tokio::test]
+    async fn cancelling_an_authorized_intent_releases_its_spend() {
+        let state = testing::state();
+        put_session(&state, "ses_1", 2_000).await;
+        insert(&state, "pi_1", &[], at(0)).await;
+        authorize(&state, "pi_1", at(10)).await;
+        assert_eq!(used_today(&state).await, 3_000);
+
+        let (code, body) = cancel(&state, "pi_1").await;
+        assert_eq!(code, StatusCode::OK, "{body}");
+        assert_eq!(status(&state, "pi_1").await, Some(IntentStatus::Cancelled));
+        assert_eq!(used_today(&state).await, 2_000);
+    }
+
+    #[tokio::test]
+    async fn sent_or_foreign_intents_are_not_cancelled() {
+        let state = testing::state();
+        put_session(&state, "ses_1", 0).await;
+        insert(&state, "pi_1", &[], at(0)).await;
+        authorize(&state, "pi_1", at(10)).await;
+        state
+            .store
+            .update_payment_intent(
+                "pi_1",
+                Box::new(|intent| {
+                    intent.signature = Some("sent_signature".to_string());
+                    Ok(())
+                }),
+            )
+            .await
+            .unwrap();
+
+        let (code, body) = cancel(&state, "pi_1").await;
+        assert_eq!(code, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "INTENT_SIGNED");
+        assert_eq!(status(&state, "pi_1").await, Some(IntentStatus::Authorized));
+        assert_eq!(used_today(&state).await, 1_000);
+
+        let (code, _, _) = Call::post(
+            "/v1/payment-intents/pi_1/cancel",
+            json!({ "reason": "other" }),
+        )
+        .user("mallory")
+        .send(&state)
+        .await;
+        assert_eq!(code, StatusCode::UNAUTHORIZED);
+    }
+
+    async fn save(state: &AppState, intent: PaymentIntentRecord) {
+        state.store.insert_payment_intent(intent).await.unwrap();
+    }
+
+    async fn list(state: &AppState, user: &str, query: &str) -> (StatusCode, Value) {
+        let (status, _, body) = Call::new(Method::GET, &format!("/v1/payment-intents?{query}"))
+            .user(user)
+            .send(state)
+            .await;
+        (status, body)
+    }
+
+    fn ids(page: &Value) -> Vec<String> {
+        page["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["id"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    /// Every id listed by `query`, following `nextCursor` to the last page.
+    async fn walk(state: &AppState, user: &str, query: &str) -> Vec<String> {
+        let mut listed = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let query = match &cursor {
+                Some(cursor) => format!("{query}&cursor={cursor}"),
+                None => query.to_string(),
+            };
+            let (status, page) = list(state, user, &query).await;
+            assert_eq!(status, StatusCode::OK, "{page}");
+            listed.extend(ids(&page));
+            match page["nextCursor"].as_str() {
+                Some(next) => cursor = Some(next.to_string()),
+                None => return listed,
+            }
+        }
+    }
+
+    fn time(seconds: i64) -> String {
+        at(seconds).to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+
+    #[tokio::test]
+    async fn cursors_survive_intents_created_between_pages() {
+        let state = testing::state();
+        for n in 1..=5 {
+            save(
+                &state,
+                testing::intent(&format!("pi_{n}"), "alice", "bob_wallet", 1_000, at(n)),
+            )
+            .await;
+        }
+
+        let (_, first) = list(&state, "alice", "limit=2").await;
+        assert_eq!(ids(&first), ["pi_5", "pi_4"]);
+        save(
+            &state,
+            testing::intent("pi_6", "alice", "bob_wallet", 1_000, at(6)),
+        )
+        .await;
+        let cursor = first["nextCursor"].as_str().unwrap();
+        let (_, second) = list(&state, "alice", &format!("limit=2&cursor={cursor}")).await;
+        assert_eq!(ids(&second), ["pi_3", "pi_2"]);
+        save(
+            &state,
+            testing::intent("pi_7", "alice", "bob_wallet", 1_000, at(7)),
+        )
+        .await;
+        let cursor = second["nextCursor"].as_str().unwrap();
+        let (_, third) = list(&state, "alice", &format!("limit=2&cursor={cursor}")).await;
+        assert_eq!(ids(&third), ["pi_1"]);
+        assert!(third["nextCursor"].is_null());
+
+        // Oldest first, new intents show up at the end rather than shifting pages.
+        let (_, first) = list(&state, "alice", "limit=3&sort=oldest").await;
+        assert_eq!(ids(&first), ["pi_1", "pi_2", "pi_3"]);
+        save(
+            &state,
+            testing::intent("pi_8", "alice", "bob_wallet", 1_000, at(8)),
+        )
+        .await;
+        let cursor = first["nextCursor"].as_str().unwrap();
+        let (_, second) = list(
+            &state,
+            "alice",
+            &format!("limit=3&sort=oldest&cursor={cursor}"),
+        )
+        .await;
+        assert_eq!(ids(&second), ["pi_4", "pi_5", "pi_6"]);
+    }
+
+    #[tokio::test]
+    async fn pages_split_ties_on_the_sort_key_by_id() {
+        let state = testing::state();
+        for id in ["pi_a", "pi_b", "pi_c", "pi_d"] {
+            save(
+                &state,
+                testing::intent(id, "alice", "bob_wallet", 1_000, at(0)),
+            )
+            .await;
+        }
+        save(
+            &state,
+            testing::intent("pi_e", "alice", "bob_wallet", 2_000, at(1)),
+        )
+        .await;
+
+        assert_eq!(
+            walk(&state, "alice", "limit=1").await,
+            ["pi_e", "pi_d", "pi_c", "pi_b", "pi_a"]
+        );
+        assert_eq!(
+            walk(&state, "alice", "limit=2&sort=smallest").await,
+            ["pi_a", "pi_b", "pi_c", "pi_d", "pi_e"]
+        );
+        assert_eq!(
+            walk(&state, "alice", "limit=3&sort=largest").await,
+            ["pi_e", "pi_d", "pi_c", "pi_b", "pi_a"]
+        );
+    }
+
+    #[tokio::test]
+    async fn malformed_cursors_are_rejected() {
+        let state = testing::state();
+        for n in 1..=3 {
+            save(
+                &state,
+                testing::intent(&format!("pi_{n}"), "alice", "bob_wallet", 1_000, at(n)),
+            )
+            .await;
+        }
+        let (_, page) = list(&state, "alice", "limit=1").await;
+        let newest = page["nextCursor"].as_str().unwrap().to_string();
+
+        let garbage = [
+            "not-a-cursor!".to_string(),
+            URL_SAFE_NO_PAD.encode("newest"),
+            URL_SAFE_NO_PAD.encode("newest.soon.pi_1"),
+            URL_SAFE_NO_PAD.encode("sideways.1.pi_1"),
+            URL_SAFE_NO_PAD.encode([0xff, 0xfe, 0x2e]),
+        ];
+        for cursor in &garbage {
+            let (status, body) = list(&state, "alice", &format!("cursor={cursor}")).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{cursor}");
+            assert_eq!(body["error"]["code"], "INVALID_CURSOR", "{cursor}");
+        }
+
+        let (status, body) = list(&state, "alice", &format!("sort=oldest&cursor={newest}")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "INVALID_CURSOR");
+        assert_eq!(
+            body["error"]["message"],
+            "cursor was issued for another sort order"
+        );
+
+        for limit in ["0", "101"] {
+            let (status, _) = list(&state, "alice", &format!("limit={limit}")).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn filters_combine() {
+        let state = testing::state();
+        state
+            .store
+            .link_wallet("alice", "alice_wallet", at(0))
+            .await
+            .unwrap();
+        state
+            .store
+            .claim_handle(&normalize_handle("bob"), "bob_wallet")
+            .await
+            .unwrap();
+
+        save(
+            &state,
+            testing::intent("pi_sol_bob", "alice", "bob_wallet", 1_000, at(1)),
+        )
+        .await;
+        let mut usdc = testing::intent("pi_usdc_bob", "alice", "bob_wallet", 5_000, at(2));
+        usdc.token = "USDC".to_string();
+        save(&state, usdc).await;
+        let mut cancelled = testing::intent("pi_sol_carol", "alice", "carol_wallet", 3_000, at(3));
+        lifecycle::transition(&mut cancelled, IntentStatus::Cancelled, None, at(4)).unwrap();
+        save(&state, cancelled).await;
+        save(
+            &state,
+            testing::intent("pi_from_bob", "bob", "alice_wallet", 2_000, at(5)),
+        )
+        .await;
+        save(
+            &state,
+            testing::intent("pi_from_dave", "dave", "alice_wallet", 9_000, at(6)),
+        )
+        .await;
+        // Neither created by alice nor paying her.
+        save(
+            &state,
+            testing::intent("pi_other", "bob", "carol_wallet", 1_000, at(7)),
+        )
+        .await;
+
+        let cases = [
+            (
+                "",
+                vec![
+                    "pi_from_dave",
+                    "pi_from_bob",
+                    "pi_sol_carol",
+                    "pi_usdc_bob",
+                    "pi_sol_bob",
+                ],
+            ),
+            (
+                "direction=sent",
+                vec!["pi_sol_carol", "pi_usdc_bob", "pi_sol_bob"],
+            ),
+            ("direction=received", vec!["pi_from_dave", "pi_from_bob"]),
+            (
+                "token=sol&direction=sent",
+                vec!["pi_sol_carol", "pi_sol_bob"],
+            ),
+            (
+                "counterparty=bob",
+                vec!["pi_from_bob", "pi_usdc_bob", "pi_sol_bob"],
+            ),
+            ("counterparty=@Bob&token=usdc", vec!["pi_usdc_bob"]),
+            ("counterparty=bob&direction=received", vec!["pi_from_bob"]),
+            ("counterparty=nobody", vec![]),
+            ("status=cancelled", vec!["pi_sol_carol"]),
+            (
+                "status=created,cancelled&direction=sent&token=SOL",
+                vec!["pi_sol_carol", "pi_sol_bob"],
+            ),
+            (
+                "minInr=20&maxInr=50",
+                vec!["pi_from_bob", "pi_sol_carol", "pi_usdc_bob"],
+            ),
+            (
+                "minInr=20&maxInr=50&direction=sent&status=created",
+                vec!["pi_usdc_bob"],
+            ),
+            (
+                &*format!("from={}&to={}", time(2), time(5)),
+                vec!["pi_sol_carol", "pi_usdc_bob"],
+            ),
+            (
+                &*format!("from={}&token=SOL&sort=largest", time(2)),
+                vec!["pi_from_dave", "pi_sol_carol", "pi_from_bob"],
+            ),
+        ];
+        for (query, expected) in cases {
+            assert_eq!(
+                walk(&state, "alice", &format!("limit=2&{query}")).await,
+                expected,
+                "{query}"
+            );
+        }
+
+        let (status, _) = list(&state, "alice", "status=lost").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    async fn pay(state: &AppState, handle: &str) -> (StatusCode, Value) {
+        let body = json!({ "recipientHandle": handle, "inrAmount": "10.00", "token": "SOL" });
+        create(state, body).await
+    }
+
+    #[tokio::test]
+    async fn recipient_handles_are_normalized() {
+        let state = testing::payer_and_recipient().await;
+        for handle in ["bobby", "@Bobby", " bobby@monopay.app "] {
+            let (status, body) = pay(&state, handle).await;
+            assert_eq!(status, StatusCode::OK, "{handle}: {body}");
+            assert_eq!(body["recipientWallet"], "bob_wallet");
+            let id = body["id"].as_str().unwrap();
+            let intent = state.store.payment_intent(id).await.unwrap().unwrap();
+            assert_eq!(
+                intent.recipient_handle.as_deref(),
+                Some("bobby@monopay.app")
+            );
+        }
+
+        let (status, _) = pay(&state, "nobody").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn payments_to_a_handle_belong_to_whoever_holds_its_wallet() {
+        let state = testing::payer_and_recipient().await;
+        let (_, body) = pay(&state, "bobby").await;
+        let id = body["id"].as_str().unwrap();
+        let receipt = format!("/v1/payment-intents/{id}/receipt");
+
+        let (status, _, body) = Call::new(Method::GET, &receipt)
+            .user("bob")
+            .send(&state)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["direction"], "received");
+        assert_eq!(body["recipientHandle"], "bobby@monopay.app");
+        let (_, page) = list(&state, "bob", "direction=received").await;
+        assert_eq!(ids(&page), [id]);
+
+        let (status, _, _) = Call::new(Method::GET, &receipt)
+            .user("mallory")
+            .send(&state)
+            .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // The handle stays with the wallet, and so do the payments.
+        state
+            .store
+            .unlink_wallet("bob", "bob_wallet")
+            .await
+            .unwrap();
+        let (status, _, _) = Call::new(Method::GET, &receipt)
+            .user("bob")
+            .send(&state)
+            .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (_, page) = list(&state, "bob", "direction=received").await;
+        assert!(ids(&page).is_empty());
+
+        state
+            .store
+            .link_wallet("carol", "bob_wallet", at(1))
+            .await
+            .unwrap();
+        let (status, _, body) = Call::new(Method::GET, &receipt)
+            .user("carol")
+            .send(&state)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["recipientHandle"], "bobby@monopay.app");
+    }
+}
@@ -1,28 +1,254 @@
 use crate::error::AppError;
-use crate::models::{QuoteQuery, QuoteResponse};
-use axum::extract::Query;
+use crate::extract::AuthUser;
+use crate::models::{CreateQuoteRequest, QuotePreviewResponse, QuoteQuery, QuoteResponse};
+use crate::money::{Inr, TokenAmount};
+use crate::oracle::PricePoint;
+use crate::state::{AppState, QuoteRecord};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
 use axum::Json;
 use chrono::{Duration, Utc};
+use uuid::Uuid;
 
-const INR_PER_USDC: f64 = 83.61;
+pub fn quote_expired() -> AppError {
+    AppError::coded(
+        StatusCode::CONFLICT,
+        "QUOTE_EXPIRED",
+        "quote expired, request a new quote",
+    )
+}
+
+pub fn quote_mismatch(message: impl Into<String>) -> AppError {
+    AppError::coded(StatusCode::CONFLICT, "QUOTE_MISMATCH", message)
+}
+
+/// The side of a quote the caller fixed; the other side is derived from the rate.
+#[derive(Debug, Clone, Copy)]
+pub enum QuoteAmount {
+    Inr(Inr),
+    Token(TokenAmount),
+}
+
+/// Prices `amount` of `token` at the current oracle rate, returning both sides and the price used.
+async fn price_amount(
+    state: &AppState,
+    token: &str,
+    amount: QuoteAmount,
+) -> Result<(Inr, TokenAmount, PricePoint), AppError> {
+    let decimals = state.registry.enabled(token)?.decimals;
+    let price = state.oracle.price(token).await?;
+    let (inr, token_amount) = match amount {
+        QuoteAmount::Inr(inr) => {
+            let tokens = price.rate.inr_to_token(inr, decimals);
+            (inr, tokens.map_err(AppError::bad_request)?)
+        }
+        QuoteAmount::Token(tokens) => {
+            let inr = price.rate.token_to_inr(tokens);
+            (inr.map_err(AppError::bad_request)?, tokens)
+        }
+    };
+    if inr.is_zero() || token_amount.is_zero() {
+        return Err(AppError::bad_request("amount is too small to quote"));
+    }
+    Ok((inr, token_amount, price))
+}
+
+/// Prices `amount` at the current oracle rate and stores it as a quote locked for the configured window.
+pub async fn lock_quote(
+    state: &AppState,
+    token: &str,
+    amount: QuoteAmount,
+    max_slippage_bps: u32,
+) -> Result<QuoteRecord, AppError> {
+    let (inr, token_amount, price) = price_amount(state, token, amount).await?;
+    let record = QuoteRecord {
+        id: format!("qt_{}", Uuid::new_v4().simple()),
+        token: token.to_string(),
+        inr_amount: inr,
+        token_amount,
+        rate: price.rate,
+        source: price.source,
+        as_of: price.as_of,
+        expires_at: Utc::now() + Duration::seconds(state.config.quote_lock_secs),
+        max_slippage_bps,
+        intent_id: None,
+    };
+    state.store.insert_quote(record.clone()).await?;
+    Ok(record)
+}
 
-pub async fn get_usdc_quote(
+/// Refuses a locked quote once the live price has moved past its slippage tolerance.
+pub async fn ensure_within_slippage(state: &AppState, quote: &QuoteRecord) -> Result<(), AppError> {
+    let live = state.oracle.price(&quote.token).await?;
+    let locked = quote.rate.scaled();
+    let drift_bps = live.rate.scaled().abs_diff(locked) * 10_000 / locked.max(1);
+    if drift_bps > u128::from(quote.max_slippage_bps) {
+        return Err(quote_mismatch(format!(
+            "price moved {drift_bps} bps since the quote, more than the allowed {} bps",
+            quote.max_slippage_bps
+        )));
+    }
+    Ok(())
+}
+
+/// The side of the quote the caller fixed, from exactly one of `inr` and `amount`.
+fn requested_amount(
+    decimals: u8,
+    inr: Option<Inr>,
+    amount: Option<String>,
+) -> Result<QuoteAmount, AppError> {
+    match (inr, amount) {
+        (Some(inr), None) => Ok(QuoteAmount::Inr(inr)),
+        (None, Some(amount)) => Ok(QuoteAmount::Token(
+            TokenAmount::parse(&amount, decimals).map_err(AppError::bad_request)?,
+        )),
+        _ => Err(AppError::bad_request(
+            "exactly one of inr and amount is required",
+        )),
+    }
+}
+
+/// Prices `token` without locking anything, either INR -> token (`?inr=`) or token -> INR
+/// (`?amount=`). Open to anyone, so it must not write.
+pub async fn preview_quote(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
     Query(query): Query<QuoteQuery>,
+) -> Result<Json<QuotePreviewResponse>, AppError> {
+    let token = state.registry.enabled(&token)?.clone();
+    let amount = requested_amount(token.decimals, query.inr, query.amount)?;
+    let (inr, token_amount, price) = price_amount(&state, &token.symbol, amount).await?;
+
+    Ok(Json(QuotePreviewResponse {
+        token: token.symbol,
+        inr,
+        token_amount,
+        rate: price.rate,
+        source: price.source,
+        as_of: price.as_of,
+    }))
+}
+
+/// Locks a quote that a payment intent of the signed-in caller can then be priced at.
+pub async fn create_quote(
+    State(state): State<AppState>,
+    _user: AuthUser,
+    Json(payload): Json<CreateQuoteRequest>,
 ) -> Result<Json<QuoteResponse>, AppError> {
-    if query.inr <= 0.0 {
-        return Err(AppError::bad_request("inr must be positive"));
+    let token = state.registry.enabled(&payload.token)?.clone();
+    let amount = requested_amount(token.decimals, payload.inr, payload.amount)?;
+    let max_slippage_bps = payload
+        .max_slippage_bps
+        .unwrap_or(state.config.quote_max_slippage_bps);
+    if max_slippage_bps > state.config.quote_max_slippage_bps {
+        return Err(AppError::bad_request(format!(
+            "maxSlippageBps cannot exceed {}",
+            state.config.quote_max_slippage_bps
+        )));
     }
 
-    let now = Utc::now();
-    let usdc = query.inr / INR_PER_USDC;
-    let expires_at = now + Duration::seconds(30);
+    let quote = lock_quote(&state, &token.symbol, amount, max_slippage_bps).await?;
 
     Ok(Json(QuoteResponse {
-        inr: format!("{:.2}", query.inr),
-        usdc: format!("{:.6}", usdc),
-        rate: format!("{:.2}", INR_PER_USDC),
-        source: "demo_oracle".to_string(),
-        as_of: now,
-        expires_at,
+        quote_id: quote.id,
+        token: quote.token,
+        inr: quote.inr_amount,
+        token_amount: quote.token_amount,
+        rate: quote.rate,
+        source: quote.source,
+        as_of: quote.as_of,
+        expires_at: quote.expires_at,
+        max_slippage_bps: quote.max_slippage_bps,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::state::AppState;
+    use crate::testing::{self, Call};
+    use axum::http::{Method, StatusCode};
+    use chrono::Utc;
+    use serde_json::{json, Value};
+
+    /// Quotes held by the store, counted by evicting them all.
+    async fn stored_quotes(state: &AppState) -> usize {
+        let later = Utc::now() + chrono::Duration::days(1);
+        state.store.evict_expired_quotes(later).await.unwrap()
+    }
+
+    async fn preview(state: &AppState, query: &str) -> (StatusCode, Value) {
+        let (status, _, body) = Call::new(Method::GET, &format!("/v1/quotes/{query}"))
+            .send(state)
+            .await;
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn previews_are_open_to_anyone_and_write_nothing() {
+        let state = testing::state();
+        let (status, body) = preview(&state, "sol?inr=500").await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["token"], "SOL");
+        assert_eq!(body["inr"], "500.00");
+        assert_eq!(body["tokenAmount"], "0.040000000");
+        assert_eq!(body["rate"], "12500.00");
+        assert!(body.get("quoteId").is_none());
+
+        let (status, body) = preview(&state, "SOL?amount=0.04").await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["inr"], "500.00");
+
+        for _ in 0..3 {
+            preview(&state, "SOL?inr=1").await;
+        }
+        assert_eq!(stored_quotes(&state).await, 0);
+    }
+
+    #[tokio::test]
+    async fn locking_a_quote_needs_a_signed_in_caller() {
+        let state = testing::state();
+        let body = json!({ "token": "sol", "inr": "500" });
+        let (status, _, _) = Call::post("/v1/quotes", body.clone()).send(&state).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(stored_quotes(&state).await, 0);
+
+        let (status, _, locked) = Call::post("/v1/quotes", body)
+            .user("alice")
+            .send(&state)
+            .await;
+        assert_eq!(status, StatusCode::OK, "{locked}");
+        assert_eq!(locked["token"], "SOL");
+        assert_eq!(locked["tokenAmount"], "0.040000000");
+        assert_eq!(
+            locked["maxSlippageBps"],
+            state.config.quote_max_slippage_bps
+        );
+        let quote_id = locked["quoteId"].as_str().unwrap();
+        let stored = state.store.quote(quote_id).await.unwrap().unwrap();
+        assert_eq!(stored.intent_id, None);
+        assert_eq!(stored_quotes(&state).await, 1);
+    }
+
+    #[tokio::test]
+    async fn malformed_quote_requests_are_rejected() {
+        let state = testing::state();
+        for query in ["SOL", "SOL?inr=1&amount=1", "SOL?inr=0.001", "DOGE?inr=1"] {
+            let (status, body) = preview(&state, query).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{query}: {body}");
+        }
+
+        let too_loose = state.config.quote_max_slippage_bps + 1;
+        for body in [
+            json!({ "token": "SOL", "inr": "1", "maxSlippageBps": too_loose }),
+            json!({ "token": "SOL", "inr": "1", "amount": "1" }),
+            json!({ "token": "SOL", "amount": "0.0000000001" }),
+        ] {
+            let (status, _, _) = Call::post("/v1/quotes", body.clone())
+                .user("alice")
+                .send(&state)
+                .await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+        }
+        assert_eq!(stored_quotes(&state).await, 0);
+    }
+}
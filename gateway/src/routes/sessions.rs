@@ -1,30 +1,23 @@
 use crate::error::AppError;
+use crate::extract::AuthUser;
 use crate::models::{CreateSessionRequest, SessionResponse};
+use crate::money::Inr;
 use crate::state::{AppState, SessionRecord};
 use axum::extract::State;
-use axum::http::HeaderMap;
 use axum::Json;
 use chrono::{Duration, Utc};
 use uuid::Uuid;
 
-fn user_id_from_headers(headers: &HeaderMap) -> Result<String, AppError> {
-    headers
-        .get("x-user-id")
-        .and_then(|v| v.to_str().ok())
-        .map(|v| v.to_string())
-        .ok_or_else(|| AppError::unauthorized("x-user-id header is required"))
-}
-
 pub async fn create_or_refresh_session(
     State(state): State<AppState>,
-    headers: HeaderMap,
+    user: AuthUser,
     Json(payload): Json<CreateSessionRequest>,
 ) -> Result<Json<SessionResponse>, AppError> {
-    let user_id = user_id_from_headers(&headers)?;
+    let user_id = user.user_id;
     if payload.ttl_minutes <= 0 {
         return Err(AppError::bad_request("ttlMinutes must be positive"));
     }
-    if payload.per_tx_limit_inr <= 0.0 || payload.daily_limit_inr <= 0.0 {
+    if payload.per_tx_limit_inr.is_zero() || payload.daily_limit_inr.is_zero() {
         return Err(AppError::bad_request("session limits must be positive"));
     }
 
@@ -33,17 +26,30 @@ pub async fn create_or_refresh_session(
 
     let record = SessionRecord {
         id: id.clone(),
-        wallet: payload.wallet.clone(),
+        wallet: if payload.wallet.trim().is_empty() {
+            user.wallet
+        } else {
+            payload.wallet
+        },
         device_id: payload.device_id,
         per_tx_limit_inr: payload.per_tx_limit_inr,
         daily_limit_inr: payload.daily_limit_inr,
-        used_today_inr: 0.0,
+        used_today_inr: Inr::ZERO,
         expires_at,
         status: "active".to_string(),
     };
 
-    let mut store = state.store.write().await;
-    store.sessions.insert(user_id, record.clone());
+    let linked = state
+        .store
+        .wallet_link(&record.wallet)
+        .await?
+        .is_some_and(|link| link.user_id == user_id);
+    if !linked {
+        return Err(AppError::unauthorized(
+            "wallet is not linked to this account",
+        ));
+    }
+    state.store.put_session(&user_id, record.clone()).await?;
 
     Ok(Json(SessionResponse {
         id: record.id,
@@ -51,30 +57,164 @@ pub async fn create_or_refresh_session(
         wallet: record.wallet,
         per_tx_limit_inr: record.per_tx_limit_inr,
         daily_limit_inr: record.daily_limit_inr,
-        remaining_today_inr: record.daily_limit_inr - record.used_today_inr,
+        remaining_today_inr: record.daily_limit_inr.saturating_sub(record.used_today_inr),
         expires_at: record.expires_at,
     }))
 }
 
 pub async fn get_current_session(
     State(state): State<AppState>,
-    headers: HeaderMap,
+    user: AuthUser,
 ) -> Result<Json<SessionResponse>, AppError> {
-    let user_id = user_id_from_headers(&headers)?;
+    let user_id = user.user_id;
 
-    let store = state.store.read().await;
-    let record = store
-        .sessions
-        .get(&user_id)
+    let record = state
+        .store
+        .session(&user_id)
+        .await?
         .ok_or_else(|| AppError::not_found("no active session found"))?;
 
     Ok(Json(SessionResponse {
-        id: record.id.clone(),
-        status: record.status.clone(),
-        wallet: record.wallet.clone(),
+        id: record.id,
+        status: record.status,
+        wallet: record.wallet,
         per_tx_limit_inr: record.per_tx_limit_inr,
         daily_limit_inr: record.daily_limit_inr,
-        remaining_today_inr: record.daily_limit_inr - record.used_today_inr,
+        remaining_today_inr: record.daily_limit_inr.saturating_sub(record.used_today_inr),
         expires_at: record.expires_at,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::state::AppState;
+    use crate::testing::{self, Call};
+    use axum::http::{Method, StatusCode};
+    use chrono::Utc;
+    use serde_json::{json, Value};
+
+    /// The same gateway over each `Store` implementation.
+    fn request(wallet: Option<&str>) -> Value {
+        let mut body = json!({
+            "deviceId": "phone",
+            "perTxLimitInr": "500.00",
+            "dailyLimitInr": "2000.00",
+            "ttlMinutes": 30,
+        });
+        if let Some(wallet) = wallet {
+            body["wallet"] = json!(wallet);
+        }
+        body
+    }
+
+    async fn current(state: &AppState, user_id: &str) -> (StatusCode, Value) {
+        let (status, _, body) = Call::new(Method::GET, "/v1/sessions/current")
+            .user(user_id)
+            .send(state)
+            .await;
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn a_session_defaults_to_the_primary_wallet() {
+        for state in testing::states().await {
+            state
+                .store
+                .link_wallet("alice", "alice_wallet", Utc::now())
+                .await
+                .unwrap();
+            let (status, _) = current(&state, "alice").await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+
+            let (status, _, created) = Call::post("/v1/sessions", request(None))
+                .user("alice")
+                .send(&state)
+                .await;
+            assert_eq!(status, StatusCode::OK, "{created}");
+            assert_eq!(created["wallet"], "alice_wallet");
+            assert_eq!(created["status"], "active");
+            assert_eq!(created["remainingTodayInr"], "2000.00");
+
+            let (status, session) = current(&state, "alice").await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(session["id"], created["id"]);
+            assert_eq!(session["wallet"], "alice_wallet");
+            assert_eq!(session["remainingTodayInr"], "2000.00");
+        }
+    }
+
+    #[tokio::test]
+    async fn a_session_is_only_opened_for_a_wallet_linked_to_the_caller() {
+        for state in testing::states().await {
+            for (user, wallet) in [("alice", "alice_wallet"), ("bob", "bob_wallet")] {
+                state
+                    .store
+                    .link_wallet(user, wallet, Utc::now())
+                    .await
+                    .unwrap();
+            }
+            for wallet in ["bob_wallet", "nobodys_wallet"] {
+                let (status, _, _) = Call::post("/v1/sessions", request(Some(wallet)))
+                    .user("alice")
+                    .send(&state)
+                    .await;
+                assert_eq!(status, StatusCode::UNAUTHORIZED, "{wallet}");
+            }
+            // A user without any wallet has nothing to default to.
+            let (status, _, _) = Call::post("/v1/sessions", request(None))
+                .user("carol")
+                .send(&state)
+                .await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+            assert_eq!(current(&state, "alice").await.0, StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[tokio::test]
+    async fn opening_a_session_again_replaces_the_previous_one() {
+        for state in testing::states().await {
+            state
+                .store
+                .link_wallet("alice", "alice_wallet", Utc::now())
+                .await
+                .unwrap();
+            let (_, _, first) = Call::post("/v1/sessions", request(None))
+                .user("alice")
+                .send(&state)
+                .await;
+            let mut second = request(None);
+            second["dailyLimitInr"] = json!("800.00");
+            let (_, _, second) = Call::post("/v1/sessions", second)
+                .user("alice")
+                .send(&state)
+                .await;
+            assert_ne!(first["id"], second["id"]);
+            let (_, session) = current(&state, "alice").await;
+            assert_eq!(session["id"], second["id"]);
+            assert_eq!(session["dailyLimitInr"], "800.00");
+        }
+    }
+
+    #[tokio::test]
+    async fn limits_and_lifetimes_must_be_positive() {
+        let state = testing::state();
+        state
+            .store
+            .link_wallet("alice", "alice_wallet", Utc::now())
+            .await
+            .unwrap();
+        for (field, value) in [
+            ("ttlMinutes", json!(0)),
+            ("perTxLimitInr", json!("0")),
+            ("dailyLimitInr", json!("0.00")),
+        ] {
+            let mut body = request(None);
+            body[field] = value;
+            let (status, _, _) = Call::post("/v1/sessions", body)
+                .user("alice")
+                .send(&state)
+                .await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{field}");
+        }
+    }
+}
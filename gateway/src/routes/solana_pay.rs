@@ -0,0 +1,709 @@
+use crate::error::AppError;
+use crate::extract::AuthUser;
+use crate::lifecycle::IntentStatus;
+use crate::memo;
+use crate::models::{
+    HandleSolanaPayQuery, ParseSolanaPayRequest, QrQuery, SolanaPayQuery, SolanaPayResponse,
+    TransactionRequest, TransactionRequestMetadata, TransactionRequestQuery, TransactionResponse,
+};
+use crate::money::TokenAmount;
+use crate::qr::{self, QrFormat};
+use crate::registry::{unsupported_token, TokenInfo};
+use crate::routes::{handles, quotes};
+use crate::solana::pay::{self, SolanaPayUrl, TransferRequest};
+use crate::solana::transaction::{self, AccountMeta, Instruction};
+use crate::solana::{self, Pubkey};
+use crate::state::{AppState, PaymentIntentRecord};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use axum::Json;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::Utc;
+
+fn not_payable(message: impl Into<String>) -> AppError {
+    AppError::coded(StatusCode::CONFLICT, "INTENT_NOT_PAYABLE", message)
+}
+
+fn ensure_payable(intent: &PaymentIntentRecord) -> Result<(), AppError> {
+    if matches!(
+        intent.status,
+        IntentStatus::Created | IntentStatus::Authorized
+    ) {
+        Ok(())
+    } else {
+        Err(not_payable(format!("payment intent is {}", intent.status)))
+    }
+}
+
+/// Public Solana Pay transaction request link for an intent.
+pub fn transaction_url(state: &AppState, intent_id: &str) -> String {
+    format!(
+        "{}/v1/payment-intents/{intent_id}/transaction",
+        state.config.public_url
+    )
+}
+
+fn payment_message(intent: &PaymentIntentRecord) -> String {
+    format!(
+        "Pay {} {} (INR {})",
+        intent.token_amount, intent.token, intent.inr_amount
+    )
+}
+
+fn mint_of(token: &TokenInfo) -> Result<Option<Pubkey>, AppError> {
+    token
+        .mint
+        .as_deref()
+        .map(Pubkey::parse)
+        .transpose()
+        .map_err(AppError::internal)
+}
+
+/// Describes a Solana Pay URL, naming the token when the registry knows its mint.
+fn describe(state: &AppState, url: SolanaPayUrl) -> Result<SolanaPayResponse, AppError> {
+    let request = match url {
+        SolanaPayUrl::Transaction(link) => {
+            return Ok(SolanaPayResponse {
+                kind: "transaction",
+                url: pay::transaction_request_url(&link),
+                link: Some(link),
+                recipient: None,
+                amount: None,
+                token: None,
+                spl_token: None,
+                references: Vec::new(),
+                label: None,
+                message: None,
+                memo: None,
+            })
+        }
+        SolanaPayUrl::Transfer(request) => request,
+    };
+
+    let mint = request.spl_token.map(|mint| mint.to_string());
+    let token = state
+        .registry
+        .tokens()
+        .iter()
+        .find(|token| token.mint == mint);
+    if let (Some(token), Some(amount)) = (token, &request.amount) {
+        TokenAmount::parse(amount, token.decimals)
+            .map_err(|err| AppError::bad_request(format!("amount: {err}")))?;
+    }
+    Ok(SolanaPayResponse {
+        kind: "transfer",
+        url: request.to_url(),
+        link: None,
+        recipient: Some(request.recipient.to_string()),
+        amount: request.amount,
+        token: token.map(|token| token.symbol.clone()),
+        spl_token: mint,
+        references: request
+            .references
+            .iter()
+            .map(|reference| reference.to_string())
+            .collect(),
+        label: request.label,
+        message: request.message,
+        memo: request.memo,
+    })
+}
+
+/// The intent's Solana Pay URL: a transfer request for its exact amount and reference by
+/// default, or `kind=transaction` for its transaction request link.
+async fn intent_solana_pay(
+    state: &AppState,
+    user: AuthUser,
+    id: &str,
+    kind: Option<&str>,
+) -> Result<SolanaPayUrl, AppError> {
+    let intent = state
+        .store
+        .payment_intent(id)
+        .await?
+        .ok_or_else(|| AppError::not_found("payment intent not found"))?;
+    if intent.creator_user_id != user.user_id {
+        return Err(AppError::unauthorized(
+            "payment intent does not belong to user",
+        ));
+    }
+    match kind {
+        None | Some("transfer") => {}
+        Some("transaction") => {
+            return Ok(SolanaPayUrl::Transaction(transaction_url(
+                state, &intent.id,
+            )))
+        }
+        Some(other) => {
+            return Err(AppError::bad_request(format!(
+                "kind `{other}` is not supported, use transfer or transaction"
+            )))
+        }
+    }
+
+    let token = state
+        .registry
+        .get(&intent.token)
+        .ok_or_else(|| unsupported_token(format!("{} is no longer configured", intent.token)))?;
+    let recipient = Pubkey::parse(&intent.recipient_wallet).map_err(AppError::internal)?;
+    let reference = Pubkey::parse(&intent.reference)
+        .map_err(|_| not_payable("payment intent has no Solana Pay reference"))?;
+    let mut request = TransferRequest::new(recipient);
+    request.amount = Some(intent.token_amount.to_string());
+    request.spl_token = mint_of(token)?;
+    request.references.push(reference);
+    request.label = Some(state.config.solana_pay_label.clone());
+    request.message = Some(payment_message(&intent));
+    request.memo = intent.memo;
+    Ok(SolanaPayUrl::Transfer(request))
+}
+
+/// A reusable transfer request paying the handle's wallet, optionally for a fixed amount.
+async fn handle_solana_pay(
+    state: &AppState,
+    handle: &str,
+    query: HandleSolanaPayQuery,
+) -> Result<SolanaPayUrl, AppError> {
+    let handle = handles::normalize_handle(handle);
+    let wallet = state
+        .store
+        .handle_wallet(&handle)
+        .await?
+        .ok_or_else(|| AppError::not_found("handle not found"))?;
+    let recipient = Pubkey::parse(&wallet).map_err(|_| {
+        AppError::bad_request(format!("{handle} is not linked to a Solana address"))
+    })?;
+
+    let token = state
+        .registry
+        .enabled(query.token.as_deref().unwrap_or("SOL"))?;
+    let amount = query
+        .amount
+        .map(|amount| {
+            TokenAmount::parse(&amount, token.decimals)
+                .map_err(|err| AppError::bad_request(format!("amount: {err}")))
+        })
+        .transpose()?;
+    if amount.is_some_and(TokenAmount::is_zero) {
+        return Err(AppError::bad_request("amount must be positive"));
+    }
+
+    let mut request = TransferRequest::new(recipient);
+    request.amount = amount.map(|amount| amount.to_string());
+    request.spl_token = mint_of(token)?;
+    request.label = Some(query.label.unwrap_or(handle));
+    request.message = query.message;
+    request.memo = match &query.memo {
+        Some(memo) => memo::clean(&state.config, memo)?,
+        None => None,
+    };
+    Ok(SolanaPayUrl::Transfer(request))
+}
+
+pub async fn get_intent_solana_pay(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(id): Path<String>,
+    Query(query): Query<SolanaPayQuery>,
+) -> Result<Json<SolanaPayResponse>, AppError> {
+    let url = intent_solana_pay(&state, user, &id, query.kind.as_deref()).await?;
+    Ok(Json(describe(&state, url)?))
+}
+
+pub async fn get_intent_qr(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(id): Path<String>,
+    Query(query): Query<SolanaPayQuery>,
+    Query(qr_query): Query<QrQuery>,
+) -> Result<Response, AppError> {
+    let format = QrFormat::parse(qr_query.format.as_deref())?;
+    let url = intent_solana_pay(&state, user, &id, query.kind.as_deref()).await?;
+    qr::render(&describe(&state, url)?.url, format, qr_query.size)
+}
+
+pub async fn get_handle_solana_pay(
+    State(state): State<AppState>,
+    Path(handle): Path<String>,
+    Query(query): Query<HandleSolanaPayQuery>,
+) -> Result<Json<SolanaPayResponse>, AppError> {
+    let url = handle_solana_pay(&state, &handle, query).await?;
+    Ok(Json(describe(&state, url)?))
+}
+
+pub async fn get_handle_qr(
+    State(state): State<AppState>,
+    Path(handle): Path<String>,
+    Query(query): Query<HandleSolanaPayQuery>,
+    Query(qr_query): Query<QrQuery>,
+) -> Result<Response, AppError> {
+    let format = QrFormat::parse(qr_query.format.as_deref())?;
+    let url = handle_solana_pay(&state, &handle, query).await?;
+    qr::render(&describe(&state, url)?.url, format, qr_query.size)
+}
+
+/// Decodes a scanned `solana:` URL so clients need no Solana Pay parser of their own.
+pub async fn parse_solana_pay(
+    State(state): State<AppState>,
+    Json(payload): Json<ParseSolanaPayRequest>,
+) -> Result<Json<SolanaPayResponse>, AppError> {
+    let url = pay::parse(&payload.url).map_err(AppError::bad_request)?;
+    Ok(Json(describe(&state, url)?))
+}
+
+/// Transfer of the intent amount from `payer` to the recipient, tagged with the intent reference
+/// as a read-only account so the payment can be found on chain. Like Solana Pay wallets, the
+/// memo goes in its own instruction right before the transfer.
+fn transfer_instructions(
+    token: &TokenInfo,
+    intent: &PaymentIntentRecord,
+    payer: Pubkey,
+) -> Result<Vec<Instruction>, AppError> {
+    let recipient = Pubkey::parse(&intent.recipient_wallet).map_err(AppError::internal)?;
+    // Intents created before references were keys cannot be located on chain.
+    let reference = Pubkey::parse(&intent.reference)
+        .map_err(|_| not_payable("payment intent has no Solana Pay reference"))?;
+    let amount = intent.token_amount;
+    if amount.decimals() != token.decimals {
+        return Err(not_payable(format!(
+            "payment intent was priced with {} decimals but {} has {}",
+            amount.decimals(),
+            token.symbol,
+            token.decimals
+        )));
+    }
+
+    let mut instructions = Vec::new();
+    let mut transfer = match (&token.mint, &token.token_program) {
+        (Some(mint), Some(program)) => {
+            let mint = Pubkey::parse(mint).map_err(AppError::internal)?;
+            let program = Pubkey::parse(program).map_err(AppError::internal)?;
+            instructions.push(transaction::create_associated_token_account_idempotent(
+                payer, recipient, mint, program,
+            ));
+            transaction::transfer_checked(
+                program,
+                Pubkey::associated_token_address(&payer, &mint, &program),
+                mint,
+                Pubkey::associated_token_address(&recipient, &mint, &program),
+                payer,
+                amount.base_units(),
+                amount.decimals(),
+            )
+        }
+        _ => transaction::system_transfer(payer, recipient, amount.base_units()),
+    };
+    transfer
+        .accounts
+        .push(AccountMeta::readonly(reference, false));
+    if let Some(memo) = &intent.memo {
+        instructions.push(transaction::memo(memo));
+    }
+    instructions.push(transfer);
+    Ok(instructions)
+}
+
+/// Solana Pay transaction request metadata shown by the wallet before it posts the payer account.
+pub async fn transaction_metadata(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<TransactionRequestMetadata>, AppError> {
+    state
+        .store
+        .payment_intent(&id)
+        .await?
+        .ok_or_else(|| AppError::not_found("payment intent not found"))?;
+    Ok(Json(TransactionRequestMetadata {
+        label: state.config.solana_pay_label.clone(),
+        icon: state.config.solana_pay_icon.clone(),
+    }))
+}
+
+/// Solana Pay transaction request: an unsigned transfer for `account` to sign and send.
+///
+/// Unauthenticated like any Solana Pay link; the unguessable intent id is the capability.
+pub async fn create_transaction(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<TransactionRequestQuery>,
+    Json(payload): Json<TransactionRequest>,
+) -> Result<Json<TransactionResponse>, AppError> {
+    let payer = Pubkey::parse(&payload.account)
+        .map_err(|err| AppError::bad_request(format!("account: {err}")))?;
+    let intent = state
+        .store
+        .payment_intent(&id)
+        .await?
+        .ok_or_else(|| AppError::not_found("payment intent not found"))?;
+    if payload.account != intent.payer_wallet {
+        return Err(AppError::bad_request(
+            "account is not the payer wallet of this payment intent",
+        ));
+    }
+    ensure_payable(&intent)?;
+    if Utc::now() > intent.quote_expires_at {
+        return Err(quotes::quote_expired());
+    }
+    let token = state
+        .registry
+        .get(&intent.token)
+        .ok_or_else(|| unsupported_token(format!("{} is no longer configured", intent.token)))?;
+
+    let instructions = transfer_instructions(token, &intent, payer)?;
+    let blockhash = match &query.blockhash {
+        Some(blockhash) => solana::decode_32(blockhash)
+            .map_err(|err| AppError::bad_request(format!("blockhash: {err}")))?,
+        None => state.rpc.latest_blockhash().await?,
+    };
+    let transaction = transaction::unsigned_transaction(payer, &instructions, blockhash);
+
+    // Checked again under the store's lock so an intent cancelled meanwhile is not served.
+    let served_at = Utc::now();
+    state
+        .store
+        .update_payment_intent(
+            &id,
+            Box::new(move |intent| {
+                ensure_payable(intent)?;
+                intent.transaction_served_at = Some(served_at);
+                Ok(())
+            }),
+        )
+        .await?;
+
+    Ok(Json(TransactionResponse {
+        transaction: BASE64.encode(transaction),
+        message: payment_message(&intent),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::{ASSOCIATED_TOKEN_PROGRAM, MEMO_PROGRAM, SYSTEM_PROGRAM};
+    use crate::testing::{self, key, Call};
+    use axum::http::Method;
+    use serde_json::json;
+
+    fn intent(token: &TokenInfo, memo: Option<&str>) -> PaymentIntentRecord {
+        let mut intent = testing::intent("pi_1", "alice", &key(2).to_string(), 100, Utc::now());
+        intent.reference = key(3).to_string();
+        intent.token = token.symbol.clone();
+        intent.token_amount = TokenAmount::new(1_000, token.decimals);
+        intent.memo = memo.map(str::to_string);
+        intent
+    }
+
+    fn programs(instructions: &[Instruction]) -> Vec<String> {
+        instructions
+            .iter()
+            .map(|instruction| instruction.program_id.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn sol_memo_goes_right_before_the_transfer() {
+        let state = testing::state();
+        let sol = state.registry.get("SOL").unwrap();
+        let instructions = transfer_instructions(sol, &intent(sol, Some("chai")), key(1)).unwrap();
+
+        assert_eq!(programs(&instructions), [MEMO_PROGRAM, SYSTEM_PROGRAM]);
+        assert_eq!(instructions[0].data, b"chai");
+        assert!(instructions[0].accounts.is_empty());
+        let reference = instructions[1].accounts.last().unwrap();
+        assert_eq!(reference.pubkey, key(3));
+        assert!(!reference.is_signer && !reference.is_writable);
+    }
+
+    #[test]
+    fn spl_transfer_creates_the_recipient_account_idempotently() {
+        let state = testing::state();
+        let usdc = state.registry.get("USDC").unwrap();
+        let program = usdc.token_program.clone().unwrap();
+        let instructions =
+            transfer_instructions(usdc, &intent(usdc, Some("rent")), key(1)).unwrap();
+
+        assert_eq!(
+            programs(&instructions),
+            [ASSOCIATED_TOKEN_PROGRAM, MEMO_PROGRAM, program.as_str()]
+        );
+        assert_eq!(instructions[0].data, [1]);
+        let transfer = &instructions[2];
+        assert_eq!(transfer.accounts.len(), 5);
+        assert_eq!(transfer.accounts[4].pubkey, key(3));
+        assert!(!transfer.accounts[4].is_signer && !transfer.accounts[4].is_writable);
+        // The created account is the one the transfer pays.
+        assert_eq!(
+            instructions[0].accounts[1].pubkey,
+            transfer.accounts[2].pubkey
+        );
+    }
+
+    #[test]
+    fn no_memo_instruction_without_a_memo() {
+        let state = testing::state();
+        let sol = state.registry.get("SOL").unwrap();
+        let instructions = transfer_instructions(sol, &intent(sol, None), key(1)).unwrap();
+        assert_eq!(programs(&instructions), [SYSTEM_PROGRAM]);
+    }
+
+    #[test]
+    fn intents_without_a_key_reference_are_not_payable() {
+        let state = testing::state();
+        let sol = state.registry.get("SOL").unwrap();
+        let mut intent = intent(sol, None);
+        intent.reference = "ref_legacy".to_string();
+        let err = transfer_instructions(sol, &intent, key(1)).unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::Coded {
+                code: "INTENT_NOT_PAYABLE",
+                ..
+            }
+        ));
+    }
+
+    /// A created SOL intent paid from `key(1)`, stored in a fresh state.
+    async fn payable() -> AppState {
+        let state = testing::state();
+        let sol = state.registry.get("SOL").unwrap().clone();
+        let mut record = intent(&sol, None);
+        record.payer_wallet = key(1).to_string();
+        state.store.insert_payment_intent(record).await.unwrap();
+        state
+    }
+
+    fn request(account: Pubkey) -> Call {
+        let blockhash = bs58::encode([0; 32]).into_string();
+        Call::post(
+            &format!("/v1/payment-intents/pi_1/transaction?blockhash={blockhash}"),
+            json!({ "account": account.to_string() }),
+        )
+    }
+
+    async fn served_at(state: &AppState) -> Option<chrono::DateTime<Utc>> {
+        let intent = state.store.payment_intent("pi_1").await.unwrap().unwrap();
+        intent.transaction_served_at
+    }
+
+    #[tokio::test]
+    async fn transactions_are_served_to_the_payer_and_recorded() {
+        let state = payable().await;
+        let (status, _, body) = request(key(1)).send(&state).await;
+        assert_eq!(status, StatusCode::OK);
+        let bytes = BASE64
+            .decode(body["transaction"].as_str().unwrap())
+            .unwrap();
+        // One zeroed signature slot, then the header and the payer as first account.
+        assert_eq!(bytes[0], 1);
+        assert_eq!(bytes[1..65], [0; 64]);
+        assert_eq!(bytes[69..101], key(1).to_bytes());
+        assert!(served_at(&state).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn other_accounts_are_refused() {
+        let state = payable().await;
+        let (status, _, body) = request(key(5)).send(&state).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body["error"]["message"],
+            "account is not the payer wallet of this payment intent"
+        );
+        assert_eq!(served_at(&state).await, None);
+    }
+
+    #[tokio::test]
+    async fn closed_intents_are_not_served() {
+        let state = payable().await;
+        state
+            .store
+            .update_payment_intent(
+                "pi_1",
+                Box::new(|intent| {
+                    intent.status = IntentStatus::Cancelled;
+                    Ok(())
+                }),
+            )
+            .await
+            .unwrap();
+        let (status, _, body) = request(key(1)).send(&state).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "INTENT_NOT_PAYABLE");
+        assert_eq!(served_at(&state).await, None);
+    }
+
+    fn cancel() -> Call {
+        Call::post(
+            "/v1/payment-intents/pi_1/cancel",
+            json!({ "reason": "payer_abandoned" }),
+        )
+        .user("alice")
+    }
+
+    #[tokio::test]
+    async fn served_intents_can_no_longer_be_cancelled() {
+        let state = payable().await;
+        let (status, _, _) = request(key(1)).send(&state).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // The wallet holds a payable transaction, so the payer cannot abandon the intent.
+        let (status, _, body) = cancel().send(&state).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "TRANSACTION_SERVED");
+        let intent = state.store.payment_intent("pi_1").await.unwrap().unwrap();
+        assert_eq!(intent.status, IntentStatus::Created);
+    }
+
+    #[tokio::test]
+    async fn cancelled_intents_are_not_served() {
+        let state = payable().await;
+        let (status, _, body) = cancel().send(&state).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["status"], "cancelled");
+
+        let (status, _, body) = request(key(1)).send(&state).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "INTENT_NOT_PAYABLE");
+        assert_eq!(served_at(&state).await, None);
+    }
+
+    async fn get(state: &AppState, uri: &str) -> (StatusCode, serde_json::Value) {
+        let (status, _, body) = Call::new(Method::GET, uri).user("alice").send(state).await;
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn intent_urls_carry_the_exact_amount_and_reference() {
+        let state = payable().await;
+        let (status, body) = get(&state, "/v1/payment-intents/pi_1/solana-pay").await;
+        assert_eq!(status, StatusCode::OK);
+        let url = format!(
+            "solana:{}?amount=0.000001000&reference={}&label=monopay\
+             &message=Pay%200.000001000%20SOL%20(INR%201.00)",
+            key(2),
+            key(3)
+        );
+        assert_eq!(
+            body,
+            json!({
+                "kind": "transfer",
+                "url": url,
+                "link": null,
+                "recipient": key(2).to_string(),
+                "amount": "0.000001000",
+                "token": "SOL",
+                "splToken": null,
+                "references": [key(3).to_string()],
+                "label": "monopay",
+                "message": "Pay 0.000001000 SOL (INR 1.00)",
+                "memo": null,
+            })
+        );
+
+        let (status, parsed) = get(
+            &state,
+            "/v1/payment-intents/pi_1/solana-pay?kind=transaction",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let link = transaction_url(&state, "pi_1");
+        assert_eq!(parsed["kind"], "transaction");
+        assert_eq!(parsed["link"], link);
+        assert_eq!(parsed["url"], pay::transaction_request_url(&link));
+
+        let (status, _, _) = Call::new(Method::GET, "/v1/payment-intents/pi_1/solana-pay")
+            .user("mallory")
+            .send(&state)
+            .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = get(&state, "/v1/payment-intents/pi_1/solana-pay?kind=nfc").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn intent_qr_codes_render_as_svg_or_png() {
+        let state = payable().await;
+        let (status, headers, svg) = Call::new(Method::GET, "/v1/payment-intents/pi_1/qr")
+            .user("alice")
+            .send_text(&state)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers["content-type"], "image/svg+xml");
+        assert_eq!(headers["cache-control"], "no-store");
+        assert!(svg.contains("<svg"));
+
+        let (status, headers, _) = Call::new(
+            Method::GET,
+            "/v1/payment-intents/pi_1/qr?format=png&size=128",
+        )
+        .user("alice")
+        .send_text(&state)
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers["content-type"], "image/png");
+
+        let (status, _) = get(&state, "/v1/payment-intents/pi_1/qr?format=gif").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn handle_urls_pay_the_handle_wallet() {
+        let state = testing::state();
+        state
+            .store
+            .claim_handle("priya@monopay.app", &key(2).to_string())
+            .await
+            .unwrap();
+        let usdc = state.registry.get("USDC").unwrap().clone();
+
+        let (status, body) =
+            get(&state, "/v1/handles/priya/solana-pay?token=USDC&amount=1.5").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["recipient"], key(2).to_string());
+        assert_eq!(body["amount"], "1.500000");
+        assert_eq!(body["token"], "USDC");
+        assert_eq!(body["splToken"], usdc.mint.unwrap());
+        assert_eq!(body["label"], "priya@monopay.app");
+        assert_eq!(body["references"], json!([]));
+
+        let (status, parsed) = get(&state, "/v1/handles/priya/solana-pay").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(parsed["amount"], serde_json::Value::Null);
+        assert_eq!(parsed["token"], "SOL");
+
+        let (status, _) = get(&state, "/v1/handles/priya/solana-pay?amount=0.0000000001").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = get(&state, "/v1/handles/nobody/solana-pay").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn generated_urls_parse_back_to_the_same_fields() {
+        let state = payable().await;
+        let (_, generated) = get(&state, "/v1/payment-intents/pi_1/solana-pay").await;
+        let (status, _, parsed) =
+            Call::post("/v1/solana-pay/parse", json!({ "url": generated["url"] }))
+                .send(&state)
+                .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(parsed, generated);
+
+        let (status, _, _) = Call::post(
+            "/v1/solana-pay/parse",
+            json!({ "url": "solana:https://example.com/tx" }),
+        )
+        .send(&state)
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _, _) = Call::post(
+            "/v1/solana-pay/parse",
+            json!({ "url": "solana:http%3A%2F%2Fexample.com%2Ftx" }),
+        )
+        .send(&state)
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
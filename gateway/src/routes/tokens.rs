@@ -0,0 +1,60 @@
+use crate::models::{TokenListResponse, TokenResponse};
+use crate::state::AppState;
+use axum::extract::State;
+use axum::Json;
+
+/// Tokens in the registry for this gateway's cluster, including disabled ones.
+pub async fn list_tokens(State(state): State<AppState>) -> Json<TokenListResponse> {
+    let tokens = state
+        .registry
+        .tokens()
+        .iter()
+        .map(|token| TokenResponse {
+            symbol: token.symbol.clone(),
+            mint: token.mint.clone(),
+            token_program: token.token_program.clone(),
+            decimals: token.decimals,
+            cluster: token.cluster.chain_id().to_string(),
+            enabled: token.enabled,
+            explorer_url: token.explorer_url(),
+        })
+        .collect();
+    Json(TokenListResponse { tokens })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Cluster;
+    use crate::registry::TokenRegistry;
+    use crate::testing::{self, Call};
+    use axum::http::{Method, StatusCode};
+    use serde_json::Value;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn lists_the_cluster_registry_including_disabled_tokens() {
+        let mut state = testing::state();
+        let registry = TokenRegistry::parse(include_str!("../../tokens.json"), Cluster::Mainnet);
+        state.registry = Arc::new(registry.unwrap());
+        let (status, _, body) = Call::new(Method::GET, "/v1/tokens").send(&state).await;
+        assert_eq!(status, StatusCode::OK);
+        let symbols: Vec<(&str, bool)> = body["tokens"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| (t["symbol"].as_str().unwrap(), t["enabled"] == true))
+            .collect();
+        assert_eq!(
+            symbols,
+            [
+                ("SOL", true),
+                ("USDC", true),
+                ("USDT", false),
+                ("PYUSD", false)
+            ]
+        );
+        assert_eq!(body["tokens"][0]["mint"], Value::Null);
+        assert_eq!(body["tokens"][1]["cluster"], "mainnet");
+        assert_eq!(body["tokens"][1]["decimals"], 6);
+    }
+}
@@ -0,0 +1,373 @@
+use crate::error::AppError;
+use crate::extract::AuthUser;
+use crate::models::{
+    LinkWalletRequest, NonceRequest, NonceResponse, WalletLinkResponse, WalletListResponse,
+};
+use crate::routes::auth;
+use crate::state::{AppState, WalletLinkRecord};
+use crate::store::Store;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::Utc;
+
+fn to_response(link: &WalletLinkRecord) -> WalletLinkResponse {
+    WalletLinkResponse {
+        wallet: link.pubkey.clone(),
+        chain: link.chain.clone(),
+        is_primary: link.is_primary,
+        linked_at: link.linked_at,
+    }
+}
+
+async fn list_response(store: &dyn Store, user_id: &str) -> Result<WalletListResponse, AppError> {
+    Ok(WalletListResponse {
+        wallets: store
+            .user_wallets(user_id)
+            .await?
+            .iter()
+            .map(to_response)
+            .collect(),
+    })
+}
+
+async fn ensure_unlinked(store: &dyn Store, wallet: &str) -> Result<(), AppError> {
+    if store.wallet_link(wallet).await?.is_some() {
+        return Err(AppError::coded(
+            StatusCode::CONFLICT,
+            "WALLET_ALREADY_LINKED",
+            "wallet is already linked to an account",
+        ));
+    }
+    Ok(())
+}
+
+async fn owned_link(
+    store: &dyn Store,
+    user_id: &str,
+    wallet: &str,
+) -> Result<WalletLinkRecord, AppError> {
+    store
+        .wallet_link(wallet)
+        .await?
+        .filter(|link| link.user_id == user_id)
+        .ok_or_else(|| AppError::not_found("wallet is not linked to this account"))
+}
+
+pub async fn create_link_challenge(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Json(payload): Json<NonceRequest>,
+) -> Result<Json<NonceResponse>, AppError> {
+    ensure_unlinked(state.store.as_ref(), &payload.wallet).await?;
+    let response = auth::issue_challenge(
+        &state.config,
+        state.store.as_ref(),
+        &payload.wallet,
+        Some(user.user_id),
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+pub async fn link_wallet(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Json(payload): Json<LinkWalletRequest>,
+) -> Result<Json<WalletLinkResponse>, AppError> {
+    ensure_unlinked(state.store.as_ref(), &payload.wallet).await?;
+    let challenge = auth::redeem_challenge(
+        &state.config,
+        state.store.as_ref(),
+        &payload.wallet,
+        &payload.message,
+        &payload.signature,
+    )
+    .await?;
+    if challenge.link_user_id.as_deref() != Some(user.user_id.as_str()) {
+        return Err(AppError::unauthorized(
+            "challenge was not issued to link a wallet for this user",
+        ));
+    }
+
+    let link = state
+        .store
+        .link_wallet(&user.user_id, &payload.wallet, Utc::now())
+        .await?;
+
+    Ok(Json(to_response(&link)))
+}
+
+pub async fn list_wallets(
+    State(state): State<AppState>,
+    user: AuthUser,
+) -> Result<Json<WalletListResponse>, AppError> {
+    Ok(Json(
+        list_response(state.store.as_ref(), &user.user_id).await?,
+    ))
+}
+
+pub async fn unlink_wallet(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(wallet): Path<String>,
+) -> Result<Json<WalletListResponse>, AppError> {
+    let link = owned_link(state.store.as_ref(), &user.user_id, &wallet).await?;
+    if link.is_primary {
+        return Err(AppError::bad_request(
+            "set another primary wallet before unlinking this one",
+        ));
+    }
+
+    state.store.unlink_wallet(&user.user_id, &wallet).await?;
+    // Anything authorized by the unlinked wallet stops working with it.
+    if state
+        .store
+        .session(&user.user_id)
+        .await?
+        .is_some_and(|session| session.wallet == wallet)
+    {
+        state.store.delete_session(&user.user_id).await?;
+    }
+    state
+        .store
+        .revoke_token_families(&user.user_id, None, Some(&wallet), Utc::now())
+        .await?;
+
+    Ok(Json(
+        list_response(state.store.as_ref(), &user.user_id).await?,
+    ))
+}
+
+pub async fn set_primary_wallet(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(wallet): Path<String>,
+) -> Result<Json<WalletListResponse>, AppError> {
+    owned_link(state.store.as_ref(), &user.user_id, &wallet).await?;
+    state
+        .store
+        .set_primary_wallet(&user.user_id, &wallet)
+        .await?;
+
+    Ok(Json(
+        list_response(state.store.as_ref(), &user.user_id).await?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::AppState;
+    use crate::testing::{self, Call, Wallet};
+    use axum::http::{Method, StatusCode};
+    use chrono::Utc;
+    use serde_json::{json, Value};
+
+    /// `user_id` signed up with `wallet` as their primary wallet.
+    async fn signed_up(state: &AppState, user_id: &str, wallet: &Wallet) {
+        state
+            .store
+            .link_wallet(user_id, &wallet.address, Utc::now())
+            .await
+            .unwrap();
+    }
+
+    /// Asks for a link challenge for `wallet` as `user_id` and returns the message.
+    async fn challenge(state: &AppState, user_id: &str, wallet: &Wallet) -> String {
+        let (status, _, body) = Call::post(
+            "/v1/wallets/link/challenge",
+            json!({ "wallet": wallet.address }),
+        )
+        .user(user_id)
+        .send(state)
+        .await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        body["message"].as_str().unwrap().to_string()
+    }
+
+    async fn link(
+        state: &AppState,
+        user_id: &str,
+        wallet: &Wallet,
+        message: &str,
+        signature: &str,
+    ) -> (StatusCode, Value) {
+        let body = json!({ "wallet": wallet.address, "message": message, "signature": signature });
+        let (status, _, body) = Call::post("/v1/wallets/link", body)
+            .user(user_id)
+            .send(state)
+            .await;
+        (status, body)
+    }
+
+    fn wallets(body: &Value) -> Vec<(String, bool)> {
+        body["wallets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|w| {
+                (
+                    w["wallet"].as_str().unwrap().to_string(),
+                    w["isPrimary"] == true,
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn a_second_wallet_is_linked_by_signing_its_challenge() {
+        let state = testing::state();
+        let (first, second) = (Wallet::new(1), Wallet::new(2));
+        signed_up(&state, "alice", &first).await;
+
+        let message = challenge(&state, "alice", &second).await;
+        assert!(message.contains("Link this wallet to monopay account alice."));
+        let (status, linked) =
+            link(&state, "alice", &second, &message, &second.sign(&message)).await;
+        assert_eq!(status, StatusCode::OK, "{linked}");
+        assert_eq!(linked["isPrimary"], false);
+
+        let (_, _, listed) = Call::new(Method::GET, "/v1/wallets")
+            .user("alice")
+            .send(&state)
+            .await;
+        let mut listed = wallets(&listed);
+        listed.sort();
+        let mut expected = vec![(first.address, true), (second.address, false)];
+        expected.sort();
+        assert_eq!(listed, expected);
+    }
+
+    #[tokio::test]
+    async fn a_wallet_owned_by_another_user_cannot_be_linked() {
+        let state = testing::state();
+        let wallet = Wallet::new(2);
+        signed_up(&state, "alice", &Wallet::new(1)).await;
+        // Challenge issued while the wallet was still free.
+        let message = challenge(&state, "alice", &wallet).await;
+        signed_up(&state, "bob", &wallet).await;
+
+        let (status, _, body) = Call::post(
+            "/v1/wallets/link/challenge",
+            json!({ "wallet": wallet.address }),
+        )
+        .user("alice")
+        .send(&state)
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "WALLET_ALREADY_LINKED");
+
+        let (status, body) = link(&state, "alice", &wallet, &message, &wallet.sign(&message)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "WALLET_ALREADY_LINKED");
+        assert_eq!(
+            state
+                .store
+                .wallet_link(&wallet.address)
+                .await
+                .unwrap()
+                .unwrap()
+                .user_id,
+            "bob"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_link_challenge_works_once() {
+        let state = testing::state();
+        let (first, second) = (Wallet::new(1), Wallet::new(2));
+        signed_up(&state, "alice", &first).await;
+        let message = challenge(&state, "alice", &second).await;
+        let signature = second.sign(&message);
+        let (status, _) = link(&state, "alice", &second, &message, &signature).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _, _) = Call::new(Method::DELETE, &format!("/v1/wallets/{}", second.address))
+            .user("alice")
+            .send(&state)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = link(&state, "alice", &second, &message, &signature).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"]["message"], "nonce not found or already used");
+    }
+
+    #[tokio::test]
+    async fn a_signature_from_another_wallet_is_rejected() {
+        let state = testing::state();
+        let (first, second) = (Wallet::new(1), Wallet::new(2));
+        signed_up(&state, "alice", &first).await;
+        let message = challenge(&state, "alice", &second).await;
+
+        // The wallet already on the account cannot vouch for the new one.
+        let (status, body) = link(&state, "alice", &second, &message, &first.sign(&message)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"]["code"], "INVALID_SIGNATURE");
+        assert!(state
+            .store
+            .wallet_link(&second.address)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn a_challenge_is_only_good_for_the_user_it_was_issued_to() {
+        let state = testing::state();
+        let wallet = Wallet::new(3);
+        signed_up(&state, "alice", &Wallet::new(1)).await;
+        signed_up(&state, "bob", &Wallet::new(2)).await;
+        let message = challenge(&state, "bob", &wallet).await;
+        let (status, body) = link(&state, "alice", &wallet, &message, &wallet.sign(&message)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            body["error"]["message"],
+            "challenge was not issued to link a wallet for this user"
+        );
+
+        // Sign-in challenges do not carry a user to link to either.
+        let (_, _, nonce) = Call::post("/v1/auth/nonce", json!({ "wallet": wallet.address }))
+            .send(&state)
+            .await;
+        let message = nonce["message"].as_str().unwrap();
+        let (status, _) = link(&state, "alice", &wallet, message, &wallet.sign(message)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn the_primary_wallet_is_unlinked_only_after_another_is_made_primary() {
+        let state = testing::state();
+        let (first, second) = (Wallet::new(1), Wallet::new(2));
+        signed_up(&state, "alice", &first).await;
+        let message = challenge(&state, "alice", &second).await;
+        link(&state, "alice", &second, &message, &second.sign(&message)).await;
+
+        let unlink_first =
+            || Call::new(Method::DELETE, &format!("/v1/wallets/{}", first.address)).user("alice");
+        let (status, _, _) = unlink_first().send(&state).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _, _) = Call::new(
+            Method::POST,
+            &format!("/v1/wallets/{}/primary", second.address),
+        )
+        .user("alice")
+        .send(&state)
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _, body) = unlink_first().send(&state).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(wallets(&body), vec![(second.address.clone(), true)]);
+
+        // Someone else's wallet is not found rather than unlinked.
+        signed_up(&state, "bob", &Wallet::new(3)).await;
+        let (status, _, _) = Call::new(
+            Method::DELETE,
+            &format!("/v1/wallets/{}", Wallet::new(3).address),
+        )
+        .user("alice")
+        .send(&state)
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,347 @@
+use crate::error::AppError;
+use crate::models::WebhookResponse;
+use crate::reconciler;
+use crate::solana::helius;
+use crate::state::AppState;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+pub const TIMESTAMP_HEADER: &str = "x-monopay-timestamp";
+pub const SIGNATURE_HEADER: &str = "x-monopay-signature";
+/// Prefix of the signature scheme in [`SIGNATURE_HEADER`].
+const SIGNATURE_VERSION: &str = "v1=";
+
+fn invalid_signature(message: impl Into<String>) -> AppError {
+    AppError::coded(
+        StatusCode::UNAUTHORIZED,
+        "INVALID_WEBHOOK_SIGNATURE",
+        message,
+    )
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str, AppError> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| invalid_signature(format!("missing {name} header")))
+}
+
+/// Checks `v1=<hex HMAC-SHA256(secret, "{timestamp}.{body}")>` and that the timestamp is
+/// within the tolerance of `now`. Returns the signature, which identifies the delivery.
+fn verify_signature(
+    secret: &str,
+    tolerance: Duration,
+    headers: &HeaderMap,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Result<Vec<u8>, AppError> {
+    let timestamp = header(headers, TIMESTAMP_HEADER)?;
+    let sent_at = timestamp
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .ok_or_else(|| invalid_signature("timestamp is not unix seconds"))?;
+    if (now - sent_at).abs() > tolerance {
+        return Err(invalid_signature("timestamp is outside the tolerance"));
+    }
+
+    let signature = header(headers, SIGNATURE_HEADER)?
+        .strip_prefix(SIGNATURE_VERSION)
+        .and_then(|signature| hex::decode(signature).ok())
+        .ok_or_else(|| invalid_signature("signature is not v1=<hex>"))?;
+    // HMAC takes keys of any length.
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|err| AppError::internal(err.to_string()))?;
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.verify_slice(&signature)
+        .map_err(|_| invalid_signature("signature does not match"))?;
+    Ok(signature)
+}
+
+/// Applies every transaction in a delivery to the intents whose reference it carries.
+async fn apply(
+    state: &AppState,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Result<WebhookResponse, AppError> {
+    let body = serde_json::from_slice(body)
+        .map_err(|err| AppError::bad_request(format!("webhook body is not json: {err}")))?;
+    let transactions = helius::parse_webhook(&body).map_err(AppError::bad_request)?;
+
+    let mut response = WebhookResponse {
+        transactions: transactions.len(),
+        matched: 0,
+        updated: 0,
+    };
+    for transaction in &transactions {
+        let mut keys: Vec<&String> = transaction.account_keys.iter().collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            let Some(intent) = state.store.payment_intent_by_reference(key).await? else {
+                continue;
+            };
+            response.matched += 1;
+            if reconciler::record_transfer(state, &intent, transaction, now).await? {
+                response.updated += 1;
+            }
+        }
+    }
+    Ok(response)
+}
+
+/// `POST /v1/webhooks/solana/tx`: transactions pushed by the indexer at `confirmed`
+/// commitment. Each signed delivery is processed once; a failed one may be retried.
+pub async fn solana_tx(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<WebhookResponse>, AppError> {
+    let secret = state
+        .config
+        .indexer_webhook_secret
+        .as_deref()
+        .ok_or_else(|| {
+            AppError::coded(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "WEBHOOK_DISABLED",
+                "INDEXER_WEBHOOK_SECRET is not configured",
+            )
+        })?;
+    let now = Utc::now();
+    let tolerance = Duration::seconds(state.config.webhook_tolerance_secs);
+    let signature = verify_signature(secret, tolerance, &headers, &body, now)?;
+
+    // A captured delivery stays valid for the tolerance on either side of its timestamp.
+    let delivery = hex::encode(signature);
+    let expires_at = now + tolerance * 2;
+    if !state
+        .store
+        .record_webhook_delivery(&delivery, expires_at)
+        .await?
+    {
+        return Err(AppError::coded(
+            StatusCode::CONFLICT,
+            "WEBHOOK_REPLAYED",
+            "this delivery was already processed",
+        ));
+    }
+    match apply(&state, &body, now).await {
+        Ok(response) => Ok(Json(response)),
+        Err(err) => {
+            state.store.release_webhook_delivery(&delivery).await?;
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lifecycle::IntentStatus;
+    use crate::money::TokenAmount;
+    use crate::testing::{self, key, Call};
+    use serde_json::{json, Value};
+
+    const SECRET: &str = "test-webhook-secret";
+
+    fn sent_at() -> DateTime<Utc> {
+        testing::at(0)
+    }
+
+    fn headers(timestamp: &str, signature: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(TIMESTAMP_HEADER, timestamp.parse().unwrap());
+        headers.insert(SIGNATURE_HEADER, signature.parse().unwrap());
+        headers
+    }
+
+    fn sign(timestamp: i64, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).unwrap();
+        mac.update(format!("{timestamp}.").as_bytes());
+        mac.update(body);
+        format!(
+            "{SIGNATURE_VERSION}{}",
+            hex::encode(mac.finalize().into_bytes())
+        )
+    }
+
+    fn verify(headers: &HeaderMap, body: &[u8], now: DateTime<Utc>) -> Result<Vec<u8>, AppError> {
+        verify_signature(SECRET, Duration::seconds(300), headers, body, now)
+    }
+
+    fn rejection(result: Result<Vec<u8>, AppError>) -> String {
+        match result {
+            Err(AppError::Coded {
+                status: StatusCode::UNAUTHORIZED,
+                code: "INVALID_WEBHOOK_SIGNATURE",
+                message,
+            }) => message,
+            other => panic!("expected INVALID_WEBHOOK_SIGNATURE, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn accepts_hmac_sha256_over_timestamp_and_body() {
+        // HMAC-SHA256("test-webhook-secret", "1760000000.[]")
+        let expected = "71d6519de9685f2de518feca87cbbfe6633371f7094a5c91e1756fc1311400e4";
+        let signature = verify(
+            &headers("1760000000", &format!("v1={expected}")),
+            b"[]",
+            sent_at(),
+        )
+        .unwrap();
+        assert_eq!(hex::encode(signature), expected);
+    }
+
+    #[test]
+    fn rejects_other_secrets_bodies_and_timestamps() {
+        let valid = sign(1_760_000_000, b"[]");
+        assert_eq!(
+            rejection(verify(&headers("1760000000", &valid), b"[ ]", sent_at())),
+            "signature does not match"
+        );
+        // The timestamp is covered by the signature.
+        assert_eq!(
+            rejection(verify(&headers("1760000001", &valid), b"[]", sent_at())),
+            "signature does not match"
+        );
+        let other_secret = verify_signature(
+            "other-secret",
+            Duration::seconds(300),
+            &headers("1760000000", &valid),
+            b"[]",
+            sent_at(),
+        );
+        assert_eq!(rejection(other_secret), "signature does not match");
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        let valid = sign(1_760_000_000, b"[]");
+        let unversioned = valid.trim_start_matches(SIGNATURE_VERSION);
+        for (timestamp, signature, message) in [
+            ("1760000000", unversioned, "signature is not v1=<hex>"),
+            ("1760000000", "v2=00", "signature is not v1=<hex>"),
+            ("1760000000", "v1=not-hex", "signature is not v1=<hex>"),
+            (
+                "2025-10-09T08:53:20Z",
+                valid.as_str(),
+                "timestamp is not unix seconds",
+            ),
+        ] {
+            assert_eq!(
+                rejection(verify(&headers(timestamp, signature), b"[]", sent_at())),
+                message
+            );
+        }
+        assert_eq!(
+            rejection(verify(&HeaderMap::new(), b"[]", sent_at())),
+            "missing x-monopay-timestamp header"
+        );
+    }
+
+    #[test]
+    fn timestamps_must_be_within_the_tolerance() {
+        let valid = headers("1760000000", &sign(1_760_000_000, b"[]"));
+        for offset in [-300, 0, 300] {
+            assert!(verify(&valid, b"[]", sent_at() + Duration::seconds(offset)).is_ok());
+        }
+        for offset in [-301, 301, 86_400] {
+            assert_eq!(
+                rejection(verify(&valid, b"[]", sent_at() + Duration::seconds(offset))),
+                "timestamp is outside the tolerance"
+            );
+        }
+    }
+
+    fn delivery(body: &Value, timestamp: i64) -> Call {
+        Call::post("/v1/webhooks/solana/tx", body.clone())
+            .header(TIMESTAMP_HEADER, &timestamp.to_string())
+            .header(
+                SIGNATURE_HEADER,
+                &sign(timestamp, body.to_string().as_bytes()),
+            )
+    }
+
+    #[tokio::test]
+    async fn signed_delivery_confirms_the_intent_once() {
+        let state = testing::state();
+        let mut intent = testing::intent("pi_1", "alice", &key(2).to_string(), 1_000, Utc::now());
+        intent.reference = key(3).to_string();
+        intent.token_amount = TokenAmount::new(10_000_000, 9);
+        state.store.insert_payment_intent(intent).await.unwrap();
+        let body = json!([{
+            "signature": "sig1",
+            "transactionError": null,
+            "accountData": [
+                { "account": key(1).to_string(), "nativeBalanceChange": -10_005_000 },
+                { "account": key(2).to_string(), "nativeBalanceChange": 10_000_000 },
+            ],
+            "instructions": [{
+                "programId": "11111111111111111111111111111111",
+                "accounts": [key(1).to_string(), key(2).to_string(), key(3).to_string()],
+            }],
+        }]);
+
+        let now = Utc::now().timestamp();
+        let (status, _, response) = delivery(&body, now).send(&state).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            response,
+            json!({ "transactions": 1, "matched": 1, "updated": 1 })
+        );
+        let intent = state.store.payment_intent("pi_1").await.unwrap().unwrap();
+        assert_eq!(intent.status, IntentStatus::Confirmed);
+        assert_eq!(intent.signature.as_deref(), Some("sig1"));
+
+        // The indexer sending the transaction again changes nothing.
+        let (status, _, response) = delivery(&body, now + 1).send(&state).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            response,
+            json!({ "transactions": 1, "matched": 1, "updated": 0 })
+        );
+    }
+
+    #[tokio::test]
+    async fn replayed_delivery_is_rejected() {
+        let state = testing::state();
+        let now = Utc::now().timestamp();
+        let (status, _, _) = delivery(&json!([]), now).send(&state).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _, response) = delivery(&json!([]), now).send(&state).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(response["error"]["code"], "WEBHOOK_REPLAYED");
+    }
+
+    #[tokio::test]
+    async fn rejected_delivery_can_be_retried() {
+        let state = testing::state();
+        let now = Utc::now().timestamp();
+        for _ in 0..2 {
+            let (status, _, response) = delivery(&json!({ "not": "an array" }), now)
+                .send(&state)
+                .await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(response["error"]["code"], "BAD_REQUEST");
+        }
+    }
+
+    #[tokio::test]
+    async fn unsigned_delivery_is_rejected() {
+        let state = testing::state();
+        let (status, _, response) = Call::post("/v1/webhooks/solana/tx", json!([]))
+            .send(&state)
+            .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(response["error"]["code"], "INVALID_WEBHOOK_SIGNATURE");
+    }
+}
@@ -0,0 +1,193 @@
+use crate::error::AppError;
+use axum::http::StatusCode;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+pub fn decode_pubkey(wallet: &str) -> Result<VerifyingKey, AppError> {
+    let bytes = bs58::decode(wallet.trim()).into_vec().map_err(|_| {
+        AppError::coded(
+            StatusCode::BAD_REQUEST,
+            "INVALID_ENCODING",
+            "wallet must be base58 encoded",
+        )
+    })?;
+    let bytes: [u8; 32] = bytes.try_into().map_err(|_| {
+        AppError::coded(
+            StatusCode::BAD_REQUEST,
+            "INVALID_WALLET",
+            "wallet must be a 32 byte public key",
+        )
+    })?;
+
+    VerifyingKey::from_bytes(&bytes).map_err(|_| {
+        AppError::coded(
+            StatusCode::BAD_REQUEST,
+            "INVALID_WALLET",
+            "wallet is not a valid ed25519 public key",
+        )
+    })
+}
+
+pub fn decode_signature(signature: &str) -> Result<Signature, AppError> {
+    let bytes = bs58::decode(signature.trim()).into_vec().map_err(|_| {
+        AppError::coded(
+            StatusCode::BAD_REQUEST,
+            "INVALID_ENCODING",
+            "signature must be base58 encoded",
+        )
+    })?;
+
+    Signature::from_slice(&bytes).map_err(|_| {
+        AppError::coded(
+            StatusCode::BAD_REQUEST,
+            "INVALID_ENCODING",
+            "signature must be 64 bytes",
+        )
+    })
+}
+
+pub fn verify_wallet_signature(
+    wallet: &str,
+    message: &[u8],
+    signature: &str,
+) -> Result<(), AppError> {
+    let pubkey = decode_pubkey(wallet)?;
+    let signature = decode_signature(signature)?;
+
+    pubkey.verify(message, &signature).map_err(|_| {
+        AppError::coded(
+            StatusCode::UNAUTHORIZED,
+            "INVALID_SIGNATURE",
+            "wallet signature verification failed",
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn wallet() -> (SigningKey, String) {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let wallet = bs58::encode(key.verifying_key().as_bytes()).into_string();
+        (key, wallet)
+    }
+
+    fn sign(key: &SigningKey, message: &[u8]) -> String {
+        bs58::encode(key.sign(message).to_bytes()).into_string()
+    }
+
+    fn code<T>(result: Result<T, AppError>) -> (StatusCode, &'static str) {
+        match result {
+            Err(AppError::Coded { status, code, .. }) => (status, code),
+            Err(other) => panic!("expected a coded error, got {other:?}"),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn a_signature_by_the_wallet_over_the_message_verifies() {
+        let (key, wallet) = wallet();
+        let signature = sign(&key, b"sign in to monopay");
+        assert!(verify_wallet_signature(&wallet, b"sign in to monopay", &signature).is_ok());
+        // Surrounding whitespace from copy and paste is tolerated.
+        let padded = format!(" {signature}\n");
+        assert!(
+            verify_wallet_signature(&format!(" {wallet} "), b"sign in to monopay", &padded).is_ok()
+        );
+    }
+
+    #[test]
+    fn a_signature_by_another_key_is_rejected() {
+        let (_, wallet) = wallet();
+        let other = SigningKey::from_bytes(&[8; 32]);
+        let signature = sign(&other, b"sign in to monopay");
+        assert_eq!(
+            code(verify_wallet_signature(
+                &wallet,
+                b"sign in to monopay",
+                &signature
+            )),
+            (StatusCode::UNAUTHORIZED, "INVALID_SIGNATURE")
+        );
+    }
+
+    #[test]
+    fn a_tampered_message_is_rejected() {
+        let (key, wallet) = wallet();
+        let signature = sign(&key, b"pay 100 INR to alice");
+        assert_eq!(
+            code(verify_wallet_signature(
+                &wallet,
+                b"pay 900 INR to alice",
+                &signature
+            )),
+            (StatusCode::UNAUTHORIZED, "INVALID_SIGNATURE")
+        );
+    }
+
+    #[test]
+    fn malformed_base58_is_an_encoding_error() {
+        let (key, wallet) = wallet();
+        let signature = sign(&key, b"message");
+        // `0`, `O`, `I` and `l` are not in the base58 alphabet.
+        assert_eq!(
+            code(decode_pubkey("0OIl")),
+            (StatusCode::BAD_REQUEST, "INVALID_ENCODING")
+        );
+        assert_eq!(
+            code(verify_wallet_signature(&wallet, b"message", "0OIl")),
+            (StatusCode::BAD_REQUEST, "INVALID_ENCODING")
+        );
+        assert_eq!(
+            code(verify_wallet_signature(
+                "not-base58!",
+                b"message",
+                &signature
+            )),
+            (StatusCode::BAD_REQUEST, "INVALID_ENCODING")
+        );
+    }
+
+    #[test]
+    fn keys_and_signatures_of_the_wrong_length_are_rejected() {
+        let (key, wallet) = wallet();
+        let short_key = bs58::encode([1u8; 31]).into_string();
+        let long_key = bs58::encode([1u8; 33]).into_string();
+        assert_eq!(
+            code(decode_pubkey(&short_key)),
+            (StatusCode::BAD_REQUEST, "INVALID_WALLET")
+        );
+        assert_eq!(
+            code(decode_pubkey(&long_key)),
+            (StatusCode::BAD_REQUEST, "INVALID_WALLET")
+        );
+
+        let signature = key.sign(b"message").to_bytes();
+        let short_signature = bs58::encode(&signature[..63]).into_string();
+        let long_signature = bs58::encode([&signature[..], &[0]].concat()).into_string();
+        for signature in [short_signature, long_signature] {
+            assert_eq!(
+                code(verify_wallet_signature(&wallet, b"message", &signature)),
+                (StatusCode::BAD_REQUEST, "INVALID_ENCODING")
+            );
+        }
+    }
+
+    #[test]
+    fn thirty_two_bytes_off_the_curve_are_not_a_wallet() {
+        // Program derived addresses are 32 bytes but have no private key.
+        let off_curve = (0u8..=255)
+            .map(|byte| {
+                let mut bytes = [byte; 32];
+                bytes[31] = 0x7f;
+                bytes
+            })
+            .find(|bytes| VerifyingKey::from_bytes(bytes).is_err())
+            .expect("some repeated byte pattern is off the curve");
+        assert_eq!(
+            code(decode_pubkey(&bs58::encode(off_curve).into_string())),
+            (StatusCode::BAD_REQUEST, "INVALID_WALLET")
+        );
+    }
+}
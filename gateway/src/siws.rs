@@ -0,0 +1,257 @@
+use crate::error::AppError;
+use axum::http::StatusCode;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+const HEADER_SUFFIX: &str = " wants you to sign in with your Solana account:";
+pub const VERSION: &str = "1";
+
+/// Sign-In-With-Solana challenge, rendered in the ABNF layout wallets display.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SiwsMessage {
+    pub domain: String,
+    pub address: String,
+    pub statement: Option<String>,
+    pub uri: String,
+    pub version: String,
+    #[serde(rename = "chainId")]
+    pub chain_id: String,
+    pub nonce: String,
+    #[serde(rename = "issuedAt")]
+    pub issued_at: DateTime<Utc>,
+    #[serde(rename = "expirationTime")]
+    pub expiration_time: Option<DateTime<Utc>>,
+    #[serde(rename = "requestId")]
+    pub request_id: Option<String>,
+}
+
+fn invalid(message: impl Into<String>) -> AppError {
+    AppError::coded(StatusCode::BAD_REQUEST, "INVALID_MESSAGE", message)
+}
+
+fn format_time(value: &DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn parse_time(field: &str, value: &str) -> Result<DateTime<Utc>, AppError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|_| invalid(format!("{field} must be an RFC 3339 timestamp")))
+}
+
+impl SiwsMessage {
+    pub fn to_message(&self) -> String {
+        let mut out = format!("{}{HEADER_SUFFIX}\n{}\n\n", self.domain, self.address);
+        if let Some(statement) = &self.statement {
+            out.push_str(statement);
+            out.push_str("\n\n");
+        }
+        out.push_str(&format!("URI: {}\n", self.uri));
+        out.push_str(&format!("Version: {}\n", self.version));
+        out.push_str(&format!("Chain ID: {}\n", self.chain_id));
+        out.push_str(&format!("Nonce: {}\n", self.nonce));
+        out.push_str(&format!("Issued At: {}", format_time(&self.issued_at)));
+        if let Some(expiration_time) = &self.expiration_time {
+            out.push_str(&format!(
+                "\nExpiration Time: {}",
+                format_time(expiration_time)
+            ));
+        }
+        if let Some(request_id) = &self.request_id {
+            out.push_str(&format!("\nRequest ID: {request_id}"));
+        }
+        out
+    }
+
+    pub fn parse(message: &str) -> Result<Self, AppError> {
+        let mut lines = message.split('\n');
+
+        let domain = lines
+            .next()
+            .and_then(|l| l.strip_suffix(HEADER_SUFFIX))
+            .filter(|d| !d.is_empty())
+            .ok_or_else(|| invalid("message header is malformed"))?
+            .to_string();
+        let address = lines
+            .next()
+            .filter(|a| !a.is_empty())
+            .ok_or_else(|| invalid("message address is missing"))?
+            .to_string();
+        if lines.next() != Some("") {
+            return Err(invalid("expected blank line after address"));
+        }
+
+        let mut rest: Vec<&str> = lines.collect();
+        let mut statement = None;
+        if rest.first().is_some_and(|l| !l.starts_with("URI: ")) {
+            statement = Some(rest.remove(0).to_string());
+            if rest.first() != Some(&"") {
+                return Err(invalid("expected blank line after statement"));
+            }
+            rest.remove(0);
+        }
+
+        let mut uri = None;
+        let mut version = None;
+        let mut chain_id = None;
+        let mut nonce = None;
+        let mut issued_at = None;
+        let mut expiration_time = None;
+        let mut request_id = None;
+        for line in rest {
+            let (key, value) = line
+                .split_once(": ")
+                .ok_or_else(|| invalid(format!("malformed message line: {line}")))?;
+            let slot = match key {
+                "URI" => &mut uri,
+                "Version" => &mut version,
+                "Chain ID" => &mut chain_id,
+                "Nonce" => &mut nonce,
+                "Issued At" => &mut issued_at,
+                "Expiration Time" => &mut expiration_time,
+                "Request ID" => &mut request_id,
+                _ => return Err(invalid(format!("unknown message field: {key}"))),
+            };
+            if slot.replace(value.to_string()).is_some() {
+                return Err(invalid(format!("duplicate message field: {key}")));
+            }
+        }
+
+        let required = |value: Option<String>, field: &str| {
+            value.ok_or_else(|| invalid(format!("{field} is required")))
+        };
+
+        Ok(Self {
+            domain,
+            address,
+            statement,
+            uri: required(uri, "URI")?,
+            version: required(version, "Version")?,
+            chain_id: required(chain_id, "Chain ID")?,
+            nonce: required(nonce, "Nonce")?,
+            issued_at: parse_time("Issued At", &required(issued_at, "Issued At")?)?,
+            expiration_time: expiration_time
+                .map(|v| parse_time("Expiration Time", &v))
+                .transpose()?,
+            request_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> SiwsMessage {
+        SiwsMessage {
+            domain: "monopay.app".to_string(),
+            address: "4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T".to_string(),
+            statement: Some("Sign in to monopay.".to_string()),
+            uri: "https://monopay.app/login".to_string(),
+            version: VERSION.to_string(),
+            chain_id: "solana:devnet".to_string(),
+            nonce: "32891756".to_string(),
+            issued_at: DateTime::from_timestamp(1_760_000_000, 0).unwrap(),
+            expiration_time: DateTime::from_timestamp(1_760_000_300, 0),
+            request_id: Some("req_1".to_string()),
+        }
+    }
+
+    fn rejection(text: &str) -> String {
+        match SiwsMessage::parse(text) {
+            Err(AppError::Coded { code, message, .. }) => {
+                assert_eq!(code, "INVALID_MESSAGE");
+                message
+            }
+            other => panic!("expected INVALID_MESSAGE, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn renders_the_abnf_layout() {
+        assert_eq!(
+            message().to_message(),
+            "monopay.app wants you to sign in with your Solana account:\n\
+             4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T\n\
+             \n\
+             Sign in to monopay.\n\
+             \n\
+             URI: https://monopay.app/login\n\
+             Version: 1\n\
+             Chain ID: solana:devnet\n\
+             Nonce: 32891756\n\
+             Issued At: 2025-10-09T08:53:20Z\n\
+             Expiration Time: 2025-10-09T08:58:20Z\n\
+             Request ID: req_1"
+        );
+    }
+
+    #[test]
+    fn parse_round_trips_with_and_without_optional_fields() {
+        let full = message();
+        assert_eq!(SiwsMessage::parse(&full.to_message()).unwrap(), full);
+
+        let minimal = SiwsMessage {
+            statement: None,
+            expiration_time: None,
+            request_id: None,
+            ..message()
+        };
+        assert_eq!(SiwsMessage::parse(&minimal.to_message()).unwrap(), minimal);
+    }
+
+    #[test]
+    fn missing_required_fields_are_rejected() {
+        let text = message().to_message();
+        for (field, line) in [
+            ("URI", "URI: https://monopay.app/login\n"),
+            ("Version", "Version: 1\n"),
+            ("Chain ID", "Chain ID: solana:devnet\n"),
+            ("Nonce", "Nonce: 32891756\n"),
+        ] {
+            assert_eq!(
+                rejection(&text.replace(line, "")),
+                format!("{field} is required")
+            );
+        }
+        let without_issued_at = text.replace("Issued At: 2025-10-09T08:53:20Z\n", "");
+        assert_eq!(rejection(&without_issued_at), "Issued At is required");
+    }
+
+    #[test]
+    fn duplicate_and_unknown_fields_are_rejected() {
+        let text = message().to_message();
+        let duplicated = text.replace("Nonce: 32891756\n", "Nonce: 32891756\nNonce: 1\n");
+        assert_eq!(rejection(&duplicated), "duplicate message field: Nonce");
+
+        let unknown = format!("{text}\nResources: https://evil.example");
+        assert_eq!(rejection(&unknown), "unknown message field: Resources");
+    }
+
+    #[test]
+    fn malformed_layouts_are_rejected() {
+        let text = message().to_message();
+        assert_eq!(
+            rejection(&text.replace(" wants you to sign in", " asks you to sign in")),
+            "message header is malformed"
+        );
+        let mut lines: Vec<&str> = text.split('\n').collect();
+        lines.remove(2);
+        assert_eq!(
+            rejection(&lines.join("\n")),
+            "expected blank line after address"
+        );
+        assert_eq!(
+            rejection(&text.replace("Sign in to monopay.\n\n", "Sign in to monopay.\n")),
+            "expected blank line after statement"
+        );
+        assert_eq!(
+            rejection(&text.replace("Version: 1", "Version 1")),
+            "malformed message line: Version 1"
+        );
+        assert_eq!(
+            rejection(&text.replace("2025-10-09T08:58:20Z", "tomorrow")),
+            "Expiration Time must be an RFC 3339 timestamp"
+        );
+    }
+}
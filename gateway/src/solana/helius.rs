@@ -0,0 +1,127 @@
+//! Transactions pushed by a Helius-style indexer webhook.
+
+use super::rpc::{parse_transaction, ConfirmedTransaction, TokenBalance};
+use serde_json::Value;
+
+fn accounts_of(instruction: &Value) -> impl Iterator<Item = &str> {
+    instruction
+        .get("accounts")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+}
+
+/// Reads one enhanced transaction (`signature`, `transactionError`, `accountData`,
+/// `instructions`).
+///
+/// Enhanced payloads carry balance changes rather than balances, so each change becomes a
+/// pre/post pair around zero; that difference is all payment verification looks at.
+fn parse_enhanced(value: &Value) -> Result<ConfirmedTransaction, String> {
+    let signature = value
+        .get("signature")
+        .and_then(Value::as_str)
+        .ok_or("transaction has no signature")?
+        .to_string();
+    let account_data = value
+        .get("accountData")
+        .and_then(Value::as_array)
+        .ok_or("transaction has no accountData")?;
+
+    let mut transaction = ConfirmedTransaction {
+        signature,
+        account_keys: Vec::new(),
+        err: value
+            .get("transactionError")
+            .filter(|err| !err.is_null())
+            .map(Value::to_string),
+        pre_balances: Vec::new(),
+        post_balances: Vec::new(),
+        pre_token_balances: Vec::new(),
+        post_token_balances: Vec::new(),
+    };
+    for entry in account_data {
+        let account = entry
+            .get("account")
+            .and_then(Value::as_str)
+            .ok_or("accountData entry has no account")?;
+        let change = entry
+            .get("nativeBalanceChange")
+            .and_then(Value::as_i64)
+            .unwrap_or(0);
+        transaction.account_keys.push(account.to_string());
+        transaction.pre_balances.push((-change).max(0) as u64);
+        transaction.post_balances.push(change.max(0) as u64);
+
+        for token in entry
+            .get("tokenBalanceChanges")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let change: i128 = token
+                .pointer("/rawTokenAmount/tokenAmount")
+                .and_then(Value::as_str)
+                .and_then(|amount| amount.parse().ok())
+                .ok_or("token balance change has no rawTokenAmount")?;
+            let balance = TokenBalance {
+                mint: token
+                    .get("mint")
+                    .and_then(Value::as_str)
+                    .ok_or("token balance change has no mint")?
+                    .to_string(),
+                owner: token
+                    .get("userAccount")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                amount: u64::try_from(change.unsigned_abs())
+                    .map_err(|_| "token balance change is too large")?,
+            };
+            if change < 0 {
+                transaction.pre_token_balances.push(balance);
+            } else {
+                transaction.post_token_balances.push(balance);
+            }
+        }
+    }
+
+    // References are read-only accounts of the transfer instruction.
+    let instructions = value
+        .get("instructions")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten();
+    let inner = instructions.clone().flat_map(|instruction| {
+        instruction
+            .get("innerInstructions")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+    });
+    for account in instructions.chain(inner).flat_map(accounts_of) {
+        if !transaction.account_keys.iter().any(|key| key == account) {
+            transaction.account_keys.push(account.to_string());
+            transaction.pre_balances.push(0);
+            transaction.post_balances.push(0);
+        }
+    }
+    Ok(transaction)
+}
+
+/// Reads a webhook body: an array of enhanced transactions, or of raw `getTransaction`
+/// results as sent by raw webhooks.
+pub fn parse_webhook(body: &Value) -> Result<Vec<ConfirmedTransaction>, String> {
+    body.as_array()
+        .ok_or("webhook body is not an array of transactions")?
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let parsed = if value.get("meta").is_some() {
+                parse_transaction(value)
+            } else {
+                parse_enhanced(value)
+            };
+            parsed.map_err(|err| format!("transaction {index}: {err}"))
+        })
+        .collect()
+}
@@ -0,0 +1,123 @@
+use super::decode_32;
+use super::rpc::{
+    parse_signature_status, parse_transaction, rpc_unavailable, AddressSignature,
+    ConfirmedTransaction, SignatureStatus, SolanaRpc,
+};
+use crate::error::AppError;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Blockhash handed out when the ledger does not name one.
+const DEFAULT_BLOCKHASH: &str = "11111111111111111111111111111111";
+
+/// An offline chain for development and end-to-end checks, re-read from a JSON file on every
+/// call so a script can "land" transactions while the gateway runs:
+///
+/// ```json
+/// {
+///   "blockhash": "EkSnNWid2cvwEVnVx9aBqawnmiCNiDgp3gUdkDPTKN1N",
+///   "transactions": [
+///     {
+///       "confirmationStatus": "finalized",
+///       "transaction": { "signatures": ["5fP9..."], "message": { "accountKeys": ["..."] } },
+///       "meta": { "err": null, "preBalances": [], "postBalances": [],
+///                 "preTokenBalances": [], "postTokenBalances": [] }
+///     }
+///   ]
+/// }
+/// ```
+///
+/// Entries are `getTransaction` results plus their `confirmationStatus`, oldest first.
+#[derive(Debug, Clone)]
+pub struct MockRpc {
+    path: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Ledger {
+    blockhash: Option<String>,
+    #[serde(default)]
+    transactions: Vec<Value>,
+}
+
+impl MockRpc {
+    pub fn new(path: String) -> Self {
+        Self { path }
+    }
+
+    async fn ledger(&self) -> Result<Ledger, AppError> {
+        let contents = tokio::fs::read(&self.path)
+            .await
+            .map_err(|err| rpc_unavailable(format!("mock ledger unreadable: {err}")))?;
+        serde_json::from_slice(&contents)
+            .map_err(|err| rpc_unavailable(format!("mock ledger is invalid: {err}")))
+    }
+
+    /// Landed transactions with their status, oldest first.
+    async fn landed(&self) -> Result<Vec<(ConfirmedTransaction, SignatureStatus)>, AppError> {
+        self.ledger()
+            .await?
+            .transactions
+            .iter()
+            .map(|entry| {
+                let transaction = parse_transaction(entry).map_err(rpc_unavailable)?;
+                let mut status = parse_signature_status(entry)
+                    .map_err(rpc_unavailable)?
+                    .ok_or_else(|| rpc_unavailable("mock ledger entry is null"))?;
+                status.err = transaction.err.clone();
+                Ok((transaction, status))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl SolanaRpc for MockRpc {
+    async fn latest_blockhash(&self) -> Result<[u8; 32], AppError> {
+        let blockhash = self.ledger().await?.blockhash;
+        decode_32(blockhash.as_deref().unwrap_or(DEFAULT_BLOCKHASH)).map_err(rpc_unavailable)
+    }
+
+    async fn signatures_for_address(
+        &self,
+        address: &str,
+    ) -> Result<Vec<AddressSignature>, AppError> {
+        Ok(self
+            .landed()
+            .await?
+            .into_iter()
+            .rev()
+            .filter(|(transaction, _)| transaction.account_keys.iter().any(|key| key == address))
+            .map(|(transaction, status)| AddressSignature {
+                signature: transaction.signature,
+                err: status.err,
+            })
+            .collect())
+    }
+
+    async fn signature_statuses(
+        &self,
+        signatures: &[String],
+    ) -> Result<Vec<Option<SignatureStatus>>, AppError> {
+        let landed = self.landed().await?;
+        Ok(signatures
+            .iter()
+            .map(|signature| {
+                landed
+                    .iter()
+                    .find(|(transaction, _)| transaction.signature == *signature)
+                    .map(|(_, status)| status.clone())
+            })
+            .collect())
+    }
+
+    async fn transaction(&self, signature: &str) -> Result<Option<ConfirmedTransaction>, AppError> {
+        Ok(self
+            .landed()
+            .await?
+            .into_iter()
+            .map(|(transaction, _)| transaction)
+            .find(|transaction| transaction.signature == signature))
+    }
+}
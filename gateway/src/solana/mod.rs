@@ -0,0 +1,148 @@
+pub mod helius;
+mod mock;
+pub mod pay;
+mod rpc;
+pub mod transaction;
+
+pub use mock::MockRpc;
+pub use rpc::{
+    Commitment, ConfirmedTransaction, HttpRpc, SignatureStatus, SolanaRpc, TokenBalance,
+    MAX_STATUS_BATCH,
+};
+
+use crate::config::Config;
+use ed25519_dalek::VerifyingKey;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::sync::Arc;
+use tracing::warn;
+
+pub const SYSTEM_PROGRAM: &str = "11111111111111111111111111111111";
+pub const ASSOCIATED_TOKEN_PROGRAM: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+pub const MEMO_PROGRAM: &str = "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr";
+
+/// The RPC client for `MONOPAY_SOLANA_RPC_URL`, or the file-backed mock when
+/// `MONOPAY_SOLANA_MOCK_LEDGER` is set.
+pub fn rpc_from_config(config: &Config) -> anyhow::Result<Arc<dyn SolanaRpc>> {
+    match &config.solana_mock_ledger {
+        Some(path) => {
+            warn!(
+                "MONOPAY_SOLANA_MOCK_LEDGER is set, payments are read from {path}, not the chain"
+            );
+            Ok(Arc::new(MockRpc::new(path.clone())))
+        }
+        None => Ok(Arc::new(HttpRpc::new(config.solana_rpc_url.clone())?)),
+    }
+}
+
+/// Decodes a base58 string of exactly 32 bytes, as used for keys and blockhashes.
+pub fn decode_32(value: &str) -> Result<[u8; 32], String> {
+    let bytes = bs58::decode(value.trim())
+        .into_vec()
+        .map_err(|_| format!("`{value}` is not base58"))?;
+    bytes
+        .try_into()
+        .map_err(|_| format!("`{value}` is not 32 bytes"))
+}
+
+/// A Solana account address. Unlike a wallet key it need not lie on the ed25519 curve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Pubkey([u8; 32]);
+
+impl Pubkey {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        decode_32(value).map(Self)
+    }
+
+    /// Parses one of the well-known program ids above.
+    pub fn from_static(value: &'static str) -> Self {
+        Self::parse(value).expect("static program ids are valid")
+    }
+
+    /// A random key, e.g. a Solana Pay `reference`; nobody holds its private key.
+    pub fn new_unique() -> Self {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        Self(bytes)
+    }
+
+    pub fn to_bytes(self) -> [u8; 32] {
+        self.0
+    }
+
+    fn is_on_curve(&self) -> bool {
+        VerifyingKey::from_bytes(&self.0).is_ok()
+    }
+
+    /// Same derivation as `Pubkey::find_program_address` in the Solana SDK.
+    pub fn find_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> Option<Self> {
+        (0..=u8::MAX).rev().find_map(|bump| {
+            let bump = [bump];
+            let mut seeds = seeds.to_vec();
+            seeds.push(&bump);
+            Self::create_program_address(&seeds, program_id)
+        })
+    }
+
+    /// Same as `Pubkey::create_program_address` in the Solana SDK: `None` when the
+    /// seeds hash onto the curve.
+    fn create_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> Option<Self> {
+        let mut hasher = Sha256::new();
+        for seed in seeds {
+            hasher.update(seed);
+        }
+        hasher.update(program_id.0);
+        hasher.update(b"ProgramDerivedAddress");
+        let candidate = Self(hasher.finalize().into());
+        (!candidate.is_on_curve()).then_some(candidate)
+    }
+
+    /// The owner's associated token account for `mint` under `token_program`.
+    pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Self {
+        Self::find_program_address(
+            &[&owner.0, &token_program.0, &mint.0],
+            &Self::from_static(ASSOCIATED_TOKEN_PROGRAM),
+        )
+        .expect("an associated token address always exists")
+    }
+}
+
+impl fmt::Display for Pubkey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&bs58::encode(self.0).into_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Vectors from the Solana SDK's own `create_program_address` test.
+    #[test]
+    fn program_addresses_match_the_sdk() {
+        let program = Pubkey::parse("BPFLoaderUpgradeab1e11111111111111111111111").unwrap();
+        let seed_key = Pubkey::parse("SeedPubey1111111111111111111111111111111111").unwrap();
+        for (seeds, expected) in [
+            (
+                vec![&b""[..], &[1]],
+                "BwqrghZA2htAcqq8dzP1WDAhTXYTYWj7CHxF5j7TDBAe",
+            ),
+            (
+                vec!["☉".as_bytes(), &[0]],
+                "13yWmRpaTR4r5nAktwLqMpRNr28tnVUZw26rTvPSSB19",
+            ),
+            (
+                vec![&b"Talking"[..], b"Squirrels"],
+                "2fnQrngrQT4SeLcdToJAD96phoEjNL2man2kfRLCASVk",
+            ),
+            (
+                vec![&seed_key.0[..], &[1]],
+                "976ymqVnfE32QFe6NfGDctSvVa36LWnvYxhU6G2232YL",
+            ),
+        ] {
+            let address = Pubkey::create_program_address(&seeds, &program).unwrap();
+            assert_eq!(address.to_string(), expected);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Solana Pay URLs (<https://docs.solanapay.com/spec>).
+//!
+//! A transfer request names the payment itself:
+//!
+//! ```text
+//! solana:<recipient>?amount=<decimal>&spl-token=<mint>&reference=<key>&label=..&message=..&memo=..
+//! ```
+//!
+//! A transaction request wraps an https link the wallet fetches the transaction from:
+//! `solana:<percent-encoded https link>`.
+
+use super::Pubkey;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use url::form_urlencoded;
+use url::Url;
+
+pub const SCHEME: &str = "solana";
+
+/// What JavaScript's `encodeURIComponent` leaves alone, as the reference implementation uses it.
+const URI_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'!')
+    .remove(b'~')
+    .remove(b'*')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')');
+
+/// A `solana:<recipient>?...` transfer request.
+#[derive(Debug, Clone)]
+pub struct TransferRequest {
+    pub recipient: Pubkey,
+    /// Decimal amount in whole tokens, e.g. `0.25`; absent lets the payer choose.
+    pub amount: Option<String>,
+    /// Mint of the SPL token; absent for native SOL.
+    pub spl_token: Option<Pubkey>,
+    pub references: Vec<Pubkey>,
+    pub label: Option<String>,
+    pub message: Option<String>,
+    pub memo: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum SolanaPayUrl {
+    Transfer(TransferRequest),
+    /// The https link of a transaction request.
+    Transaction(String),
+}
+
+/// Solana Pay amounts are plain decimals: digits with at most one point and a leading digit.
+fn is_decimal(value: &str) -> bool {
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, "0"));
+    !whole.is_empty()
+        && !fraction.is_empty()
+        && whole
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+}
+
+impl TransferRequest {
+    pub fn new(recipient: Pubkey) -> Self {
+        Self {
+            recipient,
+            amount: None,
+            spl_token: None,
+            references: Vec::new(),
+            label: None,
+            message: None,
+            memo: None,
+        }
+    }
+
+    /// Values are percent-encoded like `encodeURIComponent`, so spaces become `%20`: wallets
+    /// that decode the query without form rules would show a `+` for them.
+    pub fn to_url(&self) -> String {
+        let mut fields = Vec::new();
+        if let Some(amount) = &self.amount {
+            fields.push(("amount", amount.clone()));
+        }
+        if let Some(mint) = &self.spl_token {
+            fields.push(("spl-token", mint.to_string()));
+        }
+        for reference in &self.references {
+            fields.push(("reference", reference.to_string()));
+        }
+        for (key, value) in [
+            ("label", &self.label),
+            ("message", &self.message),
+            ("memo", &self.memo),
+        ] {
+            if let Some(value) = value {
+                fields.push((key, value.clone()));
+            }
+        }
+        let query = fields
+            .iter()
+            .map(|(key, value)| format!("{key}={}", utf8_percent_encode(value, URI_COMPONENT)))
+            .collect::<Vec<_>>()
+            .join("&");
+        if query.is_empty() {
+            format!("{SCHEME}:{}", self.recipient)
+        } else {
+            format!("{SCHEME}:{}?{query}", self.recipient)
+        }
+    }
+}
+
+/// The transaction request URL for an https `link`.
+pub fn transaction_request_url(link: &str) -> String {
+    format!("{SCHEME}:{}", utf8_percent_encode(link, URI_COMPONENT))
+}
+
+/// Parses and validates a scanned `solana:` URL.
+pub fn parse(input: &str) -> Result<SolanaPayUrl, String> {
+    let rest = input
+        .trim()
+        .split_once(':')
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case(SCHEME))
+        .map(|(_, rest)| rest)
+        .ok_or("not a solana: URL")?;
+
+    // `:` is not in the base58 alphabet, so only a link can start with a scheme.
+    let link = percent_decode_str(rest)
+        .decode_utf8()
+        .map_err(|_| "URL is not valid UTF-8 once decoded")?;
+    let lowercase = link.to_ascii_lowercase();
+    if lowercase.starts_with("https:") || lowercase.starts_with("http:") {
+        let link = Url::parse(&link).map_err(|err| format!("transaction request link: {err}"))?;
+        if link.scheme() != "https" {
+            return Err("transaction request links must use https".to_string());
+        }
+        return Ok(SolanaPayUrl::Transaction(link.into()));
+    }
+
+    let (recipient, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let recipient = Pubkey::parse(recipient).map_err(|err| format!("recipient: {err}"))?;
+    let mut request = TransferRequest::new(recipient);
+    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+        let value = value.into_owned();
+        let repeated = match key.as_ref() {
+            "amount" => {
+                if !is_decimal(&value) {
+                    return Err(format!("amount `{value}` is not a plain decimal"));
+                }
+                request.amount.replace(value).is_some()
+            }
+            "spl-token" => {
+                let mint = Pubkey::parse(&value).map_err(|err| format!("spl-token: {err}"))?;
+                request.spl_token.replace(mint).is_some()
+            }
+            "reference" => {
+                let reference = Pubkey::parse(&value).map_err(|err| format!("reference: {err}"))?;
+                request.references.push(reference);
+                false
+            }
+            "label" => request.label.replace(value).is_some(),
+            "message" => request.message.replace(value).is_some(),
+            "memo" => request.memo.replace(value).is_some(),
+            // Other fields are reserved for future versions of the spec.
+            _ => false,
+        };
+        if repeated {
+            return Err(format!("{key} appears more than once"));
+        }
+    }
+    Ok(SolanaPayUrl::Transfer(request))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::key;
+
+    #[test]
+    fn transfer_urls_percent_encode_spaces() {
+        let mut request = TransferRequest::new(key(1));
+        request.amount = Some("0.25".to_string());
+        request.references.push(key(2));
+        request.label = Some("monopay".to_string());
+        request.message = Some("Pay 0.25 SOL (INR 10.00)".to_string());
+        request.memo = Some("chai & samosa+1 ☕".to_string());
+
+        let url = request.to_url();
+        assert_eq!(
+            url,
+            format!(
+                "solana:{}?amount=0.25&reference={}&label=monopay\
+                 &message=Pay%200.25%20SOL%20(INR%2010.00)\
+                 &memo=chai%20%26%20samosa%2B1%20%E2%98%95",
+                key(1),
+                key(2)
+            )
+        );
+        assert!(!url.contains('+'));
+
+        let Ok(SolanaPayUrl::Transfer(parsed)) = parse(&url) else {
+            panic!("{url} did not parse as a transfer request");
+        };
+        assert_eq!(parsed.message, request.message);
+        assert_eq!(parsed.memo, request.memo);
+        assert_eq!(parsed.references, request.references);
+    }
+
+    #[test]
+    fn bare_recipient_has_no_query() {
+        assert_eq!(
+            TransferRequest::new(key(1)).to_url(),
+            format!("solana:{}", key(1))
+        );
+    }
+
+    #[test]
+    fn form_encoded_spaces_still_parse() {
+        let url = format!("solana:{}?message=Pay+me&memo=a%20b", key(1));
+        let Ok(SolanaPayUrl::Transfer(parsed)) = parse(&url) else {
+            panic!("{url} did not parse as a transfer request");
+        };
+        assert_eq!(parsed.message.as_deref(), Some("Pay me"));
+        assert_eq!(parsed.memo.as_deref(), Some("a b"));
+    }
+
+    #[test]
+    fn repeated_fields_other_than_reference_are_rejected() {
+        let url = format!(
+            "solana:{}?reference={}&reference={}&amount=1&amount=2",
+            key(1),
+            key(2),
+            key(3)
+        );
+        assert!(parse(&url).is_err());
+        let url = format!(
+            "solana:{}?reference={}&reference={}",
+            key(1),
+            key(2),
+            key(3)
+        );
+        let Ok(SolanaPayUrl::Transfer(parsed)) = parse(&url) else {
+            panic!("{url} did not parse as a transfer request");
+        };
+        assert_eq!(parsed.references, [key(2), key(3)]);
+    }
+}
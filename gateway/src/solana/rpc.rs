@@ -0,0 +1,297 @@
+use super::decode_32;
+use crate::error::AppError;
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Most signatures `getSignatureStatuses` accepts per call.
+pub const MAX_STATUS_BATCH: usize = 256;
+
+pub(crate) fn rpc_unavailable(message: impl Into<String>) -> AppError {
+    AppError::coded(StatusCode::SERVICE_UNAVAILABLE, "RPC_UNAVAILABLE", message)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+#[derive(Debug, Clone)]
+pub struct SignatureStatus {
+    pub confirmation: Commitment,
+    /// The transaction error as reported by the node, when it failed.
+    pub err: Option<String>,
+}
+
+/// A signature touching an address, as listed by `getSignaturesForAddress`.
+#[derive(Debug, Clone)]
+pub struct AddressSignature {
+    pub signature: String,
+    pub err: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenBalance {
+    pub mint: String,
+    pub owner: Option<String>,
+    pub amount: u64,
+}
+
+/// The parts of a landed transaction needed to check what it paid.
+#[derive(Debug, Clone)]
+pub struct ConfirmedTransaction {
+    pub signature: String,
+    /// Static keys followed by any loaded from lookup tables, in balance order.
+    pub account_keys: Vec<String>,
+    pub err: Option<String>,
+    pub pre_balances: Vec<u64>,
+    pub post_balances: Vec<u64>,
+    pub pre_token_balances: Vec<TokenBalance>,
+    pub post_token_balances: Vec<TokenBalance>,
+}
+
+/// The subset of the Solana JSON-RPC API the gateway relies on.
+#[async_trait]
+pub trait SolanaRpc: Send + Sync {
+    async fn latest_blockhash(&self) -> Result<[u8; 32], AppError>;
+    /// Confirmed signatures that include `address`, newest first.
+    async fn signatures_for_address(
+        &self,
+        address: &str,
+    ) -> Result<Vec<AddressSignature>, AppError>;
+    /// Statuses in the order of `signatures`; `None` when the node does not know one.
+    async fn signature_statuses(
+        &self,
+        signatures: &[String],
+    ) -> Result<Vec<Option<SignatureStatus>>, AppError>;
+    async fn transaction(&self, signature: &str) -> Result<Option<ConfirmedTransaction>, AppError>;
+}
+
+fn error_text(value: Option<&Value>) -> Option<String> {
+    value
+        .filter(|err| !err.is_null())
+        .map(|err| err.to_string())
+}
+
+pub(crate) fn parse_signature_status(value: &Value) -> Result<Option<SignatureStatus>, String> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    let confirmation = value
+        .get("confirmationStatus")
+        .cloned()
+        .map(serde_json::from_value::<Commitment>)
+        .transpose()
+        .map_err(|err| format!("invalid confirmationStatus: {err}"))?
+        .unwrap_or(Commitment::Processed);
+    Ok(Some(SignatureStatus {
+        confirmation,
+        err: error_text(value.get("err")),
+    }))
+}
+
+fn parse_token_balances(value: Option<&Value>) -> Result<Vec<TokenBalance>, String> {
+    let Some(balances) = value.and_then(Value::as_array) else {
+        return Ok(Vec::new());
+    };
+    balances
+        .iter()
+        .map(|balance| {
+            let amount = balance
+                .pointer("/uiTokenAmount/amount")
+                .and_then(Value::as_str)
+                .and_then(|amount| amount.parse().ok())
+                .ok_or("token balance has no amount")?;
+            Ok(TokenBalance {
+                mint: balance
+                    .get("mint")
+                    .and_then(Value::as_str)
+                    .ok_or("token balance has no mint")?
+                    .to_string(),
+                owner: balance
+                    .get("owner")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                amount,
+            })
+        })
+        .collect()
+}
+
+fn parse_lamports(value: Option<&Value>) -> Result<Vec<u64>, String> {
+    value
+        .and_then(Value::as_array)
+        .ok_or("transaction has no balances")?
+        .iter()
+        .map(|lamports| {
+            lamports
+                .as_u64()
+                .ok_or("balance is not a number".to_string())
+        })
+        .collect()
+}
+
+/// Reads a `getTransaction` result in `json` encoding.
+pub(crate) fn parse_transaction(value: &Value) -> Result<ConfirmedTransaction, String> {
+    let signature = value
+        .pointer("/transaction/signatures/0")
+        .and_then(Value::as_str)
+        .ok_or("transaction has no signature")?
+        .to_string();
+    let meta = value.get("meta").ok_or("transaction has no meta")?;
+    let loaded = ["/loadedAddresses/writable", "/loadedAddresses/readonly"]
+        .into_iter()
+        .filter_map(|pointer| meta.pointer(pointer).and_then(Value::as_array))
+        .flatten();
+    let account_keys = value
+        .pointer("/transaction/message/accountKeys")
+        .and_then(Value::as_array)
+        .ok_or("transaction has no accountKeys")?
+        .iter()
+        .chain(loaded)
+        .map(|key| {
+            key.as_str()
+                .map(str::to_string)
+                .ok_or("account key is not a string".to_string())
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(ConfirmedTransaction {
+        signature,
+        account_keys,
+        err: error_text(meta.get("err")),
+        pre_balances: parse_lamports(meta.get("preBalances"))?,
+        post_balances: parse_lamports(meta.get("postBalances"))?,
+        pre_token_balances: parse_token_balances(meta.get("preTokenBalances"))?,
+        post_token_balances: parse_token_balances(meta.get("postTokenBalances"))?,
+    })
+}
+
+/// JSON-RPC over HTTP against `MONOPAY_SOLANA_RPC_URL`.
+#[derive(Debug, Clone)]
+pub struct HttpRpc {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpRpc {
+    pub fn new(url: String) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        Ok(Self { client, url })
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value, AppError> {
+        let body: Value = self
+            .client
+            .post(&self.url)
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| rpc_unavailable(format!("{method} failed: {err}")))?
+            .json()
+            .await
+            .map_err(|err| rpc_unavailable(format!("{method} sent invalid json: {err}")))?;
+        if let Some(error) = body.get("error") {
+            return Err(rpc_unavailable(format!("{method} failed: {error}")));
+        }
+        body.get("result")
+            .cloned()
+            .ok_or_else(|| rpc_unavailable(format!("{method} returned no result")))
+    }
+}
+
+#[async_trait]
+impl SolanaRpc for HttpRpc {
+    async fn latest_blockhash(&self) -> Result<[u8; 32], AppError> {
+        let result = self
+            .call("getLatestBlockhash", json!([{ "commitment": "confirmed" }]))
+            .await?;
+        result
+            .pointer("/value/blockhash")
+            .and_then(Value::as_str)
+            .ok_or_else(|| rpc_unavailable("getLatestBlockhash returned no blockhash"))
+            .and_then(|blockhash| decode_32(blockhash).map_err(rpc_unavailable))
+    }
+
+    async fn signatures_for_address(
+        &self,
+        address: &str,
+    ) -> Result<Vec<AddressSignature>, AppError> {
+        let result = self
+            .call(
+                "getSignaturesForAddress",
+                json!([address, { "commitment": "confirmed" }]),
+            )
+            .await?;
+        result
+            .as_array()
+            .ok_or_else(|| rpc_unavailable("getSignaturesForAddress returned no list"))?
+            .iter()
+            .map(|entry| {
+                let signature =
+                    entry
+                        .get("signature")
+                        .and_then(Value::as_str)
+                        .ok_or_else(|| {
+                            rpc_unavailable("getSignaturesForAddress entry has no signature")
+                        })?;
+                Ok(AddressSignature {
+                    signature: signature.to_string(),
+                    err: error_text(entry.get("err")),
+                })
+            })
+            .collect()
+    }
+
+    async fn signature_statuses(
+        &self,
+        signatures: &[String],
+    ) -> Result<Vec<Option<SignatureStatus>>, AppError> {
+        let result = self
+            .call(
+                "getSignatureStatuses",
+                json!([signatures, { "searchTransactionHistory": true }]),
+            )
+            .await?;
+        let statuses = result
+            .get("value")
+            .and_then(Value::as_array)
+            .ok_or_else(|| rpc_unavailable("getSignatureStatuses returned no value"))?;
+        if statuses.len() != signatures.len() {
+            return Err(rpc_unavailable(
+                "getSignatureStatuses returned the wrong number of statuses",
+            ));
+        }
+        statuses
+            .iter()
+            .map(|status| parse_signature_status(status).map_err(rpc_unavailable))
+            .collect()
+    }
+
+    async fn transaction(&self, signature: &str) -> Result<Option<ConfirmedTransaction>, AppError> {
+        let result = self
+            .call(
+                "getTransaction",
+                json!([signature, {
+                    "encoding": "json",
+                    "commitment": "confirmed",
+                    "maxSupportedTransactionVersion": 0
+                }]),
+            )
+            .await?;
+        if result.is_null() {
+            return Ok(None);
+        }
+        parse_transaction(&result)
+            .map(Some)
+            .map_err(|err| rpc_unavailable(format!("getTransaction: {err}")))
+    }
+}
@@ -0,0 +1,436 @@
+//! Offline construction of unsigned legacy transactions in the Solana wire format.
+
+use super::{Pubkey, ASSOCIATED_TOKEN_PROGRAM, MEMO_PROGRAM, SYSTEM_PROGRAM};
+
+const SYSTEM_TRANSFER: u32 = 2;
+const TOKEN_TRANSFER_CHECKED: u8 = 12;
+const ATA_CREATE_IDEMPOTENT: u8 = 1;
+
+#[derive(Debug, Clone)]
+pub struct AccountMeta {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl AccountMeta {
+    pub fn writable(pubkey: Pubkey, is_signer: bool) -> Self {
+        Self {
+            pubkey,
+            is_signer,
+            is_writable: true,
+        }
+    }
+
+    pub fn readonly(pubkey: Pubkey, is_signer: bool) -> Self {
+        Self {
+            pubkey,
+            is_signer,
+            is_writable: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<AccountMeta>,
+    pub data: Vec<u8>,
+}
+
+pub fn system_transfer(from: Pubkey, to: Pubkey, lamports: u64) -> Instruction {
+    let mut data = SYSTEM_TRANSFER.to_le_bytes().to_vec();
+    data.extend_from_slice(&lamports.to_le_bytes());
+    Instruction {
+        program_id: Pubkey::from_static(SYSTEM_PROGRAM),
+        accounts: vec![
+            AccountMeta::writable(from, true),
+            AccountMeta::writable(to, false),
+        ],
+        data,
+    }
+}
+
+/// SPL Token / Token-2022 `TransferChecked` between token accounts.
+pub fn transfer_checked(
+    token_program: Pubkey,
+    source: Pubkey,
+    mint: Pubkey,
+    destination: Pubkey,
+    owner: Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Instruction {
+    let mut data = vec![TOKEN_TRANSFER_CHECKED];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(decimals);
+    Instruction {
+        program_id: token_program,
+        accounts: vec![
+            AccountMeta::writable(source, false),
+            AccountMeta::readonly(mint, false),
+            AccountMeta::writable(destination, false),
+            AccountMeta::readonly(owner, true),
+        ],
+        data,
+    }
+}
+
+/// Creates `owner`'s associated token account for `mint` if it does not exist yet.
+pub fn create_associated_token_account_idempotent(
+    payer: Pubkey,
+    owner: Pubkey,
+    mint: Pubkey,
+    token_program: Pubkey,
+) -> Instruction {
+    let account = Pubkey::associated_token_address(&owner, &mint, &token_program);
+    Instruction {
+        program_id: Pubkey::from_static(ASSOCIATED_TOKEN_PROGRAM),
+        accounts: vec![
+            AccountMeta::writable(payer, true),
+            AccountMeta::writable(account, false),
+            AccountMeta::readonly(owner, false),
+            AccountMeta::readonly(mint, false),
+            AccountMeta::readonly(Pubkey::from_static(SYSTEM_PROGRAM), false),
+            AccountMeta::readonly(token_program, false),
+        ],
+        data: vec![ATA_CREATE_IDEMPOTENT],
+    }
+}
+
+/// Memo program instruction logging `text`; it requires no signatures.
+pub fn memo(text: &str) -> Instruction {
+    Instruction {
+        program_id: Pubkey::from_static(MEMO_PROGRAM),
+        accounts: Vec::new(),
+        data: text.as_bytes().to_vec(),
+    }
+}
+
+/// Solana's "compact-u16" length prefix.
+fn push_compact_len(out: &mut Vec<u8>, len: usize) {
+    let mut rest = len;
+    loop {
+        let byte = (rest & 0x7f) as u8;
+        rest >>= 7;
+        if rest == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Orders and de-duplicates every account the instructions touch, fee payer first, then
+/// writable signers, read-only signers, writable and read-only non-signers.
+fn compile_accounts(payer: Pubkey, instructions: &[Instruction]) -> Vec<AccountMeta> {
+    let mut accounts = vec![AccountMeta::writable(payer, true)];
+    let metas = instructions.iter().flat_map(|instruction| {
+        instruction
+            .accounts
+            .iter()
+            .cloned()
+            .chain([AccountMeta::readonly(instruction.program_id, false)])
+    });
+    for meta in metas {
+        match accounts
+            .iter_mut()
+            .find(|account| account.pubkey == meta.pubkey)
+        {
+            Some(account) => {
+                account.is_signer |= meta.is_signer;
+                account.is_writable |= meta.is_writable;
+            }
+            None => accounts.push(meta),
+        }
+    }
+    // Stable sort keeps the payer first and otherwise preserves first-use order.
+    accounts.sort_by_key(|account| (!account.is_signer, !account.is_writable));
+    accounts
+}
+
+/// Serializes an unsigned legacy transaction: zeroed signature slots followed by the message.
+pub fn unsigned_transaction(
+    payer: Pubkey,
+    instructions: &[Instruction],
+    recent_blockhash: [u8; 32],
+) -> Vec<u8> {
+    let accounts = compile_accounts(payer, instructions);
+    let signers = accounts.iter().filter(|account| account.is_signer).count();
+    let readonly_signers = accounts
+        .iter()
+        .filter(|account| account.is_signer && !account.is_writable)
+        .count();
+    let readonly_unsigned = accounts
+        .iter()
+        .filter(|account| !account.is_signer && !account.is_writable)
+        .count();
+    let index_of = |pubkey: &Pubkey| {
+        accounts
+            .iter()
+            .position(|account| account.pubkey == *pubkey)
+            .expect("every instruction account was compiled") as u8
+    };
+
+    let mut message = vec![
+        signers as u8,
+        readonly_signers as u8,
+        readonly_unsigned as u8,
+    ];
+    push_compact_len(&mut message, accounts.len());
+    for account in &accounts {
+        message.extend_from_slice(&account.pubkey.to_bytes());
+    }
+    message.extend_from_slice(&recent_blockhash);
+    push_compact_len(&mut message, instructions.len());
+    for instruction in instructions {
+        message.push(index_of(&instruction.program_id));
+        push_compact_len(&mut message, instruction.accounts.len());
+        for meta in &instruction.accounts {
+            message.push(index_of(&meta.pubkey));
+        }
+        push_compact_len(&mut message, instruction.data.len());
+        message.extend_from_slice(&instruction.data);
+    }
+
+    let mut transaction = Vec::with_capacity(1 + signers * 64 + message.len());
+    push_compact_len(&mut transaction, signers);
+    transaction.resize(transaction.len() + signers * 64, 0);
+    transaction.extend_from_slice(&message);
+    transaction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::key;
+
+    const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+    /// A legacy transaction taken apart again: header, account keys, blockhash and
+    /// (program index, account indexes, data) per instruction.
+    struct Decoded {
+        signatures: Vec<[u8; 64]>,
+        header: [u8; 3],
+        keys: Vec<Pubkey>,
+        blockhash: [u8; 32],
+        instructions: Vec<(u8, Vec<u8>, Vec<u8>)>,
+    }
+
+    fn decode(bytes: &[u8]) -> Decoded {
+        let mut rest = bytes;
+        let mut take = |len: usize| {
+            let (head, tail) = rest.split_at(len);
+            rest = tail;
+            head.to_vec()
+        };
+        // Every length in these tests fits one compact-u16 byte.
+        let signatures = (0..take(1)[0])
+            .map(|_| take(64).try_into().unwrap())
+            .collect();
+        let header = take(3).try_into().unwrap();
+        let keys = (0..take(1)[0])
+            .map(|_| Pubkey::parse(&bs58::encode(take(32)).into_string()).unwrap())
+            .collect();
+        let blockhash = take(32).try_into().unwrap();
+        let instructions = (0..take(1)[0])
+            .map(|_| {
+                let program = take(1)[0];
+                let accounts = take(1)[0] as usize;
+                let accounts = take(accounts);
+                let data = take(1)[0] as usize;
+                (program, accounts, take(data))
+            })
+            .collect();
+        assert!(rest.is_empty(), "trailing bytes");
+        Decoded {
+            signatures,
+            header,
+            keys,
+            blockhash,
+            instructions,
+        }
+    }
+
+    #[test]
+    fn compact_lengths_use_seven_bits_per_byte() {
+        for (len, expected) in [
+            (0, vec![0x00]),
+            (0x7f, vec![0x7f]),
+            (0x80, vec![0x80, 0x01]),
+            (0x3fff, vec![0xff, 0x7f]),
+            (0x4000, vec![0x80, 0x80, 0x01]),
+        ] {
+            let mut out = Vec::new();
+            push_compact_len(&mut out, len);
+            assert_eq!(out, expected, "{len}");
+        }
+    }
+
+    #[test]
+    fn sol_transfer_with_memo_and_reference() {
+        let (payer, recipient, reference) = (key(1), key(2), key(3));
+        let mut transfer = system_transfer(payer, recipient, 1_500_000_000);
+        transfer
+            .accounts
+            .push(AccountMeta::readonly(reference, false));
+        let bytes = unsigned_transaction(payer, &[memo("chai"), transfer], [9; 32]);
+
+        let memo_program = Pubkey::from_static(MEMO_PROGRAM);
+        let system_program = Pubkey::from_static(SYSTEM_PROGRAM);
+        let mut expected = vec![1];
+        expected.extend([0; 64]);
+        // One writable signer; the memo program, reference and system program are read-only.
+        expected.extend([1, 0, 3, 5]);
+        for account in [payer, recipient, memo_program, reference, system_program] {
+            expected.extend(account.to_bytes());
+        }
+        expected.extend([9; 32]);
+        expected.push(2);
+        expected.extend([2, 0, 4]);
+        expected.extend(b"chai");
+        expected.extend([4, 3, 0, 1, 3, 12, 2, 0, 0, 0]);
+        expected.extend(1_500_000_000u64.to_le_bytes());
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn sol_transfer_golden_base64() {
+        use base64::engine::general_purpose::STANDARD;
+        use base64::Engine;
+
+        let bytes = unsigned_transaction(key(1), &[system_transfer(key(1), key(2), 1)], [0; 32]);
+        assert_eq!(
+            STANDARD.encode(bytes),
+            "AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+             AAAAAAAAAAAAAAABAAEDAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQECAgICAgIC\
+             AgICAgICAgICAgICAgICAgICAgICAgICAgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+             AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABAgIAAQwCAAAAAQAAAAAAAAA="
+        );
+    }
+
+    #[test]
+    fn spl_transfer_creates_the_recipient_account_first() {
+        let (payer, recipient, mint, reference) = (key(1), key(2), key(4), key(3));
+        let program = Pubkey::from_static(TOKEN_PROGRAM);
+        let payer_account = Pubkey::associated_token_address(&payer, &mint, &program);
+        let recipient_account = Pubkey::associated_token_address(&recipient, &mint, &program);
+        let mut transfer = transfer_checked(
+            program,
+            payer_account,
+            mint,
+            recipient_account,
+            payer,
+            2_500_000,
+            6,
+        );
+        transfer
+            .accounts
+            .push(AccountMeta::readonly(reference, false));
+        let instructions = [
+            create_associated_token_account_idempotent(payer, recipient, mint, program),
+            memo("rent"),
+            transfer,
+        ];
+        let decoded = decode(&unsigned_transaction(payer, &instructions, [7; 32]));
+
+        assert_eq!(decoded.signatures, vec![[0; 64]]);
+        assert_eq!(decoded.header, [1, 0, 7]);
+        assert_eq!(decoded.blockhash, [7; 32]);
+        assert_eq!(
+            decoded.keys,
+            vec![
+                payer,
+                recipient_account,
+                payer_account,
+                recipient,
+                mint,
+                Pubkey::from_static(SYSTEM_PROGRAM),
+                program,
+                Pubkey::from_static(ASSOCIATED_TOKEN_PROGRAM),
+                Pubkey::from_static(MEMO_PROGRAM),
+                reference,
+            ]
+        );
+
+        let mut amount = vec![TOKEN_TRANSFER_CHECKED];
+        amount.extend(2_500_000u64.to_le_bytes());
+        amount.push(6);
+        assert_eq!(
+            decoded.instructions,
+            vec![
+                (7, vec![0, 1, 3, 4, 5, 6], vec![ATA_CREATE_IDEMPOTENT]),
+                (8, vec![], b"rent".to_vec()),
+                (6, vec![2, 4, 1, 0, 9], amount),
+            ]
+        );
+    }
+
+    #[test]
+    fn associated_token_addresses_are_off_curve_and_distinct() {
+        let program = Pubkey::from_static(TOKEN_PROGRAM);
+        let first = Pubkey::associated_token_address(&key(1), &key(4), &program);
+        assert!(!first.is_on_curve());
+        assert_eq!(
+            first,
+            Pubkey::associated_token_address(&key(1), &key(4), &program)
+        );
+        assert_ne!(
+            first,
+            Pubkey::associated_token_address(&key(2), &key(4), &program)
+        );
+        assert_ne!(
+            first,
+            Pubkey::associated_token_address(&key(1), &key(5), &program)
+        );
+    }
+
+    /// `test_sdk_serialize` from the Solana SDK: the same message signed by its keypair
+    /// must reproduce the SDK's bytes exactly.
+    #[test]
+    fn signed_message_matches_the_sdk_serialization() {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let signer = SigningKey::from_bytes(&[
+            255, 101, 36, 24, 124, 23, 167, 21, 132, 204, 155, 5, 185, 58, 121, 75, 156, 227, 116,
+            193, 215, 38, 142, 22, 8, 14, 229, 239, 119, 93, 5, 218,
+        ]);
+        let payer = Pubkey(signer.verifying_key().to_bytes());
+        let to = Pubkey([
+            1, 1, 1, 4, 5, 6, 7, 8, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 8, 7, 6, 5, 4,
+            1, 1, 1,
+        ]);
+        let program = Pubkey([
+            2, 2, 2, 4, 5, 6, 7, 8, 9, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 9, 8, 7, 6, 5, 4,
+            2, 2, 2,
+        ]);
+        let instruction = Instruction {
+            program_id: program,
+            accounts: vec![
+                AccountMeta::writable(payer, true),
+                AccountMeta::writable(to, false),
+            ],
+            data: vec![1, 2, 3],
+        };
+
+        let mut transaction = unsigned_transaction(payer, &[instruction], [0; 32]);
+        let signature = signer.sign(&transaction[65..]);
+        transaction[1..65].copy_from_slice(&signature.to_bytes());
+
+        let mut expected = vec![
+            1, 120, 138, 162, 185, 59, 209, 241, 157, 71, 157, 74, 131, 4, 87, 54, 28, 38, 180,
+            222, 82, 64, 62, 61, 62, 22, 46, 17, 203, 187, 136, 62, 43, 11, 38, 235, 17, 239, 82,
+            240, 139, 130, 217, 227, 214, 9, 242, 141, 223, 94, 29, 184, 110, 62, 32, 87, 137, 63,
+            139, 100, 221, 20, 137, 4, 5, 1, 0, 1, 3,
+        ];
+        expected.extend([
+            36, 100, 158, 252, 33, 161, 97, 185, 62, 89, 99, 195, 250, 249, 187, 189, 171, 118,
+            241, 90, 248, 14, 68, 219, 231, 62, 157, 5, 142, 27, 210, 117,
+        ]);
+        expected.extend(to.to_bytes());
+        expected.extend(program.to_bytes());
+        expected.extend([0; 32]);
+        expected.extend([1, 2, 2, 0, 1, 3, 1, 2, 3]);
+        assert_eq!(transaction, expected);
+    }
+}
@@ -1,27 +1,54 @@
+use crate::config::Config;
+use crate::events::IntentEvents;
+use crate::lifecycle::IntentStatus;
+use crate::money::{Inr, Rate, TokenAmount};
+use crate::oracle::PriceOracle;
+use crate::registry::TokenRegistry;
+use crate::solana::SolanaRpc;
+use crate::store::Store;
 use chrono::{DateTime, Utc};
-use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub store: Arc<RwLock<InMemoryStore>>,
-}
-
-#[derive(Debug, Default)]
-pub struct InMemoryStore {
-    pub wallet_nonces: HashMap<String, NonceRecord>,
-    pub wallet_users: HashMap<String, String>,
-    pub handles: HashMap<String, String>,
-    pub pin_profiles: HashMap<String, PinProfileRecord>,
-    pub sessions: HashMap<String, SessionRecord>,
-    pub payment_intents: HashMap<String, PaymentIntentRecord>,
+    pub config: Config,
+    pub store: Arc<dyn Store>,
+    pub oracle: Arc<dyn PriceOracle>,
+    pub registry: Arc<TokenRegistry>,
+    pub rpc: Arc<dyn SolanaRpc>,
+    pub events: IntentEvents,
 }
 
 #[derive(Debug, Clone)]
 pub struct NonceRecord {
     pub nonce: String,
+    pub request_id: String,
+    /// Set when the challenge proves ownership of a wallet being linked to this user.
+    pub link_user_id: Option<String>,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WalletLinkRecord {
+    pub user_id: String,
+    pub pubkey: String,
+    pub chain: String,
+    pub is_primary: bool,
+    pub linked_at: DateTime<Utc>,
+}
+
+/// A chain of rotated refresh tokens issued to one device from a single sign-in.
+#[derive(Debug, Clone)]
+pub struct TokenFamilyRecord {
+    pub id: String,
+    pub user_id: String,
+    pub wallet: String,
+    pub device_id: String,
+    pub current_hash: String,
+    pub retired_hashes: Vec<String>,
     pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone)]
@@ -36,24 +63,81 @@ pub struct SessionRecord {
     pub id: String,
     pub wallet: String,
     pub device_id: String,
-    pub per_tx_limit_inr: f64,
-    pub daily_limit_inr: f64,
-    pub used_today_inr: f64,
+    pub per_tx_limit_inr: Inr,
+    pub daily_limit_inr: Inr,
+    pub used_today_inr: Inr,
     pub expires_at: DateTime<Utc>,
     pub status: String,
 }
 
+/// A price locked for a specific INR amount until `expires_at`; usable by one payment intent.
+#[derive(Debug, Clone)]
+pub struct QuoteRecord {
+    pub id: String,
+    pub token: String,
+    pub inr_amount: Inr,
+    pub token_amount: TokenAmount,
+    pub rate: Rate,
+    pub source: String,
+    pub as_of: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// How far the live price may drift from `rate` before the quote is refused.
+    pub max_slippage_bps: u32,
+    pub intent_id: Option<String>,
+}
+
+/// A request made under an `Idempotency-Key`; `response_status` stays `None` while it is in flight,
+/// during which `expires_at` is a short lease rather than the replay window.
+#[derive(Debug, Clone)]
+pub struct IdempotencyRecord {
+    pub user_id: String,
+    pub key: String,
+    /// SHA-256 over the method, path and body of the original request.
+    pub request_hash: String,
+    pub response_status: Option<u16>,
+    pub response_body: String,
+    pub expires_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone)]
 pub struct PaymentIntentRecord {
     pub id: String,
     pub creator_user_id: String,
+    /// The creator's wallet the intent is paid from.
+    pub payer_wallet: String,
     pub recipient_wallet: String,
-    pub inr_amount: f64,
-    pub token_amount: f64,
+    /// Handle the payer addressed, resolved to `recipient_wallet` at creation.
+    pub recipient_handle: Option<String>,
+    pub inr_amount: Inr,
+    pub token_amount: TokenAmount,
     pub token: String,
+    pub quote_id: String,
     pub quote_expires_at: DateTime<Utc>,
-    pub status: String,
+    /// Cleaned by `memo::clean`; public once the transfer lands.
+    pub memo: Option<String>,
+    /// Only changed through `lifecycle::transition`.
+    pub status: IntentStatus,
     pub mode: Option<String>,
+    /// Session whose daily spend the intent reserved when it was authorized.
+    pub session_id: Option<String>,
     pub signature: Option<String>,
     pub reference: String,
+    /// When a Solana Pay transaction request last handed out a transaction for the intent;
+    /// the payer may sign and send it at any time until the quote expires.
+    pub transaction_served_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// Status history, oldest first.
+    pub events: Vec<PaymentEventRecord>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PaymentEventRecord {
+    pub id: String,
+    /// `None` for the event that created the intent.
+    pub previous_status: Option<IntentStatus>,
+    pub status: IntentStatus,
+    pub reason: Option<String>,
+    /// The intent's signature at the time of the event, if any.
+    pub signature: Option<String>,
+    pub at: DateTime<Utc>,
 }
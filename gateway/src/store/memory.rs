@@ -0,0 +1,540 @@
+use super::{IntentQuery, Mutation, SpendMutation, Store, MAX_NONCES_PER_WALLET};
+use crate::error::AppError;
+use crate::lifecycle::{self, IntentStatus};
+use crate::state::{
+    IdempotencyRecord, NonceRecord, PaymentIntentRecord, PinProfileRecord, QuoteRecord,
+    SessionRecord, TokenFamilyRecord, WalletLinkRecord,
+};
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use tokio::sync::RwLock;
+
+#[derive(Debug, Default)]
+struct Tables {
+    wallet_nonces: HashMap<String, VecDeque<NonceRecord>>,
+    /// Keyed by wallet pubkey; a wallet belongs to at most one user.
+    wallet_links: HashMap<String, WalletLinkRecord>,
+    handles: HashMap<String, String>,
+    pin_profiles: HashMap<String, PinProfileRecord>,
+    sessions: HashMap<String, SessionRecord>,
+    quotes: HashMap<String, QuoteRecord>,
+    /// Keyed by (user id, idempotency key).
+    idempotency_keys: HashMap<(String, String), IdempotencyRecord>,
+    payment_intents: HashMap<String, PaymentIntentRecord>,
+    token_families: HashMap<String, TokenFamilyRecord>,
+    /// Access token ids revoked before expiry, kept until their `exp` passes.
+    revoked_access_tokens: HashMap<String, DateTime<Utc>>,
+    /// Webhook delivery ids, kept until their replay window ends.
+    webhook_deliveries: HashMap<String, DateTime<Utc>>,
+}
+
+/// Process-local store behind a single lock; state is lost on restart.
+#[derive(Debug, Default)]
+pub struct InMemoryStore {
+    tables: RwLock<Tables>,
+}
+
+/// Runs `mutation` on a copy and only writes it back when it succeeds.
+fn apply<T: Clone>(slot: &mut T, mutation: Mutation<'_, T>) -> Result<T, AppError> {
+    let mut draft = slot.clone();
+    mutation(&mut draft)?;
+    *slot = draft.clone();
+    Ok(draft)
+}
+
+#[async_trait]
+impl Store for InMemoryStore {
+    async fn push_nonce(&self, wallet: &str, record: NonceRecord) -> Result<(), AppError> {
+        let mut tables = self.tables.write().await;
+        let pool = tables.wallet_nonces.entry(wallet.to_string()).or_default();
+        pool.push_back(record);
+        while pool.len() > MAX_NONCES_PER_WALLET {
+            pool.pop_front();
+        }
+        Ok(())
+    }
+
+    async fn find_nonce(&self, wallet: &str, nonce: &str) -> Result<Option<NonceRecord>, AppError> {
+        let tables = self.tables.read().await;
+        Ok(tables
+            .wallet_nonces
+            .get(wallet)
+            .and_then(|pool| pool.iter().find(|record| record.nonce == nonce))
+            .cloned())
+    }
+
+    async fn consume_nonce(
+        &self,
+        wallet: &str,
+        nonce: &str,
+    ) -> Result<Option<NonceRecord>, AppError> {
+        let mut tables = self.tables.write().await;
+        let Some(pool) = tables.wallet_nonces.get_mut(wallet) else {
+            return Ok(None);
+        };
+        let record = pool
+            .iter()
+            .position(|record| record.nonce == nonce)
+            .and_then(|index| pool.remove(index));
+        if pool.is_empty() {
+            tables.wallet_nonces.remove(wallet);
+        }
+        Ok(record)
+    }
+
+    async fn evict_expired_nonces(&self, now: DateTime<Utc>) -> Result<usize, AppError> {
+        let mut tables = self.tables.write().await;
+        let mut evicted = 0;
+        tables.wallet_nonces.retain(|_, pool| {
+            let before = pool.len();
+            pool.retain(|record| record.expires_at > now);
+            evicted += before - pool.len();
+            !pool.is_empty()
+        });
+        Ok(evicted)
+    }
+
+    async fn wallet_link(&self, wallet: &str) -> Result<Option<WalletLinkRecord>, AppError> {
+        Ok(self.tables.read().await.wallet_links.get(wallet).cloned())
+    }
+
+    async fn user_wallets(&self, user_id: &str) -> Result<Vec<WalletLinkRecord>, AppError> {
+        let tables = self.tables.read().await;
+        let mut wallets: Vec<_> = tables
+            .wallet_links
+            .values()
+            .filter(|link| link.user_id == user_id)
+            .cloned()
+            .collect();
+        wallets.sort_by(|a, b| {
+            b.is_primary
+                .cmp(&a.is_primary)
+                .then(a.linked_at.cmp(&b.linked_at))
+        });
+        Ok(wallets)
+    }
+
+    async fn link_wallet(
+        &self,
+        user_id: &str,
+        wallet: &str,
+        now: DateTime<Utc>,
+    ) -> Result<WalletLinkRecord, AppError> {
+        let mut tables = self.tables.write().await;
+        if tables.wallet_links.contains_key(wallet) {
+            return Err(AppError::coded(
+                StatusCode::CONFLICT,
+                "WALLET_ALREADY_LINKED",
+                "wallet is already linked to an account",
+            ));
+        }
+        let is_primary = !tables
+            .wallet_links
+            .values()
+            .any(|link| link.user_id == user_id && link.is_primary);
+        let record = WalletLinkRecord {
+            user_id: user_id.to_string(),
+            pubkey: wallet.to_string(),
+            chain: "solana".to_string(),
+            is_primary,
+            linked_at: now,
+        };
+        tables
+            .wallet_links
+            .insert(wallet.to_string(), record.clone());
+        Ok(record)
+    }
+
+    async fn unlink_wallet(&self, user_id: &str, wallet: &str) -> Result<(), AppError> {
+        let mut tables = self.tables.write().await;
+        if tables
+            .wallet_links
+            .get(wallet)
+            .is_some_and(|link| link.user_id == user_id)
+        {
+            tables.wallet_links.remove(wallet);
+        }
+        Ok(())
+    }
+
+    async fn set_primary_wallet(&self, user_id: &str, wallet: &str) -> Result<(), AppError> {
+        let mut tables = self.tables.write().await;
+        for link in tables.wallet_links.values_mut() {
+            if link.user_id == user_id {
+                link.is_primary = link.pubkey == wallet;
+            }
+        }
+        Ok(())
+    }
+
+    async fn insert_token_family(&self, record: TokenFamilyRecord) -> Result<(), AppError> {
+        let mut tables = self.tables.write().await;
+        tables.token_families.insert(record.id.clone(), record);
+        Ok(())
+    }
+
+    async fn update_token_family<'a>(
+        &self,
+        family_id: &str,
+        mutation: Mutation<'a, TokenFamilyRecord>,
+    ) -> Result<TokenFamilyRecord, AppError> {
+        let mut tables = self.tables.write().await;
+        let family = tables
+            .token_families
+            .get_mut(family_id)
+            .ok_or_else(|| AppError::not_found("token family not found"))?;
+        apply(family, mutation)
+    }
+
+    async fn revoke_token_families(
+        &self,
+        user_id: &str,
+        family_id: Option<&str>,
+        wallet: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        let mut tables = self.tables.write().await;
+        for family in tables.token_families.values_mut() {
+            if family.user_id != user_id
+                || family_id.is_some_and(|id| id != family.id)
+                || wallet.is_some_and(|w| w != family.wallet)
+            {
+                continue;
+            }
+            family.revoked_at.get_or_insert(now);
+        }
+        Ok(())
+    }
+
+    async fn revoke_access_token(
+        &self,
+        token_id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        let mut tables = self.tables.write().await;
+        tables
+            .revoked_access_tokens
+            .insert(token_id.to_string(), expires_at);
+        Ok(())
+    }
+
+    async fn is_access_token_revoked(
+        &self,
+        token_id: &str,
+        family_id: &str,
+    ) -> Result<bool, AppError> {
+        let tables = self.tables.read().await;
+        Ok(tables.revoked_access_tokens.contains_key(token_id)
+            || tables
+                .token_families
+                .get(family_id)
+                .is_none_or(|family| family.revoked_at.is_some()))
+    }
+
+    async fn evict_expired_tokens(&self, now: DateTime<Utc>) -> Result<usize, AppError> {
+        let mut tables = self.tables.write().await;
+        let before = tables.token_families.len() + tables.revoked_access_tokens.len();
+        tables
+            .token_families
+            .retain(|_, family| family.expires_at > now);
+        tables
+            .revoked_access_tokens
+            .retain(|_, expires_at| *expires_at > now);
+        Ok(before - tables.token_families.len() - tables.revoked_access_tokens.len())
+    }
+
+    async fn handle_wallet(&self, handle: &str) -> Result<Option<String>, AppError> {
+        Ok(self.tables.read().await.handles.get(handle).cloned())
+    }
+
+    async fn wallet_handle(&self, wallet: &str) -> Result<Option<String>, AppError> {
+        Ok(self
+            .tables
+            .read()
+            .await
+            .handles
+            .iter()
+            .filter(|(_, owner)| *owner == wallet)
+            .map(|(handle, _)| handle)
+            .min()
+            .cloned())
+    }
+
+    async fn claim_handle(&self, handle: &str, wallet: &str) -> Result<(), AppError> {
+        let mut tables = self.tables.write().await;
+        if let Some(existing_wallet) = tables.handles.get(handle) {
+            if existing_wallet != wallet {
+                return Err(AppError::bad_request("handle is already taken"));
+            }
+        }
+        tables
+            .handles
+            .insert(handle.to_string(), wallet.to_string());
+        Ok(())
+    }
+
+    async fn pin_profile(&self, user_id: &str) -> Result<Option<PinProfileRecord>, AppError> {
+        Ok(self.tables.read().await.pin_profiles.get(user_id).cloned())
+    }
+
+    async fn put_pin_profile(
+        &self,
+        user_id: &str,
+        record: PinProfileRecord,
+    ) -> Result<(), AppError> {
+        let mut tables = self.tables.write().await;
+        tables.pin_profiles.insert(user_id.to_string(), record);
+        Ok(())
+    }
+
+    async fn update_pin_profile<'a>(
+        &self,
+        user_id: &str,
+        mutation: Mutation<'a, PinProfileRecord>,
+    ) -> Result<PinProfileRecord, AppError> {
+        let mut tables = self.tables.write().await;
+        let profile = tables
+            .pin_profiles
+            .get_mut(user_id)
+            .ok_or_else(|| AppError::not_found("pin profile not found"))?;
+        apply(profile, mutation)
+    }
+
+    async fn session(&self, user_id: &str) -> Result<Option<SessionRecord>, AppError> {
+        Ok(self.tables.read().await.sessions.get(user_id).cloned())
+    }
+
+    async fn put_session(&self, user_id: &str, record: SessionRecord) -> Result<(), AppError> {
+        let mut tables = self.tables.write().await;
+        tables.sessions.insert(user_id.to_string(), record);
+        Ok(())
+    }
+
+    async fn delete_session(&self, user_id: &str) -> Result<(), AppError> {
+        self.tables.write().await.sessions.remove(user_id);
+        Ok(())
+    }
+
+    async fn insert_quote(&self, record: QuoteRecord) -> Result<(), AppError> {
+        let mut tables = self.tables.write().await;
+        tables.quotes.insert(record.id.clone(), record);
+        Ok(())
+    }
+
+    async fn quote(&self, id: &str) -> Result<Option<QuoteRecord>, AppError> {
+        Ok(self.tables.read().await.quotes.get(id).cloned())
+    }
+
+    async fn update_quote<'a>(
+        &self,
+        id: &str,
+        mutation: Mutation<'a, QuoteRecord>,
+    ) -> Result<QuoteRecord, AppError> {
+        let mut tables = self.tables.write().await;
+        let quote = tables
+            .quotes
+            .get_mut(id)
+            .ok_or_else(|| AppError::not_found("quote not found"))?;
+        apply(quote, mutation)
+    }
+
+    async fn evict_expired_quotes(&self, now: DateTime<Utc>) -> Result<usize, AppError> {
+        let mut tables = self.tables.write().await;
+        let before = tables.quotes.len();
+        tables.quotes.retain(|_, quote| quote.expires_at > now);
+        Ok(before - tables.quotes.len())
+    }
+
+    async fn reserve_idempotency_key(
+        &self,
+        record: IdempotencyRecord,
+    ) -> Result<Option<IdempotencyRecord>, AppError> {
+        let mut tables = self.tables.write().await;
+        let slot = (record.user_id.clone(), record.key.clone());
+        match tables.idempotency_keys.get(&slot) {
+            Some(existing) if existing.expires_at > Utc::now() => Ok(Some(existing.clone())),
+            _ => {
+                tables.idempotency_keys.insert(slot, record);
+                Ok(None)
+            }
+        }
+    }
+
+    async fn complete_idempotency_key(
+        &self,
+        user_id: &str,
+        key: &str,
+        status: u16,
+        body: String,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        let mut tables = self.tables.write().await;
+        if let Some(record) = tables
+            .idempotency_keys
+            .get_mut(&(user_id.to_string(), key.to_string()))
+        {
+            record.response_status = Some(status);
+            record.response_body = body;
+            record.expires_at = expires_at;
+        }
+        Ok(())
+    }
+
+    async fn release_idempotency_key(&self, user_id: &str, key: &str) -> Result<(), AppError> {
+        let mut tables = self.tables.write().await;
+        tables
+            .idempotency_keys
+            .remove(&(user_id.to_string(), key.to_string()));
+        Ok(())
+    }
+
+    async fn evict_expired_idempotency_keys(&self, now: DateTime<Utc>) -> Result<usize, AppError> {
+        let mut tables = self.tables.write().await;
+        let before = tables.idempotency_keys.len();
+        tables
+            .idempotency_keys
+            .retain(|_, record| record.expires_at > now);
+        Ok(before - tables.idempotency_keys.len())
+    }
+
+    async fn record_webhook_delivery(
+        &self,
+        id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool, AppError> {
+        let mut tables = self.tables.write().await;
+        let now = Utc::now();
+        match tables.webhook_deliveries.get(id) {
+            Some(seen_until) if *seen_until > now => Ok(false),
+            _ => {
+                tables.webhook_deliveries.insert(id.to_string(), expires_at);
+                Ok(true)
+            }
+        }
+    }
+
+    async fn release_webhook_delivery(&self, id: &str) -> Result<(), AppError> {
+        self.tables.write().await.webhook_deliveries.remove(id);
+        Ok(())
+    }
+
+    async fn evict_expired_webhook_deliveries(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<usize, AppError> {
+        let mut tables = self.tables.write().await;
+        let before = tables.webhook_deliveries.len();
+        tables
+            .webhook_deliveries
+            .retain(|_, expires_at| *expires_at > now);
+        Ok(before - tables.webhook_deliveries.len())
+    }
+
+    async fn payment_intent(&self, id: &str) -> Result<Option<PaymentIntentRecord>, AppError> {
+        Ok(self.tables.read().await.payment_intents.get(id).cloned())
+    }
+
+    async fn insert_payment_intent(&self, record: PaymentIntentRecord) -> Result<(), AppError> {
+        let mut tables = self.tables.write().await;
+        tables.payment_intents.insert(record.id.clone(), record);
+        Ok(())
+    }
+
+    async fn payment_intent_by_reference(
+        &self,
+        reference: &str,
+    ) -> Result<Option<PaymentIntentRecord>, AppError> {
+        Ok(self
+            .tables
+            .read()
+            .await
+            .payment_intents
+            .values()
+            .find(|intent| intent.reference == reference)
+            .cloned())
+    }
+
+    async fn update_payment_intent<'a>(
+        &self,
+        id: &str,
+        mutation: Mutation<'a, PaymentIntentRecord>,
+    ) -> Result<PaymentIntentRecord, AppError> {
+        let mut tables = self.tables.write().await;
+        let intent = tables
+            .payment_intents
+            .get_mut(id)
+            .ok_or_else(|| AppError::not_found("payment intent not found"))?;
+        apply(intent, mutation)
+    }
+
+    async fn payment_intents_with_status(
+        &self,
+        statuses: &[IntentStatus],
+    ) -> Result<Vec<PaymentIntentRecord>, AppError> {
+        Ok(self
+            .tables
+            .read()
+            .await
+            .payment_intents
+            .values()
+            .filter(|intent| statuses.contains(&intent.status))
+            .cloned()
+            .collect())
+    }
+
+    async fn list_payment_intents(
+        &self,
+        query: &IntentQuery,
+    ) -> Result<Vec<PaymentIntentRecord>, AppError> {
+        let tables = self.tables.read().await;
+        let mut intents: Vec<&PaymentIntentRecord> = tables
+            .payment_intents
+            .values()
+            .filter(|intent| query.matches(intent) && query.is_after_cursor(intent))
+            .collect();
+        intents.sort_by(|a, b| query.compare(a, b));
+        Ok(intents.into_iter().take(query.limit).cloned().collect())
+    }
+
+    async fn purge_payment_intents(&self, closed_before: DateTime<Utc>) -> Result<usize, AppError> {
+        let mut tables = self.tables.write().await;
+        let before = tables.payment_intents.len();
+        tables.payment_intents.retain(|_, intent| {
+            let closed_at = intent
+                .events
+                .last()
+                .map_or(intent.quote_expires_at, |event| event.at);
+            !(lifecycle::closed_unpaid(intent) && closed_at < closed_before)
+        });
+        Ok(before - tables.payment_intents.len())
+    }
+
+    async fn spend_from_session<'a>(
+        &self,
+        user_id: &str,
+        intent_id: &str,
+        mutation: SpendMutation<'a>,
+    ) -> Result<(SessionRecord, PaymentIntentRecord), AppError> {
+        let mut tables = self.tables.write().await;
+        let mut session = tables
+            .sessions
+            .get(user_id)
+            .cloned()
+            .ok_or_else(|| AppError::unauthorized("active session required"))?;
+        let mut intent = tables
+            .payment_intents
+            .get(intent_id)
+            .cloned()
+            .ok_or_else(|| AppError::not_found("payment intent not found"))?;
+
+        mutation(&mut session, &mut intent)?;
+
+        tables.sessions.insert(user_id.to_string(), session.clone());
+        tables
+            .payment_intents
+            .insert(intent_id.to_string(), intent.clone());
+        Ok((session, intent))
+    }
+}
@@ -0,0 +1,319 @@
+mod memory;
+mod sql;
+
+pub use memory::InMemoryStore;
+pub use sql::SqlStore;
+
+use crate::error::AppError;
+use crate::lifecycle::IntentStatus;
+use crate::money::Inr;
+use crate::state::{
+    IdempotencyRecord, NonceRecord, PaymentIntentRecord, PinProfileRecord, QuoteRecord,
+    SessionRecord, TokenFamilyRecord, WalletLinkRecord,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::cmp::Ordering;
+
+/// Outstanding sign-in challenges kept per wallet; the oldest is dropped beyond this.
+pub const MAX_NONCES_PER_WALLET: usize = 5;
+
+/// In-place edit applied atomically by the store; returning `Err` discards the edit.
+pub type Mutation<'a, T> = Box<dyn FnOnce(&mut T) -> Result<(), AppError> + Send + 'a>;
+
+/// Edit spanning a user's session and one of their payment intents.
+pub type SpendMutation<'a> = Box<
+    dyn FnOnce(&mut SessionRecord, &mut PaymentIntentRecord) -> Result<(), AppError> + Send + 'a,
+>;
+
+/// Order of payment history; ties are broken by id in the same direction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IntentSort {
+    #[default]
+    Newest,
+    Oldest,
+    /// By INR amount.
+    Largest,
+    Smallest,
+}
+
+impl IntentSort {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Newest => "newest",
+            Self::Oldest => "oldest",
+            Self::Largest => "largest",
+            Self::Smallest => "smallest",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "newest" => Some(Self::Newest),
+            "oldest" => Some(Self::Oldest),
+            "largest" => Some(Self::Largest),
+            "smallest" => Some(Self::Smallest),
+            _ => None,
+        }
+    }
+
+    pub fn descending(self) -> bool {
+        matches!(self, Self::Newest | Self::Largest)
+    }
+
+    /// The value intents are ordered by: creation time in micros or the amount in paise.
+    pub fn key(self, intent: &PaymentIntentRecord) -> i64 {
+        match self {
+            Self::Newest | Self::Oldest => intent.created_at.timestamp_micros(),
+            Self::Largest | Self::Smallest => {
+                i64::try_from(intent.inr_amount.paise()).unwrap_or(i64::MAX)
+            }
+        }
+    }
+}
+
+/// Where a page of history ended: the sort key and id of its last intent. Keys and ids
+/// never change, so later pages are unaffected by intents created in the meantime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntentCursor {
+    pub key: i64,
+    pub id: String,
+}
+
+/// A page of one user's payment history. An intent is listed when it was created by
+/// `user_id` (if `sent`) or pays one of `received_wallets`, and passes every filter set.
+/// Handles need no matching of their own, as every handle names one wallet for good; see
+/// `visible_intent` in the payment intent routes.
+#[derive(Debug, Clone)]
+pub struct IntentQuery {
+    pub user_id: String,
+    pub sent: bool,
+    pub received_wallets: Vec<String>,
+    /// Any of these; empty for all.
+    pub statuses: Vec<IntentStatus>,
+    pub token: Option<String>,
+    /// The other side's wallet: the recipient of sent intents, the payer of received ones.
+    pub counterparty_wallet: Option<String>,
+    /// Inclusive.
+    pub created_from: Option<DateTime<Utc>>,
+    /// Exclusive.
+    pub created_before: Option<DateTime<Utc>>,
+    pub min_inr: Option<Inr>,
+    pub max_inr: Option<Inr>,
+    pub sort: IntentSort,
+    pub after: Option<IntentCursor>,
+    pub limit: usize,
+}
+
+impl IntentQuery {
+    pub fn is_sent(&self, intent: &PaymentIntentRecord) -> bool {
+        self.sent
+            && intent.creator_user_id == self.user_id
+            && self
+                .counterparty_wallet
+                .as_ref()
+                .is_none_or(|wallet| intent.recipient_wallet == *wallet)
+    }
+
+    pub fn is_received(&self, intent: &PaymentIntentRecord) -> bool {
+        self.received_wallets.contains(&intent.recipient_wallet)
+            && self
+                .counterparty_wallet
+                .as_ref()
+                .is_none_or(|wallet| intent.payer_wallet == *wallet)
+    }
+
+    /// Whether the intent belongs on some page, ignoring `after` and `limit`.
+    pub fn matches(&self, intent: &PaymentIntentRecord) -> bool {
+        (self.is_sent(intent) || self.is_received(intent))
+            && (self.statuses.is_empty() || self.statuses.contains(&intent.status))
+            && self
+                .token
+                .as_ref()
+                .is_none_or(|token| intent.token == *token)
+            && self
+                .created_from
+                .is_none_or(|from| intent.created_at >= from)
+            && self
+                .created_before
+                .is_none_or(|before| intent.created_at < before)
+            && self.min_inr.is_none_or(|min| intent.inr_amount >= min)
+            && self.max_inr.is_none_or(|max| intent.inr_amount <= max)
+    }
+
+    /// Orders intents as pages list them.
+    pub fn compare(&self, a: &PaymentIntentRecord, b: &PaymentIntentRecord) -> Ordering {
+        let order = (self.sort.key(a), &a.id).cmp(&(self.sort.key(b), &b.id));
+        if self.sort.descending() {
+            order.reverse()
+        } else {
+            order
+        }
+    }
+
+    /// Whether the intent comes after the cursor in page order.
+    pub fn is_after_cursor(&self, intent: &PaymentIntentRecord) -> bool {
+        let Some(cursor) = &self.after else {
+            return true;
+        };
+        let order = (self.sort.key(intent), intent.id.as_str()).cmp(&(cursor.key, &cursor.id));
+        if self.sort.descending() {
+            order.is_lt()
+        } else {
+            order.is_gt()
+        }
+    }
+}
+
+/// Persistence boundary for the gateway. Route handlers only talk to this trait.
+#[async_trait]
+pub trait Store: Send + Sync {
+    async fn push_nonce(&self, wallet: &str, record: NonceRecord) -> Result<(), AppError>;
+    async fn find_nonce(&self, wallet: &str, nonce: &str) -> Result<Option<NonceRecord>, AppError>;
+    /// Removes the nonce so the signature over it can never be replayed.
+    async fn consume_nonce(
+        &self,
+        wallet: &str,
+        nonce: &str,
+    ) -> Result<Option<NonceRecord>, AppError>;
+    /// Drops expired nonces and returns how many were evicted.
+    async fn evict_expired_nonces(&self, now: DateTime<Utc>) -> Result<usize, AppError>;
+
+    async fn wallet_link(&self, wallet: &str) -> Result<Option<WalletLinkRecord>, AppError>;
+    /// Linked wallets of a user, primary first.
+    async fn user_wallets(&self, user_id: &str) -> Result<Vec<WalletLinkRecord>, AppError>;
+    /// Links `wallet` to `user_id`, creating the user if needed. The first wallet becomes primary.
+    async fn link_wallet(
+        &self,
+        user_id: &str,
+        wallet: &str,
+        now: DateTime<Utc>,
+    ) -> Result<WalletLinkRecord, AppError>;
+    async fn unlink_wallet(&self, user_id: &str, wallet: &str) -> Result<(), AppError>;
+    async fn set_primary_wallet(&self, user_id: &str, wallet: &str) -> Result<(), AppError>;
+
+    async fn insert_token_family(&self, record: TokenFamilyRecord) -> Result<(), AppError>;
+    async fn update_token_family<'a>(
+        &self,
+        family_id: &str,
+        mutation: Mutation<'a, TokenFamilyRecord>,
+    ) -> Result<TokenFamilyRecord, AppError>;
+    /// Revokes the family, every family of the user, or only the user's families for `wallet`.
+    async fn revoke_token_families(
+        &self,
+        user_id: &str,
+        family_id: Option<&str>,
+        wallet: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<(), AppError>;
+    async fn revoke_access_token(
+        &self,
+        token_id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), AppError>;
+    async fn is_access_token_revoked(
+        &self,
+        token_id: &str,
+        family_id: &str,
+    ) -> Result<bool, AppError>;
+    /// Drops expired token families and revocation entries; returns how many were evicted.
+    async fn evict_expired_tokens(&self, now: DateTime<Utc>) -> Result<usize, AppError>;
+
+    async fn handle_wallet(&self, handle: &str) -> Result<Option<String>, AppError>;
+    /// A handle pointing at `wallet`, the first in alphabetical order if it has several.
+    async fn wallet_handle(&self, wallet: &str) -> Result<Option<String>, AppError>;
+    /// Points `handle` at `wallet` unless another wallet already owns it.
+    async fn claim_handle(&self, handle: &str, wallet: &str) -> Result<(), AppError>;
+
+    async fn pin_profile(&self, user_id: &str) -> Result<Option<PinProfileRecord>, AppError>;
+    async fn put_pin_profile(
+        &self,
+        user_id: &str,
+        record: PinProfileRecord,
+    ) -> Result<(), AppError>;
+    async fn update_pin_profile<'a>(
+        &self,
+        user_id: &str,
+        mutation: Mutation<'a, PinProfileRecord>,
+    ) -> Result<PinProfileRecord, AppError>;
+
+    async fn session(&self, user_id: &str) -> Result<Option<SessionRecord>, AppError>;
+    async fn put_session(&self, user_id: &str, record: SessionRecord) -> Result<(), AppError>;
+    async fn delete_session(&self, user_id: &str) -> Result<(), AppError>;
+
+    async fn insert_quote(&self, record: QuoteRecord) -> Result<(), AppError>;
+    async fn quote(&self, id: &str) -> Result<Option<QuoteRecord>, AppError>;
+    async fn update_quote<'a>(
+        &self,
+        id: &str,
+        mutation: Mutation<'a, QuoteRecord>,
+    ) -> Result<QuoteRecord, AppError>;
+    /// Drops quotes whose lock window has ended; returns how many were evicted.
+    async fn evict_expired_quotes(&self, now: DateTime<Utc>) -> Result<usize, AppError>;
+
+    /// Stores `record` unless the user already holds an unexpired entry for its key, which is returned instead.
+    async fn reserve_idempotency_key(
+        &self,
+        record: IdempotencyRecord,
+    ) -> Result<Option<IdempotencyRecord>, AppError>;
+    /// Records the response so retries with the same key replay it until `expires_at`.
+    async fn complete_idempotency_key(
+        &self,
+        user_id: &str,
+        key: &str,
+        status: u16,
+        body: String,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), AppError>;
+    /// Forgets a reservation so the request can be retried, e.g. after a server error.
+    async fn release_idempotency_key(&self, user_id: &str, key: &str) -> Result<(), AppError>;
+    /// Drops entries past their replay window; returns how many were evicted.
+    async fn evict_expired_idempotency_keys(&self, now: DateTime<Utc>) -> Result<usize, AppError>;
+
+    /// Remembers a webhook delivery until `expires_at`; `false` when it was already seen.
+    async fn record_webhook_delivery(
+        &self,
+        id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool, AppError>;
+    /// Forgets a delivery so the sender's retry is processed.
+    async fn release_webhook_delivery(&self, id: &str) -> Result<(), AppError>;
+    /// Drops deliveries past their replay window; returns how many were evicted.
+    async fn evict_expired_webhook_deliveries(&self, now: DateTime<Utc>)
+        -> Result<usize, AppError>;
+
+    async fn payment_intent(&self, id: &str) -> Result<Option<PaymentIntentRecord>, AppError>;
+    async fn insert_payment_intent(&self, record: PaymentIntentRecord) -> Result<(), AppError>;
+    /// The intent whose reference key is `reference`.
+    async fn payment_intent_by_reference(
+        &self,
+        reference: &str,
+    ) -> Result<Option<PaymentIntentRecord>, AppError>;
+    async fn update_payment_intent<'a>(
+        &self,
+        id: &str,
+        mutation: Mutation<'a, PaymentIntentRecord>,
+    ) -> Result<PaymentIntentRecord, AppError>;
+    /// Intents currently in any of `statuses`, with their history.
+    async fn payment_intents_with_status(
+        &self,
+        statuses: &[IntentStatus],
+    ) -> Result<Vec<PaymentIntentRecord>, AppError>;
+    /// Up to `query.limit` intents of a user's history, in page order.
+    async fn list_payment_intents(
+        &self,
+        query: &IntentQuery,
+    ) -> Result<Vec<PaymentIntentRecord>, AppError>;
+    /// Deletes intents closed without payment (see [`crate::lifecycle::closed_unpaid`]), with their
+    /// history, whose last event is before `closed_before`; returns how many were purged.
+    async fn purge_payment_intents(&self, closed_before: DateTime<Utc>) -> Result<usize, AppError>;
+    /// Applies `mutation` to the user's session and the intent as one unit.
+    async fn spend_from_session<'a>(
+        &self,
+        user_id: &str,
+        intent_id: &str,
+        mutation: SpendMutation<'a>,
+    ) -> Result<(SessionRecord, PaymentIntentRecord), AppError>;
+}
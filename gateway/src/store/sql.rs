@@ -0,0 +1,1663 @@
+use super::{IntentQuery, IntentSort, Mutation, SpendMutation, Store, MAX_NONCES_PER_WALLET};
+use crate::error::AppError;
+use crate::lifecycle::IntentStatus;
+use crate::money::{Inr, Rate, TokenAmount};
+use crate::state::{
+    IdempotencyRecord, NonceRecord, PaymentEventRecord, PaymentIntentRecord, PinProfileRecord,
+    QuoteRecord, SessionRecord, TokenFamilyRecord, WalletLinkRecord,
+};
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use sqlx::any::{AnyPoolOptions, AnyRow};
+use sqlx::{AnyConnection, AnyPool, Row};
+use tracing::error;
+
+const NONCE_COLUMNS: &str = "nonce, request_id, link_user_id, issued_at, expires_at";
+const WALLET_LINK_COLUMNS: &str = "user_id, pubkey, chain, is_primary, linked_at";
+const TOKEN_FAMILY_COLUMNS: &str =
+    "id, user_id, wallet, device_id, current_hash, retired_hashes, expires_at, revoked_at";
+const PIN_PROFILE_COLUMNS: &str = "pin_hash, failed_attempts, locked_until";
+const SESSION_COLUMNS: &str = "id, wallet_pubkey, device_id, per_tx_limit_paise, \
+     daily_limit_paise, used_today_paise, expires_at, status";
+const QUOTE_COLUMNS: &str = "id, token, inr_paise, token_base_units, token_decimals, rate, \
+     source, as_of, expires_at, max_slippage_bps, intent_id";
+const INTENT_COLUMNS: &str = "id, creator_user_id, recipient_wallet, amount_paise, \
+     token_base_units, token_decimals, token, quote_id, quote_expires_at, status, mode, \
+     signature, ref, session_id, payer_wallet, created_at, recipient_handle, memo, \
+     transaction_served_at";
+const IDEMPOTENCY_COLUMNS: &str =
+    "user_id, idempotency_key, request_hash, response_status, response_body, expires_at";
+const EVENT_COLUMNS: &str = "id, previous_status, status, reason, signature, created_at";
+
+/// SQLite or PostgreSQL store, chosen by the scheme of the connection URL.
+#[derive(Debug, Clone)]
+pub struct SqlStore {
+    pool: AnyPool,
+    /// Row locks for read-modify-write; SQLite serializes writers through a single connection instead.
+    for_update: &'static str,
+}
+
+impl SqlStore {
+    /// Connects and applies any pending migrations from `gateway/migrations`.
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        sqlx::any::install_default_drivers();
+        let is_sqlite = url.starts_with("sqlite:");
+        let pool = AnyPoolOptions::new()
+            .max_connections(if is_sqlite { 1 } else { 10 })
+            .connect(url)
+            .await?;
+        sqlx::migrate!("./migrations").run(&pool).await?;
+        Ok(Self {
+            pool,
+            for_update: if is_sqlite { "" } else { " FOR UPDATE" },
+        })
+    }
+
+    async fn conn(&self) -> Result<sqlx::pool::PoolConnection<sqlx::Any>, AppError> {
+        self.pool.acquire().await.map_err(db_err)
+    }
+}
+
+fn db_err(err: sqlx::Error) -> AppError {
+    error!(error = %err, "database error");
+    AppError::internal("database error")
+}
+
+fn is_unique_violation(err: &sqlx::Error) -> bool {
+    err.as_database_error()
+        .is_some_and(|db| db.is_unique_violation())
+}
+
+fn micros(at: DateTime<Utc>) -> i64 {
+    at.timestamp_micros()
+}
+
+fn timestamp(micros: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp_micros(micros).unwrap_or_default()
+}
+
+/// Amounts are unsigned in Rust but stored in signed BIGINT columns.
+fn unsigned(row: &AnyRow, column: &str) -> Result<u64, sqlx::Error> {
+    let value: i64 = row.try_get(column)?;
+    u64::try_from(value).map_err(|err| sqlx::Error::ColumnDecode {
+        index: column.to_string(),
+        source: Box::new(err),
+    })
+}
+
+fn signed(value: u64) -> Result<i64, sqlx::Error> {
+    i64::try_from(value).map_err(|err| sqlx::Error::Encode(Box::new(err)))
+}
+
+fn intent_status(row: &AnyRow, column: &str) -> Result<Option<IntentStatus>, sqlx::Error> {
+    let value: Option<String> = row.try_get(column)?;
+    value
+        .map(|value| {
+            IntentStatus::parse(&value).ok_or_else(|| sqlx::Error::ColumnDecode {
+                index: column.to_string(),
+                source: format!("unknown payment intent status `{value}`").into(),
+            })
+        })
+        .transpose()
+}
+
+fn nonce_from_row(row: &AnyRow) -> Result<NonceRecord, sqlx::Error> {
+    Ok(NonceRecord {
+        nonce: row.try_get("nonce")?,
+        request_id: row.try_get("request_id")?,
+        link_user_id: row.try_get("link_user_id")?,
+        issued_at: timestamp(row.try_get("issued_at")?),
+        expires_at: timestamp(row.try_get("expires_at")?),
+    })
+}
+
+fn wallet_link_from_row(row: &AnyRow) -> Result<WalletLinkRecord, sqlx::Error> {
+    Ok(WalletLinkRecord {
+        user_id: row.try_get("user_id")?,
+        pubkey: row.try_get("pubkey")?,
+        chain: row.try_get("chain")?,
+        is_primary: row.try_get::<i64, _>("is_primary")? != 0,
+        linked_at: timestamp(row.try_get("linked_at")?),
+    })
+}
+
+fn token_family_from_row(row: &AnyRow) -> Result<TokenFamilyRecord, sqlx::Error> {
+    let retired: String = row.try_get("retired_hashes")?;
+    Ok(TokenFamilyRecord {
+        id: row.try_get("id")?,
+        user_id: row.try_get("user_id")?,
+        wallet: row.try_get("wallet")?,
+        device_id: row.try_get("device_id")?,
+        current_hash: row.try_get("current_hash")?,
+        retired_hashes: serde_json::from_str(&retired)
+            .map_err(|err| sqlx::Error::Decode(Box::new(err)))?,
+        expires_at: timestamp(row.try_get("expires_at")?),
+        revoked_at: row.try_get::<Option<i64>, _>("revoked_at")?.map(timestamp),
+    })
+}
+
+fn pin_profile_from_row(row: &AnyRow) -> Result<PinProfileRecord, sqlx::Error> {
+    let failed_attempts: i64 = row.try_get("failed_attempts")?;
+    Ok(PinProfileRecord {
+        pin_hash: row.try_get("pin_hash")?,
+        failed_attempts: u8::try_from(failed_attempts).unwrap_or(u8::MAX),
+        locked_until: row
+            .try_get::<Option<i64>, _>("locked_until")?
+            .map(timestamp),
+    })
+}
+
+fn session_from_row(row: &AnyRow) -> Result<SessionRecord, sqlx::Error> {
+    Ok(SessionRecord {
+        id: row.try_get("id")?,
+        wallet: row.try_get("wallet_pubkey")?,
+        device_id: row.try_get("device_id")?,
+        per_tx_limit_inr: Inr::from_paise(unsigned(row, "per_tx_limit_paise")?),
+        daily_limit_inr: Inr::from_paise(unsigned(row, "daily_limit_paise")?),
+        used_today_inr: Inr::from_paise(unsigned(row, "used_today_paise")?),
+        expires_at: timestamp(row.try_get("expires_at")?),
+        status: row.try_get("status")?,
+    })
+}
+
+fn token_amount(row: &AnyRow) -> Result<TokenAmount, sqlx::Error> {
+    Ok(TokenAmount::new(
+        unsigned(row, "token_base_units")?,
+        u8::try_from(unsigned(row, "token_decimals")?).unwrap_or(0),
+    ))
+}
+
+fn quote_from_row(row: &AnyRow) -> Result<QuoteRecord, sqlx::Error> {
+    let rate: String = row.try_get("rate")?;
+    Ok(QuoteRecord {
+        id: row.try_get("id")?,
+        token: row.try_get("token")?,
+        inr_amount: Inr::from_paise(unsigned(row, "inr_paise")?),
+        token_amount: token_amount(row)?,
+        rate: Rate::parse(&rate).map_err(|err| sqlx::Error::ColumnDecode {
+            index: "rate".to_string(),
+            source: err.into(),
+        })?,
+        source: row.try_get("source")?,
+        as_of: timestamp(row.try_get("as_of")?),
+        expires_at: timestamp(row.try_get("expires_at")?),
+        max_slippage_bps: u32::try_from(unsigned(row, "max_slippage_bps")?).unwrap_or(u32::MAX),
+        intent_id: row.try_get("intent_id")?,
+    })
+}
+
+fn intent_from_row(row: &AnyRow) -> Result<PaymentIntentRecord, sqlx::Error> {
+    Ok(PaymentIntentRecord {
+        id: row.try_get("id")?,
+        creator_user_id: row.try_get("creator_user_id")?,
+        payer_wallet: row.try_get("payer_wallet")?,
+        recipient_wallet: row.try_get("recipient_wallet")?,
+        recipient_handle: row.try_get("recipient_handle")?,
+        inr_amount: Inr::from_paise(unsigned(row, "amount_paise")?),
+        token_amount: token_amount(row)?,
+        token: row.try_get("token")?,
+        quote_id: row.try_get("quote_id")?,
+        quote_expires_at: timestamp(row.try_get("quote_expires_at")?),
+        memo: row.try_get("memo")?,
+        status: intent_status(row, "status")?.unwrap_or(IntentStatus::Created),
+        mode: row.try_get("mode")?,
+        session_id: row.try_get("session_id")?,
+        signature: row.try_get("signature")?,
+        reference: row.try_get("ref")?,
+        transaction_served_at: row
+            .try_get::<Option<i64>, _>("transaction_served_at")?
+            .map(timestamp),
+        created_at: timestamp(row.try_get("created_at")?),
+        events: Vec::new(),
+    })
+}
+
+fn idempotency_from_row(row: &AnyRow) -> Result<IdempotencyRecord, sqlx::Error> {
+    Ok(IdempotencyRecord {
+        user_id: row.try_get("user_id")?,
+        key: row.try_get("idempotency_key")?,
+        request_hash: row.try_get("request_hash")?,
+        response_status: row
+            .try_get::<Option<i64>, _>("response_status")?
+            .and_then(|status| u16::try_from(status).ok()),
+        response_body: row.try_get("response_body")?,
+        expires_at: timestamp(row.try_get("expires_at")?),
+    })
+}
+
+fn event_from_row(row: &AnyRow) -> Result<PaymentEventRecord, sqlx::Error> {
+    Ok(PaymentEventRecord {
+        id: row.try_get("id")?,
+        previous_status: intent_status(row, "previous_status")?,
+        status: intent_status(row, "status")?.unwrap_or(IntentStatus::Created),
+        reason: row.try_get("reason")?,
+        signature: row.try_get("signature")?,
+        at: timestamp(row.try_get("created_at")?),
+    })
+}
+
+/// Reads an intent with its event history, locking the row when `for_update` is set.
+async fn read_intent(
+    conn: &mut AnyConnection,
+    id: &str,
+    for_update: &str,
+) -> Result<Option<PaymentIntentRecord>, sqlx::Error> {
+    let Some(row) = sqlx::query(&format!(
+        "SELECT {INTENT_COLUMNS} FROM payment_intents WHERE id = $1{for_update}"
+    ))
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        return Ok(None);
+    };
+    let mut intent = intent_from_row(&row)?;
+    intent.events = sqlx::query(&format!(
+        "SELECT {EVENT_COLUMNS} FROM payment_events WHERE payment_intent_id = $1 ORDER BY seq"
+    ))
+    .bind(id)
+    .fetch_all(&mut *conn)
+    .await?
+    .iter()
+    .map(event_from_row)
+    .collect::<Result<_, _>>()?;
+    Ok(Some(intent))
+}
+
+async fn write_token_family(
+    conn: &mut AnyConnection,
+    record: &TokenFamilyRecord,
+) -> Result<(), sqlx::Error> {
+    let retired = serde_json::to_string(&record.retired_hashes)
+        .map_err(|err| sqlx::Error::Encode(Box::new(err)))?;
+    sqlx::query(&format!(
+        "INSERT INTO token_families ({TOKEN_FAMILY_COLUMNS}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
+         ON CONFLICT (id) DO UPDATE SET user_id = excluded.user_id, wallet = excluded.wallet, \
+         device_id = excluded.device_id, current_hash = excluded.current_hash, \
+         retired_hashes = excluded.retired_hashes, expires_at = excluded.expires_at, \
+         revoked_at = excluded.revoked_at"
+    ))
+    .bind(&record.id)
+    .bind(&record.user_id)
+    .bind(&record.wallet)
+    .bind(&record.device_id)
+    .bind(&record.current_hash)
+    .bind(retired)
+    .bind(micros(record.expires_at))
+    .bind(record.revoked_at.map(micros))
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn write_pin_profile(
+    conn: &mut AnyConnection,
+    user_id: &str,
+    record: &PinProfileRecord,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO pin_profiles (user_id, pin_hash, failed_attempts, locked_until) \
+         VALUES ($1, $2, $3, $4) ON CONFLICT (user_id) DO UPDATE SET \
+         pin_hash = excluded.pin_hash, failed_attempts = excluded.failed_attempts, \
+         locked_until = excluded.locked_until",
+    )
+    .bind(user_id)
+    .bind(&record.pin_hash)
+    .bind(i64::from(record.failed_attempts))
+    .bind(record.locked_until.map(micros))
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn write_session(
+    conn: &mut AnyConnection,
+    user_id: &str,
+    record: &SessionRecord,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!(
+        "INSERT INTO payment_sessions (user_id, {SESSION_COLUMNS}) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) ON CONFLICT (user_id) DO UPDATE SET \
+         id = excluded.id, wallet_pubkey = excluded.wallet_pubkey, \
+         device_id = excluded.device_id, per_tx_limit_paise = excluded.per_tx_limit_paise, \
+         daily_limit_paise = excluded.daily_limit_paise, \
+         used_today_paise = excluded.used_today_paise, \
+         expires_at = excluded.expires_at, status = excluded.status"
+    ))
+    .bind(user_id)
+    .bind(&record.id)
+    .bind(&record.wallet)
+    .bind(&record.device_id)
+    .bind(signed(record.per_tx_limit_inr.paise())?)
+    .bind(signed(record.daily_limit_inr.paise())?)
+    .bind(signed(record.used_today_inr.paise())?)
+    .bind(micros(record.expires_at))
+    .bind(&record.status)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn write_intent(
+    conn: &mut AnyConnection,
+    record: &PaymentIntentRecord,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!(
+        "INSERT INTO payment_intents ({INTENT_COLUMNS}) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, \
+         $17, $18, $19) \
+         ON CONFLICT (id) DO UPDATE SET recipient_wallet = excluded.recipient_wallet, \
+         amount_paise = excluded.amount_paise, token_base_units = excluded.token_base_units, \
+         token_decimals = excluded.token_decimals, token = excluded.token, \
+         quote_id = excluded.quote_id, \
+         quote_expires_at = excluded.quote_expires_at, status = excluded.status, \
+         mode = excluded.mode, signature = excluded.signature, ref = excluded.ref, \
+         session_id = excluded.session_id, \
+         transaction_served_at = excluded.transaction_served_at"
+    ))
+    .bind(&record.id)
+    .bind(&record.creator_user_id)
+    .bind(&record.recipient_wallet)
+    .bind(signed(record.inr_amount.paise())?)
+    .bind(signed(record.token_amount.base_units())?)
+    .bind(i64::from(record.token_amount.decimals()))
+    .bind(&record.token)
+    .bind(&record.quote_id)
+    .bind(micros(record.quote_expires_at))
+    .bind(record.status.as_str())
+    .bind(record.mode.clone())
+    .bind(record.signature.clone())
+    .bind(&record.reference)
+    .bind(record.session_id.clone())
+    .bind(&record.payer_wallet)
+    .bind(micros(record.created_at))
+    .bind(record.recipient_handle.clone())
+    .bind(record.memo.clone())
+    .bind(record.transaction_served_at.map(micros))
+    .execute(&mut *conn)
+    .await?;
+
+    // History is append-only; events already stored are left untouched.
+    for (seq, event) in record.events.iter().enumerate() {
+        sqlx::query(&format!(
+            "INSERT INTO payment_events (payment_intent_id, seq, {EVENT_COLUMNS}) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT (id) DO NOTHING"
+        ))
+        .bind(&record.id)
+        .bind(seq as i64)
+        .bind(&event.id)
+        .bind(event.previous_status.map(|status| status.as_str()))
+        .bind(event.status.as_str())
+        .bind(event.reason.clone())
+        .bind(event.signature.clone())
+        .bind(micros(event.at))
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+async fn write_quote(conn: &mut AnyConnection, record: &QuoteRecord) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!(
+        "INSERT INTO quotes ({QUOTE_COLUMNS}) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) ON CONFLICT (id) DO UPDATE SET \
+         expires_at = excluded.expires_at, intent_id = excluded.intent_id"
+    ))
+    .bind(&record.id)
+    .bind(&record.token)
+    .bind(signed(record.inr_amount.paise())?)
+    .bind(signed(record.token_amount.base_units())?)
+    .bind(i64::from(record.token_amount.decimals()))
+    .bind(record.rate.to_string())
+    .bind(&record.source)
+    .bind(micros(record.as_of))
+    .bind(micros(record.expires_at))
+    .bind(i64::from(record.max_slippage_bps))
+    .bind(record.intent_id.clone())
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[async_trait]
+impl Store for SqlStore {
+    async fn push_nonce(&self, wallet: &str, record: NonceRecord) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(db_err)?;
+        sqlx::query(&format!(
+            "INSERT INTO auth_nonces (wallet, {NONCE_COLUMNS}, created_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7)"
+        ))
+        .bind(wallet)
+        .bind(&record.nonce)
+        .bind(&record.request_id)
+        .bind(record.link_user_id.clone())
+        .bind(micros(record.issued_at))
+        .bind(micros(record.expires_at))
+        .bind(micros(Utc::now()))
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+        sqlx::query(
+            "DELETE FROM auth_nonces WHERE wallet = $1 AND nonce NOT IN \
+             (SELECT nonce FROM auth_nonces WHERE wallet = $1 ORDER BY created_at DESC LIMIT $2)",
+        )
+        .bind(wallet)
+        .bind(MAX_NONCES_PER_WALLET as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+        tx.commit().await.map_err(db_err)
+    }
+
+    async fn find_nonce(&self, wallet: &str, nonce: &str) -> Result<Option<NonceRecord>, AppError> {
+        let row = sqlx::query(&format!(
+            "SELECT {NONCE_COLUMNS} FROM auth_nonces WHERE wallet = $1 AND nonce = $2"
+        ))
+        .bind(wallet)
+        .bind(nonce)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(db_err)?;
+        row.as_ref().map(nonce_from_row).transpose().map_err(db_err)
+    }
+
+    async fn consume_nonce(
+        &self,
+        wallet: &str,
+        nonce: &str,
+    ) -> Result<Option<NonceRecord>, AppError> {
+        let row = sqlx::query(&format!(
+            "DELETE FROM auth_nonces WHERE wallet = $1 AND nonce = $2 RETURNING {NONCE_COLUMNS}"
+        ))
+        .bind(wallet)
+        .bind(nonce)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(db_err)?;
+        row.as_ref().map(nonce_from_row).transpose().map_err(db_err)
+    }
+
+    async fn evict_expired_nonces(&self, now: DateTime<Utc>) -> Result<usize, AppError> {
+        let result = sqlx::query("DELETE FROM auth_nonces WHERE expires_at <= $1")
+            .bind(micros(now))
+            .execute(&self.pool)
+            .await
+            .map_err(db_err)?;
+        Ok(result.rows_affected() as usize)
+    }
+
+    async fn wallet_link(&self, wallet: &str) -> Result<Option<WalletLinkRecord>, AppError> {
+        let row = sqlx::query(&format!(
+            "SELECT {WALLET_LINK_COLUMNS} FROM wallet_links WHERE pubkey = $1"
+        ))
+        .bind(wallet)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(db_err)?;
+        row.as_ref()
+            .map(wallet_link_from_row)
+            .transpose()
+            .map_err(db_err)
+    }
+
+    async fn user_wallets(&self, user_id: &str) -> Result<Vec<WalletLinkRecord>, AppError> {
+        let rows = sqlx::query(&format!(
+            "SELECT {WALLET_LINK_COLUMNS} FROM wallet_links WHERE user_id = $1 \
+             ORDER BY is_primary DESC, linked_at ASC"
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_err)?;
+        rows.iter()
+            .map(wallet_link_from_row)
+            .collect::<Result<_, _>>()
+            .map_err(db_err)
+    }
+
+    async fn link_wallet(
+        &self,
+        user_id: &str,
+        wallet: &str,
+        now: DateTime<Utc>,
+    ) -> Result<WalletLinkRecord, AppError> {
+        let mut tx = self.pool.begin().await.map_err(db_err)?;
+        sqlx::query(
+            "INSERT INTO users (id, created_at) VALUES ($1, $2) ON CONFLICT (id) DO NOTHING",
+        )
+        .bind(user_id)
+        .bind(micros(now))
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+        let has_primary = sqlx::query(&format!(
+            "SELECT pubkey FROM wallet_links WHERE user_id = $1 AND is_primary = $2{}",
+            self.for_update
+        ))
+        .bind(user_id)
+        .bind(1_i64)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(db_err)?
+        .is_some();
+        let record = WalletLinkRecord {
+            user_id: user_id.to_string(),
+            pubkey: wallet.to_string(),
+            chain: "solana".to_string(),
+            is_primary: !has_primary,
+            linked_at: now,
+        };
+        sqlx::query(&format!(
+            "INSERT INTO wallet_links ({WALLET_LINK_COLUMNS}) VALUES ($1, $2, $3, $4, $5)"
+        ))
+        .bind(&record.user_id)
+        .bind(&record.pubkey)
+        .bind(&record.chain)
+        .bind(i64::from(record.is_primary))
+        .bind(micros(record.linked_at))
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| {
+            if is_unique_violation(&err) {
+                AppError::coded(
+                    StatusCode::CONFLICT,
+                    "WALLET_ALREADY_LINKED",
+                    "wallet is already linked to an account",
+                )
+            } else {
+                db_err(err)
+            }
+        })?;
+        tx.commit().await.map_err(db_err)?;
+        Ok(record)
+    }
+
+    async fn unlink_wallet(&self, user_id: &str, wallet: &str) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM wallet_links WHERE pubkey = $1 AND user_id = $2")
+            .bind(wallet)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    async fn set_primary_wallet(&self, user_id: &str, wallet: &str) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(db_err)?;
+        sqlx::query("UPDATE wallet_links SET is_primary = $1 WHERE user_id = $2")
+            .bind(0_i64)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        sqlx::query("UPDATE wallet_links SET is_primary = $1 WHERE user_id = $2 AND pubkey = $3")
+            .bind(1_i64)
+            .bind(user_id)
+            .bind(wallet)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        tx.commit().await.map_err(db_err)
+    }
+
+    async fn insert_token_family(&self, record: TokenFamilyRecord) -> Result<(), AppError> {
+        let mut conn = self.conn().await?;
+        write_token_family(&mut conn, &record).await.map_err(db_err)
+    }
+
+    async fn update_token_family<'a>(
+        &self,
+        family_id: &str,
+        mutation: Mutation<'a, TokenFamilyRecord>,
+    ) -> Result<TokenFamilyRecord, AppError> {
+        let mut tx = self.pool.begin().await.map_err(db_err)?;
+        let row = sqlx::query(&format!(
+            "SELECT {TOKEN_FAMILY_COLUMNS} FROM token_families WHERE id = $1{}",
+            self.for_update
+        ))
+        .bind(family_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| AppError::not_found("token family not found"))?;
+        let mut family = token_family_from_row(&row).map_err(db_err)?;
+
+        mutation(&mut family)?;
+
+        write_token_family(&mut tx, &family).await.map_err(db_err)?;
+        tx.commit().await.map_err(db_err)?;
+        Ok(family)
+    }
+
+    async fn revoke_token_families(
+        &self,
+        user_id: &str,
+        family_id: Option<&str>,
+        wallet: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            "UPDATE token_families SET revoked_at = $1 WHERE user_id = $2 AND revoked_at IS NULL \
+             AND ($3 IS NULL OR id = $3) AND ($4 IS NULL OR wallet = $4)",
+        )
+        .bind(micros(now))
+        .bind(user_id)
+        .bind(family_id)
+        .bind(wallet)
+        .execute(&self.pool)
+        .await
+        .map_err(db_err)?;
+        Ok(())
+    }
+
+    async fn revoke_access_token(
+        &self,
+        token_id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            "INSERT INTO revoked_access_tokens (token_id, expires_at) VALUES ($1, $2) \
+             ON CONFLICT (token_id) DO NOTHING",
+        )
+        .bind(token_id)
+        .bind(micros(expires_at))
+        .execute(&self.pool)
+        .await
+        .map_err(db_err)?;
+        Ok(())
+    }
+
+    async fn is_access_token_revoked(
+        &self,
+        token_id: &str,
+        family_id: &str,
+    ) -> Result<bool, AppError> {
+        let revoked = sqlx::query("SELECT token_id FROM revoked_access_tokens WHERE token_id = $1")
+            .bind(token_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_err)?
+            .is_some();
+        if revoked {
+            return Ok(true);
+        }
+        let family_live =
+            sqlx::query("SELECT id FROM token_families WHERE id = $1 AND revoked_at IS NULL")
+                .bind(family_id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(db_err)?
+                .is_some();
+        Ok(!family_live)
+    }
+
+    async fn evict_expired_tokens(&self, now: DateTime<Utc>) -> Result<usize, AppError> {
+        let mut tx = self.pool.begin().await.map_err(db_err)?;
+        let families = sqlx::query("DELETE FROM token_families WHERE expires_at <= $1")
+            .bind(micros(now))
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        let revoked = sqlx::query("DELETE FROM revoked_access_tokens WHERE expires_at <= $1")
+            .bind(micros(now))
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        tx.commit().await.map_err(db_err)?;
+        Ok((families.rows_affected() + revoked.rows_affected()) as usize)
+    }
+
+    async fn handle_wallet(&self, handle: &str) -> Result<Option<String>, AppError> {
+        let row = sqlx::query("SELECT wallet FROM handles WHERE handle = $1")
+            .bind(handle)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_err)?;
+        row.map(|row| row.try_get("wallet"))
+            .transpose()
+            .map_err(db_err)
+    }
+
+    async fn wallet_handle(&self, wallet: &str) -> Result<Option<String>, AppError> {
+        sqlx::query_scalar("SELECT MIN(handle) FROM handles WHERE wallet = $1")
+            .bind(wallet)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(db_err)
+    }
+
+    async fn claim_handle(&self, handle: &str, wallet: &str) -> Result<(), AppError> {
+        sqlx::query(
+            "INSERT INTO handles (handle, wallet) VALUES ($1, $2) ON CONFLICT (handle) DO NOTHING",
+        )
+        .bind(handle)
+        .bind(wallet)
+        .execute(&self.pool)
+        .await
+        .map_err(db_err)?;
+        if self.handle_wallet(handle).await?.as_deref() != Some(wallet) {
+            return Err(AppError::bad_request("handle is already taken"));
+        }
+        Ok(())
+    }
+
+    async fn pin_profile(&self, user_id: &str) -> Result<Option<PinProfileRecord>, AppError> {
+        let row = sqlx::query(&format!(
+            "SELECT {PIN_PROFILE_COLUMNS} FROM pin_profiles WHERE user_id = $1"
+        ))
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(db_err)?;
+        row.as_ref()
+            .map(pin_profile_from_row)
+            .transpose()
+            .map_err(db_err)
+    }
+
+    async fn put_pin_profile(
+        &self,
+        user_id: &str,
+        record: PinProfileRecord,
+    ) -> Result<(), AppError> {
+        let mut conn = self.conn().await?;
+        write_pin_profile(&mut conn, user_id, &record)
+            .await
+            .map_err(db_err)
+    }
+
+    async fn update_pin_profile<'a>(
+        &self,
+        user_id: &str,
+        mutation: Mutation<'a, PinProfileRecord>,
+    ) -> Result<PinProfileRecord, AppError> {
+        let mut tx = self.pool.begin().await.map_err(db_err)?;
+        let row = sqlx::query(&format!(
+            "SELECT {PIN_PROFILE_COLUMNS} FROM pin_profiles WHERE user_id = $1{}",
+            self.for_update
+        ))
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| AppError::not_found("pin profile not found"))?;
+        let mut profile = pin_profile_from_row(&row).map_err(db_err)?;
+
+        mutation(&mut profile)?;
+
+        write_pin_profile(&mut tx, user_id, &profile)
+            .await
+            .map_err(db_err)?;
+        tx.commit().await.map_err(db_err)?;
+        Ok(profile)
+    }
+
+    async fn session(&self, user_id: &str) -> Result<Option<SessionRecord>, AppError> {
+        let row = sqlx::query(&format!(
+            "SELECT {SESSION_COLUMNS} FROM payment_sessions WHERE user_id = $1"
+        ))
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(db_err)?;
+        row.as_ref()
+            .map(session_from_row)
+            .transpose()
+            .map_err(db_err)
+    }
+
+    async fn put_session(&self, user_id: &str, record: SessionRecord) -> Result<(), AppError> {
+        let mut conn = self.conn().await?;
+        write_session(&mut conn, user_id, &record)
+            .await
+            .map_err(db_err)
+    }
+
+    async fn delete_session(&self, user_id: &str) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM payment_sessions WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    async fn insert_quote(&self, record: QuoteRecord) -> Result<(), AppError> {
+        let mut conn = self.conn().await?;
+        write_quote(&mut conn, &record).await.map_err(db_err)
+    }
+
+    async fn quote(&self, id: &str) -> Result<Option<QuoteRecord>, AppError> {
+        let row = sqlx::query(&format!("SELECT {QUOTE_COLUMNS} FROM quotes WHERE id = $1"))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_err)?;
+        row.as_ref().map(quote_from_row).transpose().map_err(db_err)
+    }
+
+    async fn update_quote<'a>(
+        &self,
+        id: &str,
+        mutation: Mutation<'a, QuoteRecord>,
+    ) -> Result<QuoteRecord, AppError> {
+        let mut tx = self.pool.begin().await.map_err(db_err)?;
+        let row = sqlx::query(&format!(
+            "SELECT {QUOTE_COLUMNS} FROM quotes WHERE id = $1{}",
+            self.for_update
+        ))
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| AppError::not_found("quote not found"))?;
+        let mut quote = quote_from_row(&row).map_err(db_err)?;
+
+        mutation(&mut quote)?;
+
+        write_quote(&mut tx, &quote).await.map_err(db_err)?;
+        tx.commit().await.map_err(db_err)?;
+        Ok(quote)
+    }
+
+    async fn evict_expired_quotes(&self, now: DateTime<Utc>) -> Result<usize, AppError> {
+        let result = sqlx::query("DELETE FROM quotes WHERE expires_at <= $1")
+            .bind(micros(now))
+            .execute(&self.pool)
+            .await
+            .map_err(db_err)?;
+        Ok(result.rows_affected() as usize)
+    }
+
+    async fn reserve_idempotency_key(
+        &self,
+        record: IdempotencyRecord,
+    ) -> Result<Option<IdempotencyRecord>, AppError> {
+        let mut tx = self.pool.begin().await.map_err(db_err)?;
+        sqlx::query(
+            "DELETE FROM idempotency_keys \
+             WHERE user_id = $1 AND idempotency_key = $2 AND expires_at <= $3",
+        )
+        .bind(&record.user_id)
+        .bind(&record.key)
+        .bind(micros(Utc::now()))
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+        let inserted = sqlx::query(&format!(
+            "INSERT INTO idempotency_keys ({IDEMPOTENCY_COLUMNS}) VALUES ($1, $2, $3, $4, $5, $6) \
+             ON CONFLICT (user_id, idempotency_key) DO NOTHING"
+        ))
+        .bind(&record.user_id)
+        .bind(&record.key)
+        .bind(&record.request_hash)
+        .bind(record.response_status.map(i64::from))
+        .bind(&record.response_body)
+        .bind(micros(record.expires_at))
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?
+        .rows_affected();
+        let existing = if inserted == 0 {
+            let row = sqlx::query(&format!(
+                "SELECT {IDEMPOTENCY_COLUMNS} FROM idempotency_keys \
+                 WHERE user_id = $1 AND idempotency_key = $2"
+            ))
+            .bind(&record.user_id)
+            .bind(&record.key)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_err)?;
+            Some(idempotency_from_row(&row).map_err(db_err)?)
+        } else {
+            None
+        };
+        tx.commit().await.map_err(db_err)?;
+        Ok(existing)
+    }
+
+    async fn complete_idempotency_key(
+        &self,
+        user_id: &str,
+        key: &str,
+        status: u16,
+        body: String,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            "UPDATE idempotency_keys SET response_status = $3, response_body = $4, \
+             expires_at = $5 WHERE user_id = $1 AND idempotency_key = $2",
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(i64::from(status))
+        .bind(body)
+        .bind(micros(expires_at))
+        .execute(&self.pool)
+        .await
+        .map_err(db_err)?;
+        Ok(())
+    }
+
+    async fn release_idempotency_key(&self, user_id: &str, key: &str) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM idempotency_keys WHERE user_id = $1 AND idempotency_key = $2")
+            .bind(user_id)
+            .bind(key)
+            .execute(&self.pool)
+            .await
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    async fn evict_expired_idempotency_keys(&self, now: DateTime<Utc>) -> Result<usize, AppError> {
+        let result = sqlx::query("DELETE FROM idempotency_keys WHERE expires_at <= $1")
+            .bind(micros(now))
+            .execute(&self.pool)
+            .await
+            .map_err(db_err)?;
+        Ok(result.rows_affected() as usize)
+    }
+
+    async fn record_webhook_delivery(
+        &self,
+        id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool, AppError> {
+        let mut tx = self.pool.begin().await.map_err(db_err)?;
+        sqlx::query("DELETE FROM webhook_deliveries WHERE id = $1 AND expires_at <= $2")
+            .bind(id)
+            .bind(micros(Utc::now()))
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        let inserted = sqlx::query(
+            "INSERT INTO webhook_deliveries (id, expires_at) VALUES ($1, $2) \
+             ON CONFLICT (id) DO NOTHING",
+        )
+        .bind(id)
+        .bind(micros(expires_at))
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?
+        .rows_affected();
+        tx.commit().await.map_err(db_err)?;
+        Ok(inserted > 0)
+    }
+
+    async fn release_webhook_delivery(&self, id: &str) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM webhook_deliveries WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    async fn evict_expired_webhook_deliveries(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<usize, AppError> {
+        let result = sqlx::query("DELETE FROM webhook_deliveries WHERE expires_at <= $1")
+            .bind(micros(now))
+            .execute(&self.pool)
+            .await
+            .map_err(db_err)?;
+        Ok(result.rows_affected() as usize)
+    }
+
+    async fn payment_intent(&self, id: &str) -> Result<Option<PaymentIntentRecord>, AppError> {
+        let mut conn = self.conn().await?;
+        read_intent(&mut conn, id, "").await.map_err(db_err)
+    }
+
+    async fn insert_payment_intent(&self, record: PaymentIntentRecord) -> Result<(), AppError> {
+        let mut conn = self.conn().await?;
+        write_intent(&mut conn, &record).await.map_err(db_err)
+    }
+
+    async fn payment_intent_by_reference(
+        &self,
+        reference: &str,
+    ) -> Result<Option<PaymentIntentRecord>, AppError> {
+        let mut conn = self.conn().await?;
+        let id: Option<String> =
+            sqlx::query_scalar("SELECT id FROM payment_intents WHERE ref = $1")
+                .bind(reference)
+                .fetch_optional(&mut *conn)
+                .await
+                .map_err(db_err)?;
+        match id {
+            Some(id) => read_intent(&mut conn, &id, "").await.map_err(db_err),
+            None => Ok(None),
+        }
+    }
+
+    async fn update_payment_intent<'a>(
+        &self,
+        id: &str,
+        mutation: Mutation<'a, PaymentIntentRecord>,
+    ) -> Result<PaymentIntentRecord, AppError> {
+        let mut tx = self.pool.begin().await.map_err(db_err)?;
+        let mut intent = read_intent(&mut tx, id, self.for_update)
+            .await
+            .map_err(db_err)?
+            .ok_or_else(|| AppError::not_found("payment intent not found"))?;
+
+        mutation(&mut intent)?;
+
+        write_intent(&mut tx, &intent).await.map_err(db_err)?;
+        tx.commit().await.map_err(db_err)?;
+        Ok(intent)
+    }
+
+    async fn payment_intents_with_status(
+        &self,
+        statuses: &[IntentStatus],
+    ) -> Result<Vec<PaymentIntentRecord>, AppError> {
+        if statuses.is_empty() {
+            return Ok(Vec::new());
+        }
+        let placeholders = (1..=statuses.len())
+            .map(|n| format!("${n}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let query = format!("SELECT id FROM payment_intents WHERE status IN ({placeholders})");
+        let mut conn = self.conn().await?;
+        let ids: Vec<String> = statuses
+            .iter()
+            .fold(sqlx::query_scalar(&query), |query, status| {
+                query.bind(status.as_str())
+            })
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(db_err)?;
+
+        let mut intents = Vec::with_capacity(ids.len());
+        for id in ids {
+            // Skips intents removed since the listing.
+            if let Some(intent) = read_intent(&mut conn, &id, "").await.map_err(db_err)? {
+                intents.push(intent);
+            }
+        }
+        Ok(intents)
+    }
+
+    async fn list_payment_intents(
+        &self,
+        query: &IntentQuery,
+    ) -> Result<Vec<PaymentIntentRecord>, AppError> {
+        enum Param {
+            Text(String),
+            Int(i64),
+        }
+        let mut params = Vec::new();
+        let mut bind = |param: Param| {
+            params.push(param);
+            format!("${}", params.len())
+        };
+
+        let counterparty = query.counterparty_wallet.clone();
+        let mut sides = Vec::new();
+        if query.sent {
+            let mut side = format!(
+                "creator_user_id = {}",
+                bind(Param::Text(query.user_id.clone()))
+            );
+            if let Some(wallet) = &counterparty {
+                side += &format!(
+                    " AND recipient_wallet = {}",
+                    bind(Param::Text(wallet.clone()))
+                );
+            }
+            sides.push(format!("({side})"));
+        }
+        if !query.received_wallets.is_empty() {
+            let wallets = query
+                .received_wallets
+                .iter()
+                .map(|wallet| bind(Param::Text(wallet.clone())))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let mut side = format!("recipient_wallet IN ({wallets})");
+            if let Some(wallet) = &counterparty {
+                side += &format!(" AND payer_wallet = {}", bind(Param::Text(wallet.clone())));
+            }
+            sides.push(format!("({side})"));
+        }
+        if sides.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut conditions = vec![format!("({})", sides.join(" OR "))];
+
+        if !query.statuses.is_empty() {
+            let statuses = query
+                .statuses
+                .iter()
+                .map(|status| bind(Param::Text(status.as_str().to_string())))
+                .collect::<Vec<_>>()
+                .join(", ");
+            conditions.push(format!("status IN ({statuses})"));
+        }
+        if let Some(token) = &query.token {
+            conditions.push(format!("token = {}", bind(Param::Text(token.clone()))));
+        }
+        if let Some(from) = query.created_from {
+            conditions.push(format!("created_at >= {}", bind(Param::Int(micros(from)))));
+        }
+        if let Some(before) = query.created_before {
+            conditions.push(format!("created_at < {}", bind(Param::Int(micros(before)))));
+        }
+        if let Some(min) = query.min_inr {
+            let min = signed(min.paise()).map_err(db_err)?;
+            conditions.push(format!("amount_paise >= {}", bind(Param::Int(min))));
+        }
+        if let Some(max) = query.max_inr {
+            let max = signed(max.paise()).map_err(db_err)?;
+            conditions.push(format!("amount_paise <= {}", bind(Param::Int(max))));
+        }
+
+        let column = match query.sort {
+            IntentSort::Newest | IntentSort::Oldest => "created_at",
+            IntentSort::Largest | IntentSort::Smallest => "amount_paise",
+        };
+        let (direction, past) = if query.sort.descending() {
+            ("DESC", "<")
+        } else {
+            ("ASC", ">")
+        };
+        if let Some(cursor) = &query.after {
+            let key = bind(Param::Int(cursor.key));
+            let id = bind(Param::Text(cursor.id.clone()));
+            conditions.push(format!(
+                "({column} {past} {key} OR ({column} = {key} AND id {past} {id}))"
+            ));
+        }
+        let limit = i64::try_from(query.limit).unwrap_or(i64::MAX);
+        let sql = format!(
+            "SELECT id FROM payment_intents WHERE {} ORDER BY {column} {direction}, id {direction} \
+             LIMIT {}",
+            conditions.join(" AND "),
+            bind(Param::Int(limit))
+        );
+
+        let mut conn = self.conn().await?;
+        let ids: Vec<String> = params
+            .into_iter()
+            .fold(sqlx::query_scalar(&sql), |sql, param| match param {
+                Param::Text(value) => sql.bind(value),
+                Param::Int(value) => sql.bind(value),
+            })
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(db_err)?;
+        let mut intents = Vec::with_capacity(ids.len());
+        for id in &ids {
+            // Deleted by the sweeper in between; the page is one shorter.
+            if let Some(intent) = read_intent(&mut conn, id, "").await.map_err(db_err)? {
+                intents.push(intent);
+            }
+        }
+        Ok(intents)
+    }
+
+    async fn purge_payment_intents(&self, closed_before: DateTime<Utc>) -> Result<usize, AppError> {
+        // Same rule as `lifecycle::closed_unpaid`. Intents from before status history was
+        // kept fall back to their quote expiry.
+        let ids: Vec<String> = sqlx::query_scalar(
+            "SELECT id FROM payment_intents \
+             WHERE (status IN ($2, $3) OR (status = $4 AND signature IS NULL)) \
+             AND COALESCE((SELECT MAX(created_at) FROM payment_events \
+             WHERE payment_intent_id = payment_intents.id), quote_expires_at) < $1",
+        )
+        .bind(micros(closed_before))
+        .bind(IntentStatus::Expired.as_str())
+        .bind(IntentStatus::Cancelled.as_str())
+        .bind(IntentStatus::Failed.as_str())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_err)?;
+
+        let mut tx = self.pool.begin().await.map_err(db_err)?;
+        for id in &ids {
+            sqlx::query("DELETE FROM payment_events WHERE payment_intent_id = $1")
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .map_err(db_err)?;
+            sqlx::query("DELETE FROM payment_intents WHERE id = $1")
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .map_err(db_err)?;
+        }
+        tx.commit().await.map_err(db_err)?;
+        Ok(ids.len())
+    }
+
+    async fn spend_from_session<'a>(
+        &self,
+        user_id: &str,
+        intent_id: &str,
+        mutation: SpendMutation<'a>,
+    ) -> Result<(SessionRecord, PaymentIntentRecord), AppError> {
+        let mut tx = self.pool.begin().await.map_err(db_err)?;
+        let session_row = sqlx::query(&format!(
+            "SELECT {SESSION_COLUMNS} FROM payment_sessions WHERE user_id = $1{}",
+            self.for_update
+        ))
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| AppError::unauthorized("active session required"))?;
+        let mut intent = read_intent(&mut tx, intent_id, self.for_update)
+            .await
+            .map_err(db_err)?
+            .ok_or_else(|| AppError::not_found("payment intent not found"))?;
+        let mut session = session_from_row(&session_row).map_err(db_err)?;
+
+        mutation(&mut session, &mut intent)?;
+
+        write_session(&mut tx, user_id, &session)
+            .await
+            .map_err(db_err)?;
+        write_intent(&mut tx, &intent).await.map_err(db_err)?;
+        tx.commit().await.map_err(db_err)?;
+        Ok((session, intent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{InMemoryStore, IntentCursor};
+    use crate::testing::{at, intent};
+    use chrono::Duration;
+
+    /// A fresh SQLite database next to the in-memory store it must agree with.
+    async fn stores() -> Vec<Box<dyn Store>> {
+        let sql = SqlStore::connect("sqlite::memory:")
+            .await
+            .expect("migrations apply to an empty database");
+        vec![Box::new(InMemoryStore::default()), Box::new(sql)]
+    }
+
+    fn nonce(value: &str) -> NonceRecord {
+        NonceRecord {
+            nonce: value.to_string(),
+            request_id: format!("req_{value}"),
+            link_user_id: None,
+            issued_at: at(0),
+            expires_at: Utc::now() + Duration::minutes(5),
+        }
+    }
+
+    fn session(daily_paise: u64) -> SessionRecord {
+        SessionRecord {
+            id: "ses_1".to_string(),
+            wallet: "payer".to_string(),
+            device_id: "device".to_string(),
+            per_tx_limit_inr: Inr::from_paise(60_000),
+            daily_limit_inr: Inr::from_paise(daily_paise),
+            used_today_inr: Inr::ZERO,
+            expires_at: Utc::now() + Duration::hours(1),
+            status: "active".to_string(),
+        }
+    }
+
+    /// The execute handler's limit checks, reduced to what the store has to honour.
+    fn spend(
+        session: &mut SessionRecord,
+        intent: &mut PaymentIntentRecord,
+    ) -> Result<(), AppError> {
+        if intent.inr_amount > session.per_tx_limit_inr {
+            return Err(AppError::unauthorized(
+                "session per transaction limit exceeded",
+            ));
+        }
+        session.used_today_inr = session
+            .used_today_inr
+            .checked_add(intent.inr_amount)
+            .filter(|used| *used <= session.daily_limit_inr)
+            .ok_or_else(|| AppError::unauthorized("session daily limit exceeded"))?;
+        intent.status = IntentStatus::Authorized;
+        intent.session_id = Some(session.id.clone());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn nonces_are_capped_per_wallet_and_consumed_once() {
+        let mut results = Vec::new();
+        for store in stores().await {
+            for i in 0..MAX_NONCES_PER_WALLET + 2 {
+                store
+                    .push_nonce("wallet", nonce(&format!("n{i}")))
+                    .await
+                    .unwrap();
+                // Orders the SQL rows by insertion time.
+                tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+            }
+            store.push_nonce("other", nonce("n0")).await.unwrap();
+
+            let mut kept = Vec::new();
+            for i in 0..MAX_NONCES_PER_WALLET + 2 {
+                let value = format!("n{i}");
+                if store.find_nonce("wallet", &value).await.unwrap().is_some() {
+                    kept.push(value);
+                }
+            }
+            let consumed = store.consume_nonce("wallet", "n6").await.unwrap();
+            let replayed = store.consume_nonce("wallet", "n6").await.unwrap();
+            let other = store.find_nonce("other", "n0").await.unwrap();
+            results.push((
+                kept,
+                consumed.map(|record| record.request_id),
+                replayed.is_none(),
+                other.is_some(),
+            ));
+        }
+        assert_eq!(
+            results[0],
+            (
+                vec!["n2", "n3", "n4", "n5", "n6"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+                Some("req_n6".to_string()),
+                true,
+                true,
+            )
+        );
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[tokio::test]
+    async fn token_families_rotate_atomically_and_revoke_by_user_wallet_or_id() {
+        let mut results = Vec::new();
+        for store in stores().await {
+            for (id, wallet) in [
+                ("fam_1", "wallet_a"),
+                ("fam_2", "wallet_a"),
+                ("fam_3", "wallet_b"),
+            ] {
+                let family = TokenFamilyRecord {
+                    id: id.to_string(),
+                    user_id: "alice".to_string(),
+                    wallet: wallet.to_string(),
+                    device_id: "phone".to_string(),
+                    current_hash: "h0".to_string(),
+                    retired_hashes: Vec::new(),
+                    expires_at: at(3_600),
+                    revoked_at: None,
+                };
+                store.insert_token_family(family).await.unwrap();
+            }
+
+            let rotated = store
+                .update_token_family(
+                    "fam_1",
+                    Box::new(|family| {
+                        let retired = std::mem::replace(&mut family.current_hash, "h1".into());
+                        family.retired_hashes.push(retired);
+                        Ok(())
+                    }),
+                )
+                .await
+                .unwrap();
+            // A refused mutation leaves the family as it was.
+            let refused = store
+                .update_token_family(
+                    "fam_1",
+                    Box::new(|family| {
+                        family.current_hash = "h2".into();
+                        Err(AppError::unauthorized("refused"))
+                    }),
+                )
+                .await;
+            let unchanged = store
+                .update_token_family("fam_1", Box::new(|_| Ok(())))
+                .await
+                .unwrap();
+            let missing = store
+                .update_token_family("fam_9", Box::new(|_| Ok(())))
+                .await;
+
+            store
+                .revoke_token_families("alice", None, Some("wallet_a"), at(10))
+                .await
+                .unwrap();
+            let mut revoked = Vec::new();
+            for family in ["fam_1", "fam_2", "fam_3", "fam_9"] {
+                revoked.push(store.is_access_token_revoked("jti", family).await.unwrap());
+            }
+            store.revoke_access_token("jti_3", at(3_600)).await.unwrap();
+            revoked.push(
+                store
+                    .is_access_token_revoked("jti_3", "fam_3")
+                    .await
+                    .unwrap(),
+            );
+            results.push((
+                rotated.current_hash,
+                rotated.retired_hashes,
+                refused.is_err(),
+                unchanged.current_hash,
+                matches!(missing, Err(AppError::NotFound(_))),
+                revoked,
+            ));
+        }
+        assert_eq!(
+            results[0],
+            (
+                "h1".to_string(),
+                vec!["h0".to_string()],
+                true,
+                "h1".to_string(),
+                true,
+                vec![true, true, false, true, true],
+            )
+        );
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[tokio::test]
+    async fn session_spend_stops_at_the_limits_and_rolls_back_refusals() {
+        let mut results = Vec::new();
+        for store in stores().await {
+            store.put_session("alice", session(100_000)).await.unwrap();
+            for (id, paise) in [
+                ("pi_a", 40_000),
+                ("pi_b", 60_000),
+                ("pi_c", 60_001),
+                ("pi_d", 1),
+            ] {
+                store
+                    .insert_payment_intent(intent(id, "alice", "bob", paise, at(0)))
+                    .await
+                    .unwrap();
+            }
+
+            let mut outcomes = Vec::new();
+            for id in ["pi_a", "pi_c", "pi_b", "pi_d"] {
+                let outcome = store
+                    .spend_from_session("alice", id, Box::new(spend))
+                    .await
+                    .map(|(session, _)| session.used_today_inr.paise())
+                    .map_err(|err| format!("{err:?}"));
+                outcomes.push(outcome);
+            }
+            let used = store
+                .session("alice")
+                .await
+                .unwrap()
+                .unwrap()
+                .used_today_inr;
+            let refused = store.payment_intent("pi_d").await.unwrap().unwrap();
+            let missing = store
+                .spend_from_session("bob", "pi_a", Box::new(spend))
+                .await
+                .is_err();
+            results.push((
+                outcomes,
+                used.paise(),
+                refused.status,
+                refused.session_id,
+                missing,
+            ));
+        }
+        let (outcomes, used, refused_status, refused_session, missing) = &results[0];
+        assert_eq!(outcomes[0], Ok(40_000));
+        assert!(outcomes[1]
+            .as_ref()
+            .unwrap_err()
+            .contains("per transaction"));
+        assert_eq!(outcomes[2], Ok(100_000));
+        assert!(outcomes[3].as_ref().unwrap_err().contains("daily limit"));
+        assert_eq!(*used, 100_000);
+        assert_eq!(*refused_status, IntentStatus::Created);
+        assert_eq!(*refused_session, None);
+        assert!(missing);
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[tokio::test]
+    async fn idempotency_keys_are_reserved_per_user_until_released() {
+        let mut results = Vec::new();
+        for store in stores().await {
+            let record = |user: &str, hash: &str| IdempotencyRecord {
+                user_id: user.to_string(),
+                key: "key-1".to_string(),
+                request_hash: hash.to_string(),
+                response_status: None,
+                response_body: String::new(),
+                expires_at: Utc::now() + Duration::minutes(1),
+            };
+            let first = store
+                .reserve_idempotency_key(record("alice", "h1"))
+                .await
+                .unwrap();
+            let in_flight = store
+                .reserve_idempotency_key(record("alice", "h2"))
+                .await
+                .unwrap();
+            let other_user = store
+                .reserve_idempotency_key(record("bob", "h3"))
+                .await
+                .unwrap();
+            store
+                .complete_idempotency_key(
+                    "alice",
+                    "key-1",
+                    201,
+                    "{\"ok\":true}".to_string(),
+                    Utc::now() + Duration::hours(24),
+                )
+                .await
+                .unwrap();
+            let completed = store
+                .reserve_idempotency_key(record("alice", "h4"))
+                .await
+                .unwrap();
+            store.release_idempotency_key("bob", "key-1").await.unwrap();
+            let released = store
+                .reserve_idempotency_key(record("bob", "h5"))
+                .await
+                .unwrap();
+
+            let summary = |record: Option<IdempotencyRecord>| {
+                record.map(|record| {
+                    (
+                        record.request_hash,
+                        record.response_status,
+                        record.response_body,
+                    )
+                })
+            };
+            results.push((
+                summary(first),
+                summary(in_flight),
+                summary(other_user),
+                summary(completed),
+                summary(released),
+            ));
+        }
+        assert_eq!(
+            results[0],
+            (
+                None,
+                Some(("h1".to_string(), None, String::new())),
+                None,
+                Some(("h1".to_string(), Some(201), "{\"ok\":true}".to_string())),
+                None,
+            )
+        );
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[tokio::test]
+    async fn intent_pages_follow_the_cursor() {
+        let mut results = Vec::new();
+        for store in stores().await {
+            // Two intents share a creation time so the id has to break the tie.
+            let intents = [
+                intent("pi_1", "alice", "bob", 500, at(10)),
+                intent("pi_2", "alice", "bob", 100, at(20)),
+                intent("pi_3", "alice", "carol", 300, at(20)),
+                intent("pi_4", "bob", "alice_wallet", 200, at(30)),
+                intent("pi_5", "carol", "dave", 400, at(40)),
+            ];
+            for record in intents {
+                store.insert_payment_intent(record).await.unwrap();
+            }
+
+            let mut orders = Vec::new();
+            for sort in [
+                IntentSort::Newest,
+                IntentSort::Oldest,
+                IntentSort::Largest,
+                IntentSort::Smallest,
+            ] {
+                let mut query = IntentQuery {
+                    user_id: "alice".to_string(),
+                    sent: true,
+                    received_wallets: vec!["alice_wallet".to_string()],
+                    statuses: Vec::new(),
+                    token: None,
+                    counterparty_wallet: None,
+                    created_from: None,
+                    created_before: None,
+                    min_inr: None,
+                    max_inr: None,
+                    sort,
+                    after: None,
+                    limit: 2,
+                };
+                let mut ids = Vec::new();
+                loop {
+                    let page = store.list_payment_intents(&query).await.unwrap();
+                    let Some(last) = page.last() else {
+                        break;
+                    };
+                    query.after = Some(IntentCursor {
+                        key: sort.key(last),
+                        id: last.id.clone(),
+                    });
+                    ids.extend(page.into_iter().map(|intent| intent.id));
+                }
+                orders.push(ids);
+            }
+            results.push(orders);
+        }
+        assert_eq!(
+            results[0],
+            vec![
+                vec!["pi_4", "pi_3", "pi_2", "pi_1"],
+                vec!["pi_1", "pi_2", "pi_3", "pi_4"],
+                vec!["pi_1", "pi_3", "pi_4", "pi_2"],
+                vec!["pi_2", "pi_4", "pi_3", "pi_1"],
+            ]
+        );
+        assert_eq!(results[0], results[1]);
+    }
+}
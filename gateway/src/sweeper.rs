@@ -0,0 +1,243 @@
+use crate::error::AppError;
+use crate::lifecycle::{self, IntentStatus};
+use crate::routes::payment_intents;
+use crate::state::{AppState, PaymentIntentRecord};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Periodically evicts expired sign-in nonces, token families, revocation entries, quotes,
+/// idempotency keys and webhook deliveries.
+pub fn spawn_auth_sweeper(state: AppState, every: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(every);
+        loop {
+            ticker.tick().await;
+            let now = Utc::now();
+            let evicted = async {
+                let nonces = state.store.evict_expired_nonces(now).await?;
+                let tokens = state.store.evict_expired_tokens(now).await?;
+                let quotes = state.store.evict_expired_quotes(now).await?;
+                let keys = state.store.evict_expired_idempotency_keys(now).await?;
+                let deliveries = state.store.evict_expired_webhook_deliveries(now).await?;
+                Ok::<_, AppError>((nonces, tokens, quotes, keys, deliveries))
+            };
+            match evicted.await {
+                Ok((0, 0, 0, 0, 0)) => {}
+                Ok((nonces, tokens, quotes, keys, deliveries)) => debug!(
+                    "evicted {nonces} expired nonces, {tokens} expired token records, \
+                     {quotes} expired quotes, {keys} idempotency keys \
+                     and {deliveries} webhook deliveries"
+                ),
+                Err(err) => warn!("auth sweep failed: {err:?}"),
+            }
+        }
+    })
+}
+
+/// Periodically expires unpaid payment intents and purges old unpaid ones; see
+/// [`sweep_intents`].
+pub fn spawn_intent_sweeper(state: AppState, every: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(every);
+        loop {
+            ticker.tick().await;
+            match sweep_intents(&state, Utc::now()).await {
+                Ok((0, 0)) => {}
+                Ok((expired, purged)) => {
+                    debug!("expired {expired} payment intents and purged {purged} unpaid ones")
+                }
+                Err(err) => warn!("payment intent sweep failed: {err:?}"),
+            }
+        }
+    })
+}
+
+fn expire(intent: &mut PaymentIntentRecord, now: DateTime<Utc>) -> Result<(), AppError> {
+    lifecycle::transition(
+        intent,
+        IntentStatus::Expired,
+        Some("quote expired before payment".to_string()),
+        now,
+    )
+}
+
+/// Expires one open intent; `false` when it was paid or cancelled in the meantime.
+async fn expire_intent(
+    state: &AppState,
+    intent: &PaymentIntentRecord,
+    now: DateTime<Utc>,
+) -> Result<bool, AppError> {
+    let close = Box::new(move |intent: &mut PaymentIntentRecord| expire(intent, now));
+    match payment_intents::close_unpaid(state, intent, close).await {
+        Ok(_) => Ok(true),
+        Err(AppError::Coded {
+            code: "INVALID_TRANSITION",
+            ..
+        }) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// One sweep at `now`. Created and authorized intents whose quote expired more than the
+/// grace period ago become `expired`; the grace lets a transaction built just before expiry
+/// land and be reconciled first. Intents closed without payment more than the retention ago
+/// are deleted; paid ones are kept as payment history. Returns how many intents were expired
+/// and purged.
+pub async fn sweep_intents(
+    state: &AppState,
+    now: DateTime<Utc>,
+) -> Result<(usize, usize), AppError> {
+    let grace = chrono::Duration::seconds(state.config.intent_expiry_grace_secs);
+    let stale: Vec<PaymentIntentRecord> = state
+        .store
+        .payment_intents_with_status(&[IntentStatus::Created, IntentStatus::Authorized])
+        .await?
+        .into_iter()
+        .filter(|intent| intent.quote_expires_at + grace <= now)
+        .collect();
+    let mut expired = 0;
+    for intent in &stale {
+        expired += usize::from(expire_intent(state, intent, now).await?);
+    }
+
+    let retention = chrono::Duration::hours(state.config.intent_retention_hours);
+    let purged = state.store.purge_payment_intents(now - retention).await?;
+    Ok((expired, purged))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, at, authorize, insert, put_session, status, used_today};
+    use IntentStatus::*;
+
+    /// Both stores with a minute of grace and an hour of retention. Intents created at
+    /// `at(0)` have quotes that expire at `at(120)`, so they expire from `at(180)`.
+    async fn states() -> Vec<AppState> {
+        let mut states = testing::states().await;
+        for state in &mut states {
+            state.config.intent_expiry_grace_secs = 60;
+            state.config.intent_retention_hours = 1;
+        }
+        states
+    }
+
+    #[tokio::test]
+    async fn open_intents_expire_once_the_quote_and_grace_have_passed() {
+        for state in states().await {
+            insert(&state, "pi_created", &[], at(0)).await;
+            insert(&state, "pi_authorized", &[Authorized], at(10)).await;
+            insert(&state, "pi_submitted", &[Submitted], at(10)).await;
+            let mut fresh = testing::intent("pi_fresh", "alice", "bob_wallet", 1_000, at(100));
+            fresh.quote_expires_at = at(300);
+            state.store.insert_payment_intent(fresh).await.unwrap();
+
+            assert_eq!(sweep_intents(&state, at(179)).await.unwrap(), (0, 0));
+            assert_eq!(status(&state, "pi_created").await, Some(Created));
+
+            assert_eq!(sweep_intents(&state, at(180)).await.unwrap(), (2, 0));
+            for id in ["pi_created", "pi_authorized"] {
+                let intent = state.store.payment_intent(id).await.unwrap().unwrap();
+                assert_eq!(intent.status, Expired);
+                let event = intent.events.last().unwrap();
+                assert_eq!(event.at, at(180));
+                assert_eq!(
+                    event.reason.as_deref(),
+                    Some("quote expired before payment")
+                );
+            }
+            // A sent transaction is left to the reconciler, however old the quote.
+            assert_eq!(status(&state, "pi_submitted").await, Some(Submitted));
+            assert_eq!(status(&state, "pi_fresh").await, Some(Created));
+
+            assert_eq!(sweep_intents(&state, at(180)).await.unwrap(), (0, 0));
+            assert_eq!(sweep_intents(&state, at(360)).await.unwrap(), (1, 0));
+            assert_eq!(status(&state, "pi_fresh").await, Some(Expired));
+        }
+    }
+
+    #[tokio::test]
+    async fn expiry_releases_the_reserved_session_spend() {
+        for state in states().await {
+            put_session(&state, "ses_1", 2_000).await;
+            insert(&state, "pi_1", &[], at(0)).await;
+            authorize(&state, "pi_1", at(10)).await;
+            assert_eq!(used_today(&state).await, 3_000);
+
+            assert_eq!(sweep_intents(&state, at(180)).await.unwrap(), (1, 0));
+            assert_eq!(status(&state, "pi_1").await, Some(Expired));
+            assert_eq!(used_today(&state).await, 2_000);
+        }
+    }
+
+    #[tokio::test]
+    async fn expiry_leaves_a_replacement_session_alone() {
+        for state in states().await {
+            put_session(&state, "ses_1", 0).await;
+            insert(&state, "pi_1", &[], at(0)).await;
+            authorize(&state, "pi_1", at(10)).await;
+            put_session(&state, "ses_2", 500).await;
+
+            assert_eq!(sweep_intents(&state, at(180)).await.unwrap(), (1, 0));
+            assert_eq!(status(&state, "pi_1").await, Some(Expired));
+            assert_eq!(used_today(&state).await, 500);
+        }
+    }
+
+    #[tokio::test]
+    async fn only_unpaid_intents_are_purged_after_the_retention() {
+        for state in states().await {
+            let hour = chrono::Duration::hours(1);
+            insert(&state, "pi_cancelled", &[Cancelled], at(10)).await;
+            insert(&state, "pi_unsent", &[Submitted, Failed], at(10)).await;
+            insert(&state, "pi_late", &[Cancelled], at(20)).await;
+            insert(
+                &state,
+                "pi_finalized",
+                &[Submitted, Confirmed, Finalized],
+                at(10),
+            )
+            .await;
+            insert(&state, "pi_submitted", &[Submitted], at(10)).await;
+            // A transaction that landed and failed on chain is part of the payer's history.
+            let mut reverted = testing::intent("pi_reverted", "alice", "bob_wallet", 1_000, at(0));
+            reverted.signature = Some("reverted_signature".to_string());
+            lifecycle::transition(&mut reverted, Submitted, None, at(10)).unwrap();
+            lifecycle::transition(&mut reverted, Failed, None, at(10)).unwrap();
+            state.store.insert_payment_intent(reverted).await.unwrap();
+
+            assert_eq!(sweep_intents(&state, at(10) + hour).await.unwrap(), (0, 0));
+            assert_eq!(sweep_intents(&state, at(11) + hour).await.unwrap(), (0, 2));
+            assert_eq!(status(&state, "pi_cancelled").await, None);
+            assert_eq!(status(&state, "pi_unsent").await, None);
+            assert_eq!(status(&state, "pi_late").await, Some(Cancelled));
+
+            assert_eq!(sweep_intents(&state, at(21) + hour).await.unwrap(), (0, 1));
+            assert_eq!(status(&state, "pi_late").await, None);
+
+            // Paid, reverted and open intents are kept no matter how old they are.
+            assert_eq!(
+                sweep_intents(&state, at(0) + hour * 48).await.unwrap(),
+                (0, 0)
+            );
+            assert_eq!(status(&state, "pi_finalized").await, Some(Finalized));
+            assert_eq!(status(&state, "pi_reverted").await, Some(Failed));
+            assert_eq!(status(&state, "pi_submitted").await, Some(Submitted));
+        }
+    }
+
+    #[tokio::test]
+    async fn expired_intents_are_kept_for_the_retention_too() {
+        for state in states().await {
+            insert(&state, "pi_1", &[], at(0)).await;
+            let hour = chrono::Duration::hours(1);
+
+            assert_eq!(sweep_intents(&state, at(180)).await.unwrap(), (1, 0));
+            assert_eq!(sweep_intents(&state, at(180) + hour).await.unwrap(), (0, 0));
+            assert_eq!(status(&state, "pi_1").await, Some(Expired));
+            assert_eq!(sweep_intents(&state, at(181) + hour).await.unwrap(), (0, 1));
+            assert_eq!(status(&state, "pi_1").await, None);
+        }
+    }
+}
@@ -0,0 +1,412 @@
+//! Shared fixtures for in-crate tests.
+
+use crate::app::build_router;
+use crate::config::Config;
+use crate::events::IntentEvents;
+use crate::lifecycle::{self, IntentStatus};
+use crate::money::{Inr, TokenAmount};
+use crate::oracle::FixedOracle;
+use crate::registry::TokenRegistry;
+use crate::routes::handles::normalize_handle;
+use crate::solana::{MockRpc, Pubkey, SolanaRpc};
+use crate::state::{AppState, PaymentIntentRecord, SessionRecord, TokenFamilyRecord};
+use crate::store::{InMemoryStore, SqlStore, Store};
+use crate::tokens;
+use axum::body::{to_bytes, Body};
+use axum::http::{HeaderMap, Method, Request, StatusCode};
+use axum::Router;
+use chrono::{DateTime, Duration, Utc};
+use ed25519_dalek::{Signer, SigningKey};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+/// Defaults from the environment, with dev header auth on and fixed secrets.
+pub fn config() -> Config {
+    let mut config = Config::from_env();
+    config.dev_header_auth = true;
+    config.access_token_secret = "test-access-token-secret".to_string();
+    config.indexer_webhook_secret = Some("test-webhook-secret".to_string());
+    config.database_url = None;
+    config.solana_mock_ledger = None;
+    config
+}
+
+/// State over an in-memory store, fixed prices and a mock chain read from `ledger`.
+pub fn state_with(store: Arc<dyn Store>, ledger: &str) -> AppState {
+    let config = config();
+    let registry = TokenRegistry::from_config(&config).expect("bundled token registry");
+    AppState {
+        oracle: Arc::new(FixedOracle::parse(&config.oracle_fixed_rates).unwrap()),
+        registry: Arc::new(registry),
+        rpc: Arc::new(MockRpc::new(ledger.to_string())) as Arc<dyn SolanaRpc>,
+        events: IntentEvents::default(),
+        store,
+        config,
+    }
+}
+
+pub fn state() -> AppState {
+    state_with(
+        Arc::new(InMemoryStore::default()),
+        "/nonexistent/ledger.json",
+    )
+}
+
+/// The in-memory state and one over a fresh SQLite database, for behavior both stores share.
+pub async fn states() -> Vec<AppState> {
+    let sql = SqlStore::connect("sqlite::memory:")
+        .await
+        .expect("migrations apply to an empty database");
+    vec![
+        state(),
+        state_with(Arc::new(sql), "/nonexistent/ledger.json"),
+    ]
+}
+
+/// A valid address made of one repeated byte.
+pub fn key(byte: u8) -> Pubkey {
+    Pubkey::parse(&bs58::encode([byte; 32]).into_string()).unwrap()
+}
+
+/// A fixed instant `seconds` after an arbitrary epoch, whole micros so every store keeps it.
+pub fn at(seconds: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(1_760_000_000 + seconds, 0).unwrap()
+}
+
+/// A created SOL intent from `creator`'s wallet (`{creator}_wallet`) to `recipient`.
+pub fn intent(
+    id: &str,
+    creator: &str,
+    recipient: &str,
+    paise: u64,
+    created: DateTime<Utc>,
+) -> PaymentIntentRecord {
+    PaymentIntentRecord {
+        id: id.to_string(),
+        creator_user_id: creator.to_string(),
+        payer_wallet: format!("{creator}_wallet"),
+        recipient_wallet: recipient.to_string(),
+        recipient_handle: None,
+        inr_amount: Inr::from_paise(paise),
+        token_amount: TokenAmount::new(paise * 1_000, 9),
+        token: "SOL".to_string(),
+        quote_id: format!("q_{id}"),
+        quote_expires_at: created + Duration::minutes(2),
+        memo: None,
+        status: IntentStatus::Created,
+        mode: None,
+        session_id: None,
+        signature: None,
+        reference: format!("ref_{id}"),
+        transaction_served_at: None,
+        created_at: created,
+        events: vec![lifecycle::created_event(created)],
+    }
+}
+
+/// Stores alice's intent `id` to `bob_wallet`, created at `at(0)` and then moved through
+/// `statuses` at `when`.
+pub async fn insert(state: &AppState, id: &str, statuses: &[IntentStatus], when: DateTime<Utc>) {
+    let mut intent = intent(id, "alice", "bob_wallet", 1_000, at(0));
+    for status in statuses {
+        lifecycle::transition(&mut intent, *status, None, when).unwrap();
+    }
+    state.store.insert_payment_intent(intent).await.unwrap();
+}
+
+/// The intent's status, or `None` once it is gone.
+pub async fn status(state: &AppState, id: &str) -> Option<IntentStatus> {
+    let intent = state.store.payment_intent(id).await.unwrap();
+    intent.map(|intent| intent.status)
+}
+
+/// Opens `alice`'s session `id` on `alice_wallet` for the next hour, allowing 1,000 INR per
+/// payment and 5,000 INR a day, of which `used_paise` is already spent.
+pub async fn put_session(state: &AppState, id: &str, used_paise: u64) {
+    let session = SessionRecord {
+        id: id.to_string(),
+        wallet: "alice_wallet".to_string(),
+        device_id: "device".to_string(),
+        per_tx_limit_inr: Inr::from_paise(100_000),
+        daily_limit_inr: Inr::from_paise(500_000),
+        used_today_inr: Inr::from_paise(used_paise),
+        expires_at: Utc::now() + Duration::hours(1),
+        status: "active".to_string(),
+    };
+    state.store.put_session("alice", session).await.unwrap();
+}
+
+/// Authorizes `id` against alice's session at `when`, reserving its amount as the execute
+/// endpoint does.
+pub async fn authorize(state: &AppState, id: &str, when: DateTime<Utc>) {
+    state
+        .store
+        .spend_from_session(
+            "alice",
+            id,
+            Box::new(move |session, intent| {
+                lifecycle::transition(intent, IntentStatus::Authorized, None, when)?;
+                intent.session_id = Some(session.id.clone());
+                session.used_today_inr = session
+                    .used_today_inr
+                    .checked_add(intent.inr_amount)
+                    .unwrap();
+                Ok(())
+            }),
+        )
+        .await
+        .unwrap();
+}
+
+/// What alice's session has spent today, in paise.
+pub async fn used_today(state: &AppState) -> u64 {
+    let session = state.store.session("alice").await.unwrap().unwrap();
+    session.used_today_inr.paise()
+}
+
+/// `alice` paying from `alice_wallet` in session `ses_1`, and `bob` owning `bob_wallet`
+/// under the handle `bobby`.
+pub async fn payer_and_recipient() -> AppState {
+    let state = state();
+    for user in ["alice", "bob"] {
+        state
+            .store
+            .link_wallet(user, &format!("{user}_wallet"), at(0))
+            .await
+            .unwrap();
+    }
+    put_session(&state, "ses_1", 0).await;
+    state
+        .store
+        .claim_handle(&normalize_handle("bobby"), "bob_wallet")
+        .await
+        .unwrap();
+    state
+}
+
+/// Signs `user_id` in on a new token family and returns its bearer access token.
+pub async fn access_token(state: &AppState, user_id: &str, wallet: &str) -> String {
+    let family = TokenFamilyRecord {
+        id: Uuid::new_v4().simple().to_string(),
+        user_id: user_id.to_string(),
+        wallet: wallet.to_string(),
+        device_id: "device_1".to_string(),
+        current_hash: String::new(),
+        retired_hashes: Vec::new(),
+        expires_at: Utc::now() + Duration::days(1),
+        revoked_at: None,
+    };
+    state
+        .store
+        .insert_token_family(family.clone())
+        .await
+        .unwrap();
+    tokens::issue_access_token(
+        state.config.access_token_secret.as_bytes(),
+        Duration::minutes(15),
+        user_id,
+        wallet,
+        &family.id,
+    )
+    .unwrap()
+    .token
+}
+
+/// A wallet whose private key the test holds, derived from one repeated byte.
+pub struct Wallet {
+    key: SigningKey,
+    pub address: String,
+}
+
+impl Wallet {
+    pub fn new(seed: u8) -> Self {
+        let key = SigningKey::from_bytes(&[seed; 32]);
+        let address = bs58::encode(key.verifying_key().as_bytes()).into_string();
+        Self { key, address }
+    }
+
+    /// A base58 signature over `message`, as wallets return from `signMessage`.
+    pub fn sign(&self, message: &str) -> String {
+        bs58::encode(self.key.sign(message.as_bytes()).to_bytes()).into_string()
+    }
+}
+
+/// A request for `router`, signed in as `user` through dev header auth.
+pub struct Call {
+    request: Request<Body>,
+}
+
+impl Call {
+    pub fn new(method: Method, uri: &str) -> Self {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        Self { request }
+    }
+
+    pub fn post(uri: &str, body: Value) -> Self {
+        let mut call = Self::new(Method::POST, uri).header("content-type", "application/json");
+        *call.request.body_mut() = Body::from(body.to_string());
+        call
+    }
+
+    pub fn user(self, user_id: &str) -> Self {
+        self.header("x-user-id", user_id)
+    }
+
+    pub fn header(mut self, name: &'static str, value: &str) -> Self {
+        self.request
+            .headers_mut()
+            .insert(name, value.parse().unwrap());
+        self
+    }
+
+    /// Sends the request through the full router, middleware included.
+    pub async fn send(self, state: &AppState) -> (StatusCode, HeaderMap, Value) {
+        let (status, headers, body) = self.send_text(state).await;
+        let body = serde_json::from_str(&body).unwrap_or(Value::Null);
+        (status, headers, body)
+    }
+
+    /// Like [`Call::send`], for responses that are not JSON. Streams are read to their end;
+    /// binary bodies come back lossily decoded.
+    pub async fn send_text(self, state: &AppState) -> (StatusCode, HeaderMap, String) {
+        let router: Router = build_router(state.clone());
+        let response = router.oneshot(self.request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, headers, String::from_utf8_lossy(&body).into_owned())
+    }
+}
+
+/// A mock chain for `MockRpc`, kept in a temporary JSON file that is rewritten on every
+/// change and removed on drop.
+pub struct Ledger {
+    path: PathBuf,
+    transactions: Vec<Value>,
+}
+
+impl Ledger {
+    pub fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("monopay-ledger-{}.json", Uuid::new_v4()));
+        let ledger = Self {
+            path,
+            transactions: Vec::new(),
+        };
+        ledger.save();
+        ledger
+    }
+
+    pub fn path(&self) -> &str {
+        self.path.to_str().unwrap()
+    }
+
+    fn save(&self) {
+        let ledger = json!({ "transactions": self.transactions });
+        std::fs::write(&self.path, ledger.to_string()).unwrap();
+    }
+
+    /// Lands a transaction moving `lamports` from `payer` to `recipient` and touching
+    /// `reference`; returns its signature.
+    pub fn land_sol(
+        &mut self,
+        payer: &str,
+        recipient: &str,
+        reference: &str,
+        lamports: u64,
+        status: &str,
+        err: Option<Value>,
+    ) -> String {
+        let meta = json!({
+            "err": err,
+            "preBalances": [10_000_000_000u64, 5, 0, 1],
+            "postBalances": [10_000_000_000 - lamports, 5 + lamports, 0, 1],
+            "preTokenBalances": [],
+            "postTokenBalances": [],
+        });
+        self.land(
+            vec![
+                payer,
+                recipient,
+                reference,
+                "11111111111111111111111111111111",
+            ],
+            meta,
+            status,
+        )
+    }
+
+    /// Lands a transaction raising `recipient`'s balance of `mint` by `amount`.
+    pub fn land_token(
+        &mut self,
+        payer: &str,
+        recipient: &str,
+        reference: &str,
+        mint: &str,
+        amount: u64,
+        status: &str,
+    ) -> String {
+        let balance = |amount: u64| {
+            json!([{
+                "accountIndex": 3,
+                "mint": mint,
+                "owner": recipient,
+                "uiTokenAmount": { "amount": amount.to_string() },
+            }])
+        };
+        let meta = json!({
+            "err": null,
+            "preBalances": [10_000_000_000u64, 0, 0, 2_039_280],
+            "postBalances": [9_999_995_000u64, 0, 0, 2_039_280],
+            "preTokenBalances": balance(100),
+            "postTokenBalances": balance(100 + amount),
+        });
+        self.land(
+            vec![
+                payer,
+                recipient,
+                reference,
+                "RecipientTokenAccount1111111111111111111111",
+            ],
+            meta,
+            status,
+        )
+    }
+
+    fn land(&mut self, keys: Vec<&str>, meta: Value, status: &str) -> String {
+        let signature = format!("sig{}", self.transactions.len() + 1);
+        self.transactions.push(json!({
+            "confirmationStatus": status,
+            "transaction": {
+                "signatures": [signature],
+                "message": { "accountKeys": keys },
+            },
+            "meta": meta,
+        }));
+        self.save();
+        signature
+    }
+
+    /// Moves a landed transaction to another commitment.
+    pub fn set_status(&mut self, signature: &str, status: &str) {
+        let entry = self
+            .transactions
+            .iter_mut()
+            .find(|entry| entry.pointer("/transaction/signatures/0") == Some(&json!(signature)))
+            .expect("transaction was landed");
+        entry["confirmationStatus"] = json!(status);
+        self.save();
+    }
+}
+
+impl Drop for Ledger {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,332 @@
+use crate::error::AppError;
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+const ISSUER: &str = "monopay-gateway";
+const REFRESH_PREFIX: &str = "rt_";
+/// Audience of tokens that only open one intent's event stream.
+const EVENTS_AUDIENCE: &str = "payment-events";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccessClaims {
+    pub sub: String,
+    pub wallet: String,
+    pub iss: String,
+    pub iat: i64,
+    pub exp: i64,
+    pub jti: String,
+    /// Refresh-token family the access token was minted from.
+    pub fam: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EventsClaims {
+    sub: String,
+    /// The payment intent whose events the token may stream.
+    intent: String,
+    iss: String,
+    aud: String,
+    exp: i64,
+}
+
+pub struct IssuedToken {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Mints an HS256 access token for the given user and wallet.
+pub fn issue_access_token(
+    secret: &[u8],
+    ttl: Duration,
+    user_id: &str,
+    wallet: &str,
+    family_id: &str,
+) -> Result<IssuedToken, AppError> {
+    let issued_at = Utc::now();
+    let expires_at = issued_at + ttl;
+    let claims = AccessClaims {
+        sub: user_id.to_string(),
+        wallet: wallet.to_string(),
+        iss: ISSUER.to_string(),
+        iat: issued_at.timestamp(),
+        exp: expires_at.timestamp(),
+        jti: Uuid::new_v4().simple().to_string(),
+        fam: family_id.to_string(),
+    };
+
+    let token = jsonwebtoken::encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(secret),
+    )
+    .map_err(|_| AppError::internal("failed to sign access token"))?;
+
+    Ok(IssuedToken { token, expires_at })
+}
+
+pub fn decode_access_token(secret: &[u8], token: &str) -> Result<AccessClaims, AppError> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_issuer(&[ISSUER]);
+    validation.leeway = 0;
+
+    jsonwebtoken::decode::<AccessClaims>(token, &DecodingKey::from_secret(secret), &validation)
+        .map(|data| data.claims)
+        .map_err(|err| match err.kind() {
+            jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
+                AppError::unauthorized("access token expired")
+            }
+            _ => AppError::unauthorized("invalid access token"),
+        })
+}
+
+/// Mints a token that lets `user_id` open the event stream of one intent. It is meant for the
+/// query string, so it carries no other rights and lives only briefly.
+pub fn issue_events_token(
+    secret: &[u8],
+    ttl: Duration,
+    user_id: &str,
+    intent_id: &str,
+) -> Result<IssuedToken, AppError> {
+    let expires_at = Utc::now() + ttl;
+    let claims = EventsClaims {
+        sub: user_id.to_string(),
+        intent: intent_id.to_string(),
+        iss: ISSUER.to_string(),
+        aud: EVENTS_AUDIENCE.to_string(),
+        exp: expires_at.timestamp(),
+    };
+
+    let token = jsonwebtoken::encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(&events_key(secret)),
+    )
+    .map_err(|_| AppError::internal("failed to sign events token"))?;
+
+    Ok(IssuedToken { token, expires_at })
+}
+
+/// The user an events token was issued to, if it is valid for `intent_id`.
+pub fn decode_events_token(
+    secret: &[u8],
+    token: &str,
+    intent_id: &str,
+) -> Result<String, AppError> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_issuer(&[ISSUER]);
+    validation.set_audience(&[EVENTS_AUDIENCE]);
+    validation.leeway = 0;
+
+    let key = DecodingKey::from_secret(&events_key(secret));
+    let claims = jsonwebtoken::decode::<EventsClaims>(token, &key, &validation)
+        .map(|data| data.claims)
+        .map_err(|err| match err.kind() {
+            jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
+                AppError::unauthorized("events token expired")
+            }
+            _ => AppError::unauthorized("invalid events token"),
+        })?;
+    if claims.intent != intent_id {
+        return Err(AppError::unauthorized(
+            "events token is for another payment intent",
+        ));
+    }
+    Ok(claims.sub)
+}
+
+/// Events tokens are signed with a key derived from the access token secret, so neither kind
+/// of token verifies as the other.
+fn events_key(secret: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(EVENTS_AUDIENCE);
+    hasher.update(secret);
+    hasher.finalize().to_vec()
+}
+
+/// Opaque refresh token of the form `rt_<familyId>.<secret>`; only the secret's hash is stored.
+pub fn new_refresh_token(family_id: &str) -> (String, String) {
+    let mut secret = [0u8; 32];
+    OsRng.fill_bytes(&mut secret);
+    let secret = bs58::encode(secret).into_string();
+    let hash = hash_refresh_secret(&secret);
+    (format!("{REFRESH_PREFIX}{family_id}.{secret}"), hash)
+}
+
+/// Splits a refresh token into its family id and secret hash.
+pub fn parse_refresh_token(token: &str) -> Result<(String, String), AppError> {
+    token
+        .trim()
+        .strip_prefix(REFRESH_PREFIX)
+        .and_then(|rest| rest.split_once('.'))
+        .filter(|(family, secret)| !family.is_empty() && !secret.is_empty())
+        .map(|(family, secret)| (family.to_string(), hash_refresh_secret(secret)))
+        .ok_or_else(|| AppError::unauthorized("invalid refresh token"))
+}
+
+fn hash_refresh_secret(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use serde_json::json;
+
+    const SECRET: &[u8] = b"test-access-token-secret";
+
+    fn issue(ttl: Duration) -> String {
+        issue_access_token(SECRET, ttl, "usr_1", "wallet_1", "fam_1")
+            .unwrap()
+            .token
+    }
+
+    fn rejection(secret: &[u8], token: &str) -> String {
+        match decode_access_token(secret, token) {
+            Err(AppError::Unauthorized(message)) => message,
+            other => panic!("expected a rejection, got {other:?}"),
+        }
+    }
+
+    /// Signs arbitrary claims with the gateway secret, as a forger holding it could.
+    fn sign(claims: serde_json::Value) -> String {
+        jsonwebtoken::encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(SECRET),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn access_tokens_round_trip_their_claims() {
+        let issued =
+            issue_access_token(SECRET, Duration::minutes(15), "usr_1", "wallet_1", "fam_1")
+                .unwrap();
+        let claims = decode_access_token(SECRET, &issued.token).unwrap();
+        assert_eq!(claims.sub, "usr_1");
+        assert_eq!(claims.wallet, "wallet_1");
+        assert_eq!(claims.fam, "fam_1");
+        assert_eq!(claims.iss, ISSUER);
+        assert_eq!(claims.exp, issued.expires_at.timestamp());
+        assert_eq!(claims.exp - claims.iat, 15 * 60);
+    }
+
+    #[test]
+    fn expired_tokens_are_rejected_without_leeway() {
+        let token = issue(Duration::seconds(-1));
+        assert_eq!(rejection(SECRET, &token), "access token expired");
+    }
+
+    #[test]
+    fn tokens_signed_with_another_secret_are_rejected() {
+        let token = issue(Duration::minutes(15));
+        assert_eq!(rejection(b"another-secret", &token), "invalid access token");
+
+        let (header, rest) = token.split_once('.').unwrap();
+        let (_, signature) = rest.split_once('.').unwrap();
+        let forged_claims = URL_SAFE_NO_PAD.encode(
+            json!({
+                "sub": "usr_admin", "wallet": "wallet_1", "iss": ISSUER,
+                "iat": Utc::now().timestamp(), "exp": Utc::now().timestamp() + 60,
+                "jti": "jti_1", "fam": "fam_1",
+            })
+            .to_string(),
+        );
+        let tampered = format!("{header}.{forged_claims}.{signature}");
+        assert_eq!(rejection(SECRET, &tampered), "invalid access token");
+    }
+
+    #[test]
+    fn tokens_for_another_issuer_or_audience_are_rejected() {
+        let now = Utc::now().timestamp();
+        let claims = json!({
+            "sub": "usr_1", "wallet": "wallet_1", "iss": ISSUER,
+            "iat": now, "exp": now + 60, "jti": "jti_1", "fam": "fam_1",
+        });
+        assert!(decode_access_token(SECRET, &sign(claims.clone())).is_ok());
+
+        let mut other_issuer = claims.clone();
+        other_issuer["iss"] = json!("someone-else");
+        assert_eq!(
+            rejection(SECRET, &sign(other_issuer)),
+            "invalid access token"
+        );
+
+        let mut other_audience = claims;
+        other_audience["aud"] = json!("payment-events");
+        assert_eq!(
+            rejection(SECRET, &sign(other_audience)),
+            "invalid access token"
+        );
+    }
+
+    #[test]
+    fn refresh_tokens_carry_their_family_and_store_only_a_hash() {
+        let (token, hash) = new_refresh_token("fam_1");
+        assert!(token.starts_with("rt_fam_1."));
+        assert!(!token.contains(&hash));
+        assert_eq!(hash.len(), 64);
+        assert_eq!(
+            parse_refresh_token(&format!(" {token} ")).unwrap(),
+            ("fam_1".to_string(), hash)
+        );
+
+        let (other, other_hash) = new_refresh_token("fam_1");
+        assert_ne!(other, token);
+        assert_eq!(parse_refresh_token(&other).unwrap().1, other_hash);
+    }
+
+    #[test]
+    fn malformed_refresh_tokens_are_rejected() {
+        for token in [
+            "",
+            "rt_",
+            "rt_fam_1",
+            "rt_.secret",
+            "rt_fam_1.",
+            "xx_fam_1.secret",
+        ] {
+            assert!(parse_refresh_token(token).is_err(), "{token:?}");
+        }
+    }
+
+    #[test]
+    fn events_tokens_are_bound_to_one_intent() {
+        let issued = issue_events_token(SECRET, Duration::minutes(5), "usr_1", "pi_1").unwrap();
+        assert_eq!(
+            decode_events_token(SECRET, &issued.token, "pi_1").unwrap(),
+            "usr_1"
+        );
+        assert!(decode_events_token(SECRET, &issued.token, "pi_2").is_err());
+        assert!(decode_events_token(b"another-secret", &issued.token, "pi_1").is_err());
+    }
+
+    #[test]
+    fn access_and_events_tokens_do_not_verify_as_each_other() {
+        let events = issue_events_token(SECRET, Duration::minutes(5), "usr_1", "pi_1")
+            .unwrap()
+            .token;
+        assert_eq!(rejection(SECRET, &events), "invalid access token");
+
+        let access = issue(Duration::minutes(5));
+        assert!(decode_events_token(SECRET, &access, "pi_1").is_err());
+        // Not even with events claims, as long as the access token secret signed them.
+        let forged = sign(json!({
+            "sub": "usr_1",
+            "intent": "pi_1",
+            "iss": ISSUER,
+            "aud": EVENTS_AUDIENCE,
+            "exp": (Utc::now() + Duration::minutes(5)).timestamp(),
+        }));
+        assert!(decode_events_token(SECRET, &forged, "pi_1").is_err());
+    }
+}
@@ -1,29 +1,118 @@
+import bs58 from "bs58";
 import type {
+  AuthTokensResponse,
+  CancelReason,
   ExecuteIntentRequest,
   ExecuteIntentResponse,
   HandleResponse,
+  NonceResponse,
+  PaymentEvent,
+  PaymentEventsToken,
   PaymentIntentCreateRequest,
   PaymentIntentCreateResponse,
+  PaymentIntentListParams,
+  PaymentIntentListResponse,
   PaymentIntentStatusResponse,
+  PaymentReceipt,
   PinVerifyResponse,
+  QuoteCreateRequest,
+  QuotePreviewResponse,
   QuoteResponse,
   SessionResponse,
+  SolanaPayPayload,
+  TokenInfo,
   UpsertHandleRequest,
 } from "../types/api";
 
 type ApiClientOptions = {
   baseUrl: string;
+  accessToken?: string;
+  refreshToken?: string;
+  deviceId?: string;
+  /** Called whenever the client rotates tokens so callers can persist them. */
+  onTokens?: (tokens: AuthTokensResponse) => void;
+  /** Dev only: gateways started with MONOPAY_DEV_HEADER_AUTH trust this header. */
   userId?: string;
 };
 
 export class ApiClient {
+  private refreshing?: Promise<AuthTokensResponse>;
+
   constructor(private readonly options: ApiClientOptions) {}
 
-  private async request<T>(path: string, init?: RequestInit): Promise<T> {
+  /**
+   * Exchanges the stored refresh token for a new token pair, so an expired
+   * access token does not force the user to sign with their wallet again.
+   */
+  async refreshTokens(): Promise<AuthTokensResponse> {
+    const { refreshToken, deviceId } = this.options;
+    if (!refreshToken || !deviceId) {
+      throw new Error("No refresh token available, wallet sign-in required");
+    }
+    if (!this.refreshing) {
+      this.refreshing = this.request<AuthTokensResponse>("/v1/auth/refresh", {
+        method: "POST",
+        body: JSON.stringify({ refreshToken, deviceId }),
+      }, false)
+        .then((tokens) => {
+          this.options.accessToken = tokens.accessToken;
+          this.options.refreshToken = tokens.refreshToken;
+          this.options.onTokens?.(tokens);
+          return tokens;
+        })
+        .finally(() => {
+          this.refreshing = undefined;
+        });
+    }
+    return this.refreshing;
+  }
+
+  /**
+   * Signs in with `wallet`: fetches a challenge, has `sign` sign its message
+   * with the wallet key and exchanges the signature for a token pair.
+   */
+  async signIn(
+    wallet: string,
+    deviceId: string,
+    sign: (message: Uint8Array) => Uint8Array | Promise<Uint8Array>,
+  ): Promise<AuthTokensResponse> {
+    const challenge = await this.request<NonceResponse>("/v1/auth/nonce", {
+      method: "POST",
+      body: JSON.stringify({ wallet }),
+    }, false);
+    const signature = await sign(new TextEncoder().encode(challenge.message));
+    const tokens = await this.request<AuthTokensResponse>("/v1/auth/verify", {
+      method: "POST",
+      body: JSON.stringify({
+        wallet,
+        message: challenge.message,
+        signature: bs58.encode(signature),
+        deviceId,
+      }),
+    }, false);
+    this.options.accessToken = tokens.accessToken;
+    this.options.refreshToken = tokens.refreshToken;
+    this.options.deviceId = deviceId;
+    this.options.onTokens?.(tokens);
+    return tokens;
+  }
+
+  async logout(allDevices = false): Promise<void> {
+    await this.request("/v1/auth/logout", {
+      method: "POST",
+      body: JSON.stringify({ allDevices }),
+    });
+    this.options.accessToken = undefined;
+    this.options.refreshToken = undefined;
+  }
+
+  private async request<T>(path: string, init?: RequestInit, retryOnUnauthorized = true): Promise<T> {
     const headers: Record<string, string> = {
       "Content-Type": "application/json",
     };
-    if (this.options.userId) {
+    if (this.options.accessToken) {
+      headers["Authorization"] = `Bearer ${this.options.accessToken}`;
+    } else if (this.options.userId) {
       headers["x-user-id"] = this.options.userId;
     }
     
@@ -44,6 +133,11 @@ export class ApiClient {
 
       clearTimeout(timeoutId);
 
+      if (response.status === 401 && retryOnUnauthorized && this.options.refreshToken) {
+        await this.refreshTokens();
+        return this.request<T>(path, init, false);
+      }
+
       if (!response.ok) {
         let message = "";
         try {
@@ -81,21 +175,53 @@ export class ApiClient {
     return this.request<SessionResponse>("/v1/sessions/current");
   }
 
-  async getUsdcQuote(inr: number): Promise<QuoteResponse> {
-    return this.request<QuoteResponse>(`/v1/quotes/usdc?inr=${inr}`);
+  async listTokens(): Promise<TokenInfo[]> {
+    const response = await this.request<{ tokens: TokenInfo[] }>("/v1/tokens");
+    return response.tokens;
   }
 
-  async createPaymentIntent(payload: PaymentIntentCreateRequest): Promise<PaymentIntentCreateResponse> {
+  /** Prices `token` without locking anything, either from an INR amount or from a token amount. */
+  async previewQuote(token: string, amount: { inr: string | number } | { amount: string }): Promise<QuotePreviewResponse> {
+    const query = "inr" in amount
+      ? `inr=${encodeURIComponent(String(amount.inr))}`
+      : `amount=${encodeURIComponent(amount.amount)}`;
+    return this.request<QuotePreviewResponse>(`/v1/quotes/${token}?${query}`);
+  }
+
+  /** Locks a quote for a payment intent; requires a signed-in client. */
+  async lockQuote(payload: QuoteCreateRequest): Promise<QuoteResponse> {
+    return this.request<QuoteResponse>("/v1/quotes", {
+      method: "POST",
+      body: JSON.stringify(payload),
+    });
+  }
+
+  /**
+   * `idempotencyKey` identifies the payment, not the request: generate it once and
+   * reuse it on retries so the gateway replays the original response instead of
+   * creating a second intent.
+   */
+  async createPaymentIntent(
+    payload: PaymentIntentCreateRequest,
+    idempotencyKey: string,
+  ): Promise<PaymentIntentCreateResponse> {
     return this.request<PaymentIntentCreateResponse>("/v1/payment-intents", {
       method: "POST",
       body: JSON.stringify(payload),
+      headers: { "Idempotency-Key": idempotencyKey },
     });
   }
 
-  async executePaymentIntent(id: string, payload: ExecuteIntentRequest): Promise<ExecuteIntentResponse> {
+  /** Reuse the same `idempotencyKey` on retries so the session limit is only charged once. */
+  async executePaymentIntent(
+    id: string,
+    payload: ExecuteIntentRequest,
+    idempotencyKey: string,
+  ): Promise<ExecuteIntentResponse> {
     return this.request<ExecuteIntentResponse>(`/v1/payment-intents/${id}/execute`, {
       method: "POST",
       body: JSON.stringify(payload),
+      headers: { "Idempotency-Key": idempotencyKey },
     });
   }
 
@@ -103,14 +229,111 @@ export class ApiClient {
     return this.request<PaymentIntentStatusResponse>(`/v1/payment-intents/${id}`);
   }
 
-  async registerHandle(payload: UpsertHandleRequest): Promise<HandleResponse> {
+  /** One page of the user's sent and received payments. */
+  async listPaymentIntents(params: PaymentIntentListParams = {}): Promise<PaymentIntentListResponse> {
+    const query = Object.entries(params)
+      .filter(([, value]) => value !== undefined)
+      .map(([key, value]) => {
+        const text = Array.isArray(value) ? value.join(",") : String(value);
+        return `${key}=${encodeURIComponent(text)}`;
+      })
+      .join("&");
+    return this.request<PaymentIntentListResponse>(`/v1/payment-intents?${query}`);
+  }
+
+  /** Works for payments sent to the user as well as their own. */
+  async getPaymentReceipt(id: string): Promise<PaymentReceipt> {
+    return this.request<PaymentReceipt>(`/v1/payment-intents/${id}/receipt`);
+  }
+
+  /** Abandons an intent no transaction was sent for yet. */
+  async cancelPaymentIntent(
+    id: string,
+    idempotencyKey: string,
+    reason: CancelReason = "payer_abandoned",
+  ): Promise<PaymentIntentStatusResponse> {
+    return this.request<PaymentIntentStatusResponse>(`/v1/payment-intents/${id}/cancel`, {
+      method: "POST",
+      body: JSON.stringify({ reason }),
+      headers: { "Idempotency-Key": idempotencyKey },
+    });
+  }
+
+  /**
+   * Short-lived token for opening an intent's event stream from clients
+   * that cannot set headers, such as a browser `EventSource`; pass it as
+   * `?token=`.
+   */
+  async createPaymentEventsToken(id: string): Promise<PaymentEventsToken> {
+    return this.request<PaymentEventsToken>(`/v1/payment-intents/${id}/events/token`, {
+      method: "POST",
+    });
+  }
+
+  /**
+   * Streams an intent's status events over a WebSocket, starting after
+   * `lastEventId` or from the beginning. The server closes the socket once
+   * the intent is terminal. Returns a function that stops watching.
+   */
+  watchPaymentIntent(
+    id: string,
+    onEvent: (event: PaymentEvent) => void,
+    lastEventId?: string,
+  ): () => void {
+    const headers: Record<string, string> = {};
+    if (this.options.accessToken) {
+      headers["Authorization"] = `Bearer ${this.options.accessToken}`;
+    } else if (this.options.userId) {
+      headers["x-user-id"] = this.options.userId;
+    }
+    const query = lastEventId ? `?lastEventId=${encodeURIComponent(lastEventId)}` : "";
+    const url = `${this.options.baseUrl.replace(/^http/, "ws")}/v1/payment-intents/${id}/ws${query}`;
+    // React Native's WebSocket takes request headers as a third argument.
+    const socket: WebSocket = new (WebSocket as any)(url, undefined, { headers });
+    socket.onmessage = (message) => onEvent(JSON.parse(message.data) as PaymentEvent);
+    return () => socket.close();
+  }
+
+  /** Claims a handle for a wallet linked to the signed-in user; reuse `idempotencyKey` on retries. */
+  async registerHandle(
+    payload: UpsertHandleRequest,
+    idempotencyKey: string,
+  ): Promise<HandleResponse> {
     return this.request<HandleResponse>("/v1/handles", {
       method: "POST",
       body: JSON.stringify(payload),
+      headers: { "Idempotency-Key": idempotencyKey },
     });
   }
 
   async resolveHandle(handle: string): Promise<HandleResponse> {
     return this.request<HandleResponse>(`/v1/handles/${handle}`);
   }
+
+  async getPaymentIntentSolanaPay(
+    id: string,
+    kind: "transfer" | "transaction" = "transfer",
+  ): Promise<SolanaPayPayload> {
+    return this.request<SolanaPayPayload>(`/v1/payment-intents/${id}/solana-pay?kind=${kind}`);
+  }
+
+  /** Solana Pay request paying `handle`; omit `amount` to let the payer choose. */
+  async getHandleSolanaPay(
+    handle: string,
+    options: { token?: string; amount?: string; message?: string; memo?: string } = {},
+  ): Promise<SolanaPayPayload> {
+    const query = Object.entries(options)
+      .filter(([, value]) => value !== undefined)
+      .map(([key, value]) => `${key}=${encodeURIComponent(value as string)}`)
+      .join("&");
+    return this.request<SolanaPayPayload>(`/v1/handles/${handle}/solana-pay?${query}`);
+  }
+
+  /** Decodes and validates a scanned `solana:` URL. */
+  async parseSolanaPayUrl(url: string): Promise<SolanaPayPayload> {
+    return this.request<SolanaPayPayload>("/v1/solana-pay/parse", {
+      method: "POST",
+      body: JSON.stringify({ url }),
+    });
+  }
 }
@@ -1,11 +1,12 @@
 import React, { createContext, useContext, useState, useCallback, useEffect, ReactNode } from 'react';
 import * as SecureStore from 'expo-secure-store';
+import * as Crypto from 'expo-crypto';
+import { ed25519 } from '@noble/curves/ed25519';
 import { Connection, PublicKey, Keypair, LAMPORTS_PER_SOL } from '@solana/web3.js';
 import { Buffer } from 'buffer';
 import bs58 from 'bs58';
 import { WalletContextState } from '../types/state';
 import { usePin } from './PinContext';
-import { PriceService } from '../utils/prices';
 import { ApiClient } from '../api/client';
 import { API_BASE_URL } from '../config';
 
@@ -26,6 +27,16 @@ const MultiWalletContext = createContext<MultiWalletContextState | undefined>(un
 
 const WALLETS_STORAGE_KEY = 'monopay_wallets_list';
 const ACTIVE_WALLET_KEY = 'monopay_active_wallet';
+const DEVICE_ID_KEY = 'monopay_device_id';
+
+/** Stable per-install id the gateway binds refresh tokens to. */
+async function getDeviceId(): Promise<string> {
+  const saved = await SecureStore.getItemAsync(DEVICE_ID_KEY);
+  if (saved) return saved;
+  const deviceId = Crypto.randomUUID();
+  await SecureStore.setItemAsync(DEVICE_ID_KEY, deviceId);
+  return deviceId;
+}
 
 export function WalletProvider({ children }: { children: ReactNode }) {
   const [publicKey, setPublicKey] = useState<PublicKey | null>(null);
@@ -41,12 +52,18 @@ export function WalletProvider({ children }: { children: ReactNode }) {
     if (!publicKey) return;
     try {
       const connection = new Connection("https://api.devnet.solana.com", "confirmed");
-      const [bal, price] = await Promise.all([
+      const quoteClient = new ApiClient({ baseUrl: API_BASE_URL });
+      const [bal, quote] = await Promise.all([
         connection.getBalance(publicKey),
-        PriceService.getSolPriceInInr()
+        quoteClient.previewQuote("SOL", { amount: "1" }).catch((e) => {
+          console.warn("Failed to fetch SOL quote", e);
+          return null;
+        })
       ]);
       setBalance(bal / LAMPORTS_PER_SOL);
-      setSolPrice(price);
+      if (quote) {
+        setSolPrice(parseFloat(quote.rate));
+      }
       
       // Fetch Transactions more sparingly to avoid 429s
       setIsLoadingTransactions(true);
@@ -137,10 +154,17 @@ export function WalletProvider({ children }: { children: ReactNode }) {
 
       const address = keypair.publicKey.toBase58();
 
-      // Register handle if provided
+      // Register handle if provided; the gateway only lets a signed-in owner of the wallet claim one
       if (handle) {
-        const client = new ApiClient({ baseUrl: API_BASE_URL });
-        await client.registerHandle({ handle, wallet: address });
+        const client = new ApiClient({
+          baseUrl: API_BASE_URL,
+          onTokens: (tokens) => SecureStore.setItemAsync(`tokens_${address}`, JSON.stringify(tokens)),
+        });
+        await client.signIn(address, await getDeviceId(), (message) =>
+          ed25519.sign(message, keypair.secretKey.slice(0, 32)),
+        );
+        // One key for this claim, so a retried request is replayed rather than applied twice
+        await client.registerHandle({ handle, wallet: address }, Crypto.randomUUID());
       }
 
       const secretBase64 = Buffer.from(keypair.secretKey).toString('base64');
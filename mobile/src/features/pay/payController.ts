@@ -3,13 +3,11 @@ import {
   PublicKey, 
   Transaction, 
   SystemProgram, 
-  LAMPORTS_PER_SOL, 
   sendAndConfirmTransaction,
   Keypair
 } from "@solana/web3.js";
 import type { ApiClient } from "../../api/client";
 import { validatePin } from "../pin/pinPolicy";
-import { PriceService } from "../../utils/prices";
 
 const DEVNET_RPC = "https://api.devnet.solana.com";
 
@@ -31,6 +29,12 @@ export type PayResult =
       message: string;
     };
 
+/** Converts a decimal token amount string such as "0.0125" into base units without float rounding. */
+function toBaseUnits(amount: string, decimals: number): number {
+  const [whole, fraction = ""] = amount.split(".");
+  return Number(BigInt(whole + fraction.padEnd(decimals, "0").slice(0, decimals)));
+}
+
 export async function runUpiLikePayFlow(client: ApiClient, input: PayInput): Promise<PayResult> {
   try {
     const connection = new Connection(DEVNET_RPC, "confirmed");
@@ -60,15 +64,15 @@ export async function runUpiLikePayFlow(client: ApiClient, input: PayInput): Pro
       }
     }
 
-    // 2. Convert INR to SOL using real market rates
-    const solAmount = await PriceService.inrToSol(input.inrAmount);
+    // 2. Convert INR to SOL at the gateway's quoted rate
+    const quote = await client.previewQuote("SOL", { inr: input.inrAmount.toFixed(2) });
 
     // 3. Build Transaction
     const transaction = new Transaction().add(
       SystemProgram.transfer({
         fromPubkey: input.senderKeypair.publicKey,
         toPubkey: recipientPubkey,
-        lamports: Math.floor(solAmount * LAMPORTS_PER_SOL),
+        lamports: toBaseUnits(quote.tokenAmount, 9),
       })
     );
 
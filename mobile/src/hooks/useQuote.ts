@@ -1,5 +1,6 @@
 import { useState, useEffect } from "react";
-import { PriceService } from "../utils/prices";
+import { ApiClient } from "../api/client";
+import { API_BASE_URL } from "../config";
 
 export function useQuote(inrAmount: string) {
   const [quote, setQuote] = useState<{sol: string, rate: string} | null>(null);
@@ -15,12 +16,12 @@ export function useQuote(inrAmount: string) {
     const calculate = async () => {
       setIsLoading(true);
       try {
-        const priceInInr = await PriceService.getSolPriceInInr();
-        const solAmount = (amount / priceInInr).toFixed(6);
-        
+        const client = new ApiClient({ baseUrl: API_BASE_URL });
+        const response = await client.previewQuote("SOL", { inr: amount.toFixed(2) });
+
         setQuote({
-          sol: solAmount,
-          rate: priceInInr.toLocaleString(undefined, { maximumFractionDigits: 2 })
+          sol: response.tokenAmount,
+          rate: parseFloat(response.rate).toLocaleString(undefined, { maximumFractionDigits: 2 })
         });
       } catch (e) {
         console.error("Quote calculation failed", e);
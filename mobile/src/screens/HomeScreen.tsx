@@ -27,7 +27,6 @@ import { useContacts } from "../context/ContactContext";
 import { QRScanner } from "../components/QRScanner";
 import { useSafeAreaInsets } from "react-native-safe-area-context";
 import { Connection, LAMPORTS_PER_SOL } from "@solana/web3.js";
-import { PriceService } from "../utils/prices";
 
 const { width } = Dimensions.get('window');
 
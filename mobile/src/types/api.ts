@@ -1,3 +1,19 @@
+export type AuthTokensResponse = {
+  accessToken: string;
+  expiresAt: string;
+  refreshToken: string;
+  refreshExpiresAt: string;
+  userId: string;
+};
+
+/** Sign-in challenge; the wallet signs `message` as UTF-8 bytes. */
+export type NonceResponse = {
+  nonce: string;
+  expiresAt: string;
+  message: string;
+  messageBase64: string;
+};
+
 export type PinVerifyResponse = {
   verified: boolean;
   pinToken: string;
@@ -8,26 +24,60 @@ export type SessionResponse = {
   id: string;
   status: "active" | "expired" | "revoked";
   wallet: string;
-  perTxLimitInr: number;
-  dailyLimitInr: number;
-  remainingTodayInr: number;
+  /** Decimal strings with two fractional digits, e.g. "1000.00". */
+  perTxLimitInr: string;
+  dailyLimitInr: string;
+  remainingTodayInr: string;
   expiresAt: string;
 };
 
+export type TokenInfo = {
+  symbol: string;
+  /** `null` for native SOL. */
+  mint: string | null;
+  tokenProgram: string | null;
+  decimals: number;
+  cluster: string;
+  enabled: boolean;
+  explorerUrl: string;
+};
+
+export type QuotePreviewResponse = {
+  token: string;
+  inr: string;
+  tokenAmount: string;
+  rate: string;
+  source: string;
+  asOf: string;
+};
+
+/** Exactly one of `inr` and `amount`. */
+export type QuoteCreateRequest = {
+  token: string;
+  maxSlippageBps?: number;
+} & ({ inr: string | number } | { amount: string });
+
 export type QuoteResponse = {
+  quoteId: string;
+  token: string;
   inr: string;
-  usdc: string;
+  tokenAmount: string;
   rate: string;
   source: string;
   asOf: string;
   expiresAt: string;
+  maxSlippageBps: number;
 };
 
 export type PaymentIntentCreateRequest = {
   recipientHandle: string;
-  inrAmount: number;
+  /** At most two decimal places; sent as a string to avoid float rounding. */
+  inrAmount: string | number;
   token: "USDC" | "SOL";
+  /** Written on chain, so public; at most 256 UTF-8 bytes. */
   memo?: string;
+  /** Locked quote to pay at; must match token and inrAmount. */
+  quoteId?: string;
 };
 
 export type PaymentIntentCreateResponse = {
@@ -37,8 +87,10 @@ export type PaymentIntentCreateResponse = {
   inrAmount: string;
   tokenAmount: string;
   token: string;
+  quoteId: string;
   quoteExpiresAt: string;
   reference: string;
+  memo: string | null;
 };
 
 export type ExecuteIntentRequest = {
@@ -46,20 +98,130 @@ export type ExecuteIntentRequest = {
   sessionId: string;
 };
 
+export type PaymentIntentStatus =
+  | "created"
+  | "authorized"
+  | "submitted"
+  | "confirmed"
+  | "finalized"
+  | "failed"
+  | "expired"
+  | "cancelled";
+
+export type PaymentEvent = {
+  id: string;
+  previousStatus: PaymentIntentStatus | null;
+  status: PaymentIntentStatus;
+  reason: string | null;
+  signature: string | null;
+  at: string;
+};
+
+export type PaymentEventsToken = {
+  token: string;
+  expiresAt: string;
+};
+
 export type ExecuteIntentResponse = {
   id: string;
-  status: string;
+  status: PaymentIntentStatus;
   mode: "session_fast_path" | "wallet_fallback_path" | string;
-  signature: string;
-  explorerUrl: string;
+  transactionUrl: string;
+  memo: string | null;
 };
 
+export type CancelReason =
+  | "payer_abandoned"
+  | "wrong_recipient"
+  | "wrong_amount"
+  | "duplicate"
+  | "other";
+
 export type PaymentIntentStatusResponse = {
   id: string;
-  status: string;
+  status: PaymentIntentStatus;
   mode: string;
+  memo: string | null;
   signature?: string;
   explorerUrl?: string;
+  events: PaymentEvent[];
+};
+
+export type PaymentDirection = "sent" | "received";
+
+export type PaymentIntentSummary = {
+  id: string;
+  direction: PaymentDirection;
+  status: PaymentIntentStatus;
+  token: string;
+  tokenAmount: string;
+  inrAmount: string;
+  payerWallet: string;
+  recipientWallet: string;
+  /** Handle of the other side, if it has one. */
+  counterpartyHandle: string | null;
+  memo: string | null;
+  signature: string | null;
+  createdAt: string;
+};
+
+/** An intent as its payer or recipient sees it. */
+export type PaymentReceipt = {
+  id: string;
+  direction: PaymentDirection;
+  status: PaymentIntentStatus;
+  token: string;
+  tokenAmount: string;
+  inrAmount: string;
+  payerWallet: string;
+  payerHandle: string | null;
+  recipientWallet: string;
+  recipientHandle: string | null;
+  memo: string | null;
+  signature: string | null;
+  explorerUrl: string | null;
+  createdAt: string;
+  events: PaymentEvent[];
+};
+
+export type PaymentIntentListParams = {
+  limit?: number;
+  /** `nextCursor` of the previous page. */
+  cursor?: string;
+  direction?: PaymentDirection | "all";
+  status?: PaymentIntentStatus[];
+  token?: string;
+  /** Handle of the other side of the payment. */
+  counterparty?: string;
+  /** RFC 3339; inclusive. */
+  from?: string;
+  /** RFC 3339; exclusive. */
+  to?: string;
+  minInr?: string;
+  maxInr?: string;
+  sort?: "newest" | "oldest" | "largest" | "smallest";
+};
+
+export type PaymentIntentListResponse = {
+  items: PaymentIntentSummary[];
+  nextCursor: string | null;
+};
+
+/** A Solana Pay URL and its decoded fields. */
+export type SolanaPayPayload = {
+  kind: "transfer" | "transaction";
+  url: string;
+  /** https link of a transaction request. */
+  link: string | null;
+  recipient: string | null;
+  amount: string | null;
+  /** Registry symbol, when the gateway knows the mint. */
+  token: string | null;
+  splToken: string | null;
+  references: string[];
+  label: string | null;
+  message: string | null;
+  memo: string | null;
 };
 
 export type HandleResponse = {